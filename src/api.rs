@@ -0,0 +1,492 @@
+use anyhow::Result;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Json, Router};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::RwLock;
+
+use crate::database::Database;
+use crate::dex::circuit_breaker::BreakerState;
+use crate::dex::DexKey;
+use crate::pairs::PairManager;
+use crate::table_display::{PairDisplay, PairDisplayConverter};
+use crate::types::ArbitrageOpportunity;
+
+/// `--serve` 未指定地址时的监听地址
+pub const DEFAULT_API_ADDR: &str = "127.0.0.1:8080";
+
+/// WebSocket 推送通道缓存的批次数，客户端落后超过该数量时丢弃旧批次
+const WS_BROADCAST_CAPACITY: usize = 16;
+
+/// `GET /pairs` 未指定 limit 时返回的交易对数量
+const DEFAULT_PAIRS_LIMIT: usize = 100;
+
+/// `GET /pairs` 单次最多返回的交易对数量
+const MAX_PAIRS_LIMIT: usize = 10_000;
+
+/// 启动时从数据库载入的最近套利机会数量，首次扫描完成前由 `/opportunities` 返回
+const SEED_OPPORTUNITIES_LIMIT: usize = 100;
+
+/// `GET /pairs/{id}/prices` 未指定 from 时返回的时间范围
+const DEFAULT_PRICE_HISTORY_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+
+/// WebSocket 保活 ping 的间隔
+const WS_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// HTTP 接口读取的最新结果，由监控器在每次扫描后写入
+pub struct ApiState {
+    opportunities: RwLock<Vec<ArbitrageOpportunity>>,
+    /// `/pairs` 每次请求时从数据库读取，保证返回的是最新保存的交易对
+    database: Option<Database>,
+    /// 最近一次扫描后各 DEX 提供者的熔断状态
+    dex_health: RwLock<Vec<DexHealth>>,
+    /// 每批新机会序列化后的 JSON，推送给所有 WebSocket 客户端
+    updates: broadcast::Sender<String>,
+}
+
+/// `GET /health` 中单个 DEX 提供者的熔断状态
+#[derive(Debug, Clone, Serialize)]
+pub struct DexHealth {
+    pub chain_id: u64,
+    pub dex: String,
+    pub state: BreakerState,
+}
+
+impl Default for ApiState {
+    fn default() -> Self {
+        Self {
+            opportunities: RwLock::default(),
+            database: None,
+            dex_health: RwLock::default(),
+            updates: broadcast::channel(WS_BROADCAST_CAPACITY).0,
+        }
+    }
+}
+
+pub type SharedApiState = Arc<ApiState>;
+
+impl ApiState {
+    /// 替换各 DEX 提供者的熔断状态，按链 ID 和名称排序
+    pub async fn publish_circuit_states(&self, states: HashMap<DexKey, BreakerState>) {
+        let mut dex_health: Vec<DexHealth> = states
+            .into_iter()
+            .map(|((chain_id, dex), state)| DexHealth { chain_id, dex, state })
+            .collect();
+        dex_health.sort_by(|a, b| (a.chain_id, &a.dex).cmp(&(b.chain_id, &b.dex)));
+        *self.dex_health.write().await = dex_health;
+    }
+
+    /// `/pairs` 从该数据库读取交易对；`/opportunities` 在首次扫描完成前返回数据库中最近保存的套利机会（按时间倒序）
+    pub fn with_database(database: Database) -> Self {
+        let opportunities = database.load_recent_opportunities(SEED_OPPORTUNITIES_LIMIT).unwrap_or_else(|e| {
+            warn!("载入历史套利机会失败: {}", e);
+            Vec::new()
+        });
+        Self {
+            opportunities: RwLock::new(opportunities),
+            database: Some(database),
+            ..Self::default()
+        }
+    }
+
+    /// 用最新一次扫描的套利机会替换当前结果，并推送给已连接的 WebSocket 客户端
+    pub async fn publish_opportunities(&self, opportunities: Vec<ArbitrageOpportunity>) {
+        if self.updates.receiver_count() > 0 {
+            match serde_json::to_string(&opportunities) {
+                // 没有客户端时发送失败，忽略即可
+                Ok(batch) => drop(self.updates.send(batch)),
+                Err(e) => warn!("序列化套利机会失败: {}", e),
+            }
+        }
+        *self.opportunities.write().await = opportunities;
+    }
+}
+
+/// `GET /opportunities` 的查询参数
+#[derive(Debug, Default, Deserialize)]
+struct OpportunityQuery {
+    /// 最低利润率（百分比）
+    min_profit: Option<f64>,
+    /// 最多返回的数量
+    limit: Option<usize>,
+}
+
+/// `GET /pairs` 的查询参数
+#[derive(Debug, Default, Deserialize)]
+struct PairQuery {
+    /// 最多返回的数量，默认 DEFAULT_PAIRS_LIMIT，上限 MAX_PAIRS_LIMIT
+    limit: Option<usize>,
+    /// 只返回包含该代币地址的交易对
+    token: Option<String>,
+}
+
+/// `GET /pairs/{id}/prices` 的查询参数，均为 Unix 毫秒时间戳
+#[derive(Debug, Default, Deserialize)]
+struct PriceHistoryQuery {
+    /// 起始时间，默认 `to` 之前 24 小时
+    from: Option<i64>,
+    /// 结束时间，默认当前时间
+    to: Option<i64>,
+}
+
+/// 价格历史中的一条记录
+#[derive(Debug, Clone, Serialize)]
+struct PricePoint {
+    timestamp: DateTime<Utc>,
+    price: BigDecimal,
+}
+
+/// 构建 HTTP 路由
+pub fn router(state: SharedApiState) -> Router {
+    Router::new()
+        .route("/opportunities", get(get_opportunities))
+        .route("/pairs", get(get_pairs))
+        .route("/pairs/{id}/prices", get(get_price_history))
+        .route("/health", get(get_health))
+        .route("/ws", get(ws_upgrade))
+        .with_state(state)
+}
+
+/// 在指定地址启动 HTTP 接口服务，返回实际监听地址
+pub async fn serve(addr: &str, state: SharedApiState) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    info!("🌐 API 服务已启动: http://{}", local_addr);
+
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router(state)).await {
+            error!("API 服务异常退出: {}", e);
+        }
+    });
+
+    Ok(local_addr)
+}
+
+async fn get_opportunities(
+    State(state): State<SharedApiState>,
+    Query(query): Query<OpportunityQuery>,
+) -> Json<Vec<ArbitrageOpportunity>> {
+    let opportunities = state.opportunities.read().await;
+    let filtered = opportunities
+        .iter()
+        .filter(|opportunity| query.min_profit.is_none_or(|min| opportunity.profit_percentage >= min))
+        .take(query.limit.unwrap_or(usize::MAX))
+        .cloned()
+        .collect();
+    Json(filtered)
+}
+
+async fn get_pairs(
+    State(state): State<SharedApiState>,
+    Query(query): Query<PairQuery>,
+) -> Result<Json<Vec<PairDisplay>>, (StatusCode, String)> {
+    let Some(database) = &state.database else {
+        return Ok(Json(Vec::new()));
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAIRS_LIMIT).clamp(1, MAX_PAIRS_LIMIT);
+    let pair_manager = PairManager::new(database);
+    let pairs = match &query.token {
+        Some(token) => pair_manager.find_pairs_by_token(token).map(|mut pairs| {
+            pairs.truncate(limit);
+            pairs
+        }),
+        None => pair_manager.load_pairs_by_filter(None, None, Some(limit)),
+    };
+    pairs
+        .and_then(PairDisplayConverter::convert_owned)
+        .map(Json)
+        .map_err(|e| {
+            error!("读取交易对失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+async fn get_price_history(
+    State(state): State<SharedApiState>,
+    Path(pair_id): Path<String>,
+    Query(query): Query<PriceHistoryQuery>,
+) -> Result<Json<Vec<PricePoint>>, (StatusCode, String)> {
+    let Some(database) = &state.database else {
+        return Ok(Json(Vec::new()));
+    };
+    let timestamp = |millis: i64| {
+        DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("无效的时间戳: {}", millis)))
+    };
+    let to = query.to.map(timestamp).transpose()?.unwrap_or_else(Utc::now);
+    let from = query.from.map(timestamp).transpose()?.unwrap_or(to - DEFAULT_PRICE_HISTORY_WINDOW);
+
+    database
+        .load_price_history(&pair_id, from, to)
+        .map(|history| Json(history.into_iter().map(|(timestamp, price)| PricePoint { timestamp, price }).collect()))
+        .map_err(|e| {
+            error!("读取价格历史失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+async fn get_health(State(state): State<SharedApiState>) -> Json<Vec<DexHealth>> {
+    Json(state.dex_health.read().await.clone())
+}
+
+/// 完成 WebSocket 握手，升级后持续推送新的套利机会批次
+async fn ws_upgrade(State(state): State<SharedApiState>, ws: WebSocketUpgrade) -> Response {
+    // 在返回 101 之前订阅，保证握手完成后发布的批次不会丢失
+    let updates = state.updates.subscribe();
+    ws.on_failed_upgrade(|e| warn!("WebSocket 升级失败: {}", e))
+        .on_upgrade(move |socket| stream_updates(socket, updates))
+}
+
+/// 向单个客户端推送批次并定期发送 ping；客户端落后时跳过旧批次而不阻塞扫描
+async fn stream_updates(mut socket: WebSocket, mut updates: broadcast::Receiver<String>) {
+    let mut keepalive = tokio::time::interval(WS_PING_INTERVAL);
+    keepalive.tick().await;
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(batch) => {
+                    if socket.send(Message::Text(batch.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => warn!("WebSocket 客户端处理过慢，已丢弃 {} 批套利机会", skipped),
+                Err(RecvError::Closed) => break,
+            },
+            _ = keepalive.tick() => {
+                if socket.send(Message::Ping(Default::default())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    debug!("WebSocket 客户端已断开");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Token, TokenPair};
+    use futures_util::StreamExt;
+    use std::str::FromStr;
+
+    fn opportunity(id: &str, profit_percentage: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            id: id.to_string(),
+            token_pair: TokenPair {
+                token_a: Token::new("0xa".to_string(), "WETH".to_string(), "Wrapped Ether".to_string(), 18, 1),
+                token_b: Token::new("0xb".to_string(), "USDT".to_string(), "Tether USD".to_string(), 6, 1),
+            },
+            buy_dex: "uniswap_v2".to_string(),
+            sell_dex: "sushiswap".to_string(),
+            buy_price: BigDecimal::from_str("2000.123456789012345678").unwrap(),
+            sell_price: BigDecimal::from(2010),
+            profit_percentage,
+            estimated_profit: BigDecimal::from(10),
+            liquidity: BigDecimal::from(1_000_000),
+            gas_cost_estimate: BigDecimal::from_str("0.01").unwrap(),
+            timestamp: Utc::now(),
+            confidence_score: 0.8,
+            sandwich_risk: 0.02,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opportunities_endpoint() {
+        let state = SharedApiState::default();
+        state
+            .publish_opportunities(vec![opportunity("a", 2.0), opportunity("b", 1.5), opportunity("c", 0.5)])
+            .await;
+        let addr = serve("127.0.0.1:0", state).await.unwrap();
+
+        let get = |query: &'static str| async move {
+            reqwest::get(format!("http://{}/opportunities{}", addr, query))
+                .await
+                .unwrap()
+                .json::<Vec<serde_json::Value>>()
+                .await
+                .unwrap()
+        };
+
+        assert_eq!(get("").await.len(), 3);
+        let limited = get("?limit=2").await;
+        assert_eq!(limited.len(), 2);
+        assert_eq!(limited[0]["buy_price"], "2000.123456789012345678");
+        let filtered = get("?min_profit=1&limit=5").await;
+        let ids: Vec<&str> = filtered.iter().map(|o| o["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    fn pair(id: &str) -> crate::thegraph::PairData {
+        let token = |id: &str| crate::thegraph::TokenInfo {
+            id: id.to_string(),
+            symbol: id.to_uppercase(),
+            name: id.to_string(),
+            decimals: "18".to_string(),
+        };
+        crate::thegraph::PairData {
+            id: id.to_string(),
+            network: "ethereum".to_string(),
+            dex_type: crate::config::dex_types::UNISWAP_V2.to_string(),
+            protocol_type: crate::config::protocol_types::AMM_V2.to_string(),
+            token0: token("0xa"),
+            token1: token("0xb"),
+            volume_usd: "1000".to_string(),
+            reserve_usd: "1000".to_string(),
+            tx_count: "10".to_string(),
+            reserve0: "1000".to_string(),
+            reserve1: "2000".to_string(),
+            fee_tier: "3000".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opportunities_seeded_from_database() {
+        let database = Database::new(None).unwrap();
+        database.save_opportunities(&[opportunity("stored", 1.0)]).unwrap();
+        let state = Arc::new(ApiState::with_database(database));
+        let addr = serve("127.0.0.1:0", state.clone()).await.unwrap();
+
+        let get = || async move {
+            reqwest::get(format!("http://{}/opportunities", addr))
+                .await
+                .unwrap()
+                .json::<Vec<serde_json::Value>>()
+                .await
+                .unwrap()
+        };
+        // 首次扫描前返回已保存的机会，扫描结果发布后被替换
+        let seeded = get().await;
+        assert_eq!(seeded.len(), 1);
+        assert_eq!(seeded[0]["id"], "stored");
+
+        state.publish_opportunities(vec![opportunity("a", 2.0), opportunity("b", 1.5)]).await;
+        assert_eq!(get().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pairs_endpoint_reads_database() {
+        let database = Database::new(None).unwrap();
+        database.save_pairs(&[pair("0x1")]).unwrap();
+        let addr = serve("127.0.0.1:0", Arc::new(ApiState::with_database(database.clone()))).await.unwrap();
+
+        let get = |query: &'static str| async move {
+            reqwest::get(format!("http://{}/pairs{}", addr, query))
+                .await
+                .unwrap()
+                .json::<Vec<serde_json::Value>>()
+                .await
+                .unwrap()
+        };
+        assert_eq!(get("").await.len(), 1);
+
+        // 启动后保存的交易对在下一次请求中可见，limit 独立于扫描的显示数量
+        database.save_pairs(&[pair("0x2"), pair("0x3")]).unwrap();
+        assert_eq!(get("").await.len(), 3);
+        assert_eq!(get("?limit=2").await.len(), 2);
+
+        // 按代币过滤时走 token_pairs 索引，地址不区分大小写
+        let mut other = pair("0x4");
+        other.token1.id = "0xc".to_string();
+        database.save_pairs(&[other]).unwrap();
+        assert_eq!(get("?token=0xC").await.len(), 1);
+        assert_eq!(get("?token=0xa").await.len(), 4);
+        assert_eq!(get("?token=0xa&limit=2").await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_price_history_endpoint() {
+        let database = Database::new(None).unwrap();
+        let at = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        database.record_price("0xabc", &BigDecimal::from_str("2000.5").unwrap(), at(0)).unwrap();
+        database.record_price("0xabc", &BigDecimal::from(2010), at(60)).unwrap();
+        let addr = serve("127.0.0.1:0", Arc::new(ApiState::with_database(database))).await.unwrap();
+
+        let get = |query: String| async move {
+            reqwest::get(format!("http://{}/pairs/0xABC/prices{}", addr, query)).await.unwrap()
+        };
+        let history: Vec<serde_json::Value> =
+            get(format!("?from={}&to={}", at(0).timestamp_millis(), at(60).timestamp_millis()))
+                .await
+                .json()
+                .await
+                .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0]["price"], "2000.5");
+
+        // 默认只返回最近 24 小时，无效时间戳返回 400
+        assert!(get(String::new()).await.json::<Vec<serde_json::Value>>().await.unwrap().is_empty());
+        assert_eq!(get(format!("?to={}", i64::MAX)).await.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint() {
+        let state = SharedApiState::default();
+        state
+            .publish_circuit_states(HashMap::from([
+                ((56, "pancakeswap".to_string()), BreakerState::Closed),
+                ((1, "sushiswap".to_string()), BreakerState::Open),
+                ((1, "uniswap_v3".to_string()), BreakerState::HalfOpen),
+            ]))
+            .await;
+        let addr = serve("127.0.0.1:0", state).await.unwrap();
+
+        let health: Vec<serde_json::Value> =
+            reqwest::get(format!("http://{}/health", addr)).await.unwrap().json().await.unwrap();
+        assert_eq!(
+            health,
+            vec![
+                serde_json::json!({ "chain_id": 1, "dex": "sushiswap", "state": "open" }),
+                serde_json::json!({ "chain_id": 1, "dex": "uniswap_v3", "state": "half_open" }),
+                serde_json::json!({ "chain_id": 56, "dex": "pancakeswap", "state": "closed" }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ws_pushes_published_batches() {
+        let state = SharedApiState::default();
+        let addr = serve("127.0.0.1:0", state.clone()).await.unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+        state.publish_opportunities(vec![opportunity("a", 2.0)]).await;
+
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let batch: Vec<serde_json::Value> = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0]["id"], "a");
+        assert_eq!(batch[0]["buy_price"], "2000.123456789012345678");
+
+        // 普通 HTTP 请求不能升级
+        let status = reqwest::get(format!("http://{}/ws", addr)).await.unwrap().status();
+        assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+    }
+}
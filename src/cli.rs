@@ -1,39 +1,80 @@
 use anyhow::Result;
 use clap::{Arg, Command, ArgMatches};
-use log::{error, info};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use log::{error, info, warn};
+use tokio::sync::watch;
 
+use crate::api::{self, ApiState, DEFAULT_API_ADDR};
 use crate::config::Config;
 use crate::database::Database;
-use crate::monitor::ArbitrageMonitor;
+use crate::monitor::{ArbitrageMonitor, OutputFormat};
 use crate::pairs::PairManager;
+use crate::price_calculator::PriceCalculator;
 use crate::realtime_monitor::RealTimeMonitor;
 use crate::thegraph::TheGraphClient;
-use crate::token::TokenManager;
+use crate::token::{TokenManager, DEFAULT_CHAIN, TOKEN_MARKET_DATA_TTL};
 
 // 命令行参数常量
 const UPDATE_TOKENS_ARG: &str = "update";
 const UPDATE_PAIRS_ARG: &str = "update-pairs";
 const MONITOR_ARG: &str = "monitor";
+const COUNT_ARG: &str = "count";
+const INTERVAL_ARG: &str = "interval";
+const FORMAT_ARG: &str = "format";
+const SCAN_ARG: &str = "scan";
+const START_TOKEN_ARG: &str = "start-token";
+const DB_ARG: &str = "db";
+const SERVE_ARG: &str = "serve";
+const CHAIN_ARG: &str = "chain";
+const PRUNE_ARG: &str = "prune";
+const V2_ARG: &str = "v2";
+const DEMO_ARG: &str = "demo";
+const WATCH_ARG: &str = "watch";
+const UNWATCH_ARG: &str = "unwatch";
+
+/// 未指定 --db 和 DATABASE_PATH 时使用的数据库路径
+const DEFAULT_DB_PATH: &str = "data/tokens.db";
+
+// 各命令的 count 默认值
+const MONITOR_DEFAULT_COUNT: usize = 100;
+const SCAN_DEFAULT_COUNT: usize = 20;
 
 /// CLI应用程序结构
 pub struct CliApp {
     config: Config,
+    /// CONFIG_PATH 指定的配置文件，扫描模式下监听其修改
+    config_path: Option<String>,
     database: Database,
 }
 
 impl CliApp {
-    /// 创建新的CLI应用程序实例
-    pub async fn new() -> Result<Self> {
-        // 加载配置
-        let config = Config::load()?;
+    /// 创建新的CLI应用程序实例，使用 `db_path` 处的数据库文件（父目录不存在时自动创建）
+    pub async fn new(db_path: &str) -> Result<Self> {
+        // 加载配置：设置了 CONFIG_PATH 时从 TOML/JSON 文件读取，否则使用默认配置
+        let config_path = std::env::var("CONFIG_PATH").ok();
+        let config = match &config_path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::load()?,
+        };
         info!("配置加载完成");
 
+        // PRICE_DECIMAL_SCALE 控制价格除法保留的小数位数
+        if let Some(scale) = std::env::var("PRICE_DECIMAL_SCALE").ok().and_then(|v| v.parse().ok()) {
+            PriceCalculator::set_scale(scale);
+        }
+
         // 初始化数据库
         info!("初始化数据库...");
-        let database = Database::new(Some("data/tokens.db"))?;
-        info!("数据库初始化完成");
-
-        Ok(Self { config, database })
+        // DATABASE_POOL_SIZE 覆盖默认的连接池大小
+        let database = match std::env::var("DATABASE_POOL_SIZE").ok().and_then(|v| v.parse().ok()) {
+            Some(pool_size) => Database::with_pool_size(Some(db_path), pool_size)?,
+            None => Database::new(Some(db_path))?,
+        };
+        info!("数据库初始化完成: {}", db_path);
+
+        Ok(Self { config, config_path, database })
     }
 
     /// 构建命令行参数解析器
@@ -41,6 +82,13 @@ impl CliApp {
         Command::new("arbitrage-spy")
             .version("1.0")
             .about("区块链套利监控系统")
+            .arg(
+                Arg::new(DB_ARG)
+                    .long(DB_ARG)
+                    .help("数据库文件路径 (未指定时使用 DATABASE_PATH 环境变量，默认: data/tokens.db)")
+                    .value_name("PATH")
+                    .global(true),
+            )
             .arg(
                 Arg::new(UPDATE_TOKENS_ARG)
                     .long(UPDATE_TOKENS_ARG)
@@ -53,6 +101,27 @@ impl CliApp {
                     .help("更新交易对数据")
                     .action(clap::ArgAction::SetTrue),
             )
+            .arg(
+                Arg::new(CHAIN_ARG)
+                    .long(CHAIN_ARG)
+                    .help("更新交易对时使用的链，即 token 的 CoinGecko 平台 ID，如 polygon-pos (默认: ethereum)；没有内置子图的链需设置 UNISWAP_V3_SUBGRAPH_ID_<CHAIN>")
+                    .value_name("CHAIN")
+                    .default_value(DEFAULT_CHAIN),
+            )
+            .arg(
+                Arg::new(PRUNE_ARG)
+                    .long(PRUNE_ARG)
+                    .help("更新交易对成功后删除该链上本次未刷新到的交易对，其他链的交易对保留")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with_all([MONITOR_ARG, SCAN_ARG, SERVE_ARG]),
+            )
+            .arg(
+                Arg::new(V2_ARG)
+                    .long(V2_ARG)
+                    .help("更新交易对时同时按 token 查询 Uniswap V2 交易对（按 STABLECOIN_FILTER_MODE 过滤稳定币对）")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with_all([MONITOR_ARG, SCAN_ARG, SERVE_ARG]),
+            )
             .arg(
                 Arg::new(MONITOR_ARG)
                     .long(MONITOR_ARG)
@@ -61,69 +130,166 @@ impl CliApp {
                     .action(clap::ArgAction::SetTrue),
             )
             .arg(
-                Arg::new("count")
-                    .long("count")
+                Arg::new(COUNT_ARG)
+                    .long(COUNT_ARG)
                     .short('c')
-                    .help("显示的交易对数量 (默认: 100)")
+                    .help("显示的数量，必须大于 0 (监控模式: 交易对数量，默认 100；扫描模式: 套利机会数量，默认 20)")
                     .value_name("NUMBER")
-                    .default_value("100")
-                    .requires(MONITOR_ARG),
+                    .value_parser(clap::value_parser!(u64).range(1..))
+                    .conflicts_with_all([UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG]),
             )
             .arg(
-                Arg::new("interval")
-                    .long("interval")
+                Arg::new(INTERVAL_ARG)
+                    .long(INTERVAL_ARG)
                     .short('i')
-                    .help("更新间隔秒数 (默认: 1)")
+                    .help("扫描间隔秒数 (默认使用配置的扫描间隔，0 表示只扫描一次)；监控模式由链上事件驱动，不接受该参数")
                     .value_name("SECONDS")
-                    .default_value("1")
+                    .value_parser(clap::value_parser!(u64))
+                    .conflicts_with_all([MONITOR_ARG, UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG]),
+            )
+            .arg(
+                Arg::new(FORMAT_ARG)
+                    .long(FORMAT_ARG)
+                    .help("扫描结果输出格式: table 为终端表格，json 为每行一个套利机会的 NDJSON (默认: table)")
+                    .value_name("FORMAT")
+                    .value_parser(["table", "json"])
+                    .default_value("table")
+                    .conflicts_with_all([MONITOR_ARG, UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG]),
+            )
+            .arg(
+                Arg::new(SCAN_ARG)
+                    .long(SCAN_ARG)
+                    .help("只扫描一次，打印前 --count 个套利机会后退出")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with_all([MONITOR_ARG, UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG, INTERVAL_ARG]),
+            )
+            .arg(
+                Arg::new(START_TOKEN_ARG)
+                    .long(START_TOKEN_ARG)
+                    .help("只显示包含该代币符号的套利机会")
+                    .value_name("SYMBOL")
+                    .conflicts_with_all([MONITOR_ARG, UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG]),
+            )
+            .arg(
+                Arg::new(SERVE_ARG)
+                    .long(SERVE_ARG)
+                    .help("启动 HTTP 接口，提供 /opportunities、/pairs、/pairs/{id}/prices 和 /health (默认地址: 127.0.0.1:8080)")
+                    .value_name("ADDR")
+                    .num_args(0..=1)
+                    .default_missing_value(DEFAULT_API_ADDR)
+                    .conflicts_with_all([MONITOR_ARG, UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG]),
+            )
+            .arg(
+                Arg::new(DEMO_ARG)
+                    .long(DEMO_ARG)
+                    .help("没有发现套利机会时在表格中显示演示数据")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with_all([MONITOR_ARG, UPDATE_TOKENS_ARG, UPDATE_PAIRS_ARG, SCAN_ARG, START_TOKEN_ARG, FORMAT_ARG]),
+            )
+            .arg(
+                Arg::new(WATCH_ARG)
+                    .long(WATCH_ARG)
+                    .help("实时监控时额外监听该交易对地址（需已通过 --update-pairs 保存），可重复指定，重启后继续生效")
+                    .value_name("ADDRESS")
+                    .action(clap::ArgAction::Append)
+                    .requires(MONITOR_ARG),
+            )
+            .arg(
+                Arg::new(UNWATCH_ARG)
+                    .long(UNWATCH_ARG)
+                    .help("实时监控时停止监听该合约地址并从保存的监听列表中移除，可重复指定")
+                    .value_name("ADDRESS")
+                    .action(clap::ArgAction::Append)
                     .requires(MONITOR_ARG),
             )
-
     }
 
     /// 运行CLI应用程序
     pub async fn run(&self, matches: ArgMatches) -> Result<()> {
+        let chain = matches.get_one::<String>(CHAIN_ARG).map(String::as_str).unwrap_or(DEFAULT_CHAIN);
+        let prune = matches.get_flag(PRUNE_ARG);
+        let include_v2 = matches.get_flag(V2_ARG);
+
         // 检查是否只需要更新 token
         if matches.get_flag(UPDATE_TOKENS_ARG) {
             info!("执行 token 更新命令...");
-            self.update_data().await?;
+            self.update_data(chain, prune, include_v2).await?;
             return Ok(());
         }
 
         // 检查是否只需要更新交易对
         if matches.get_flag(UPDATE_PAIRS_ARG) {
             info!("执行交易对更新命令...");
-            self.update_pairs().await?;
+            self.update_pairs(&Self::graph_client_for(chain, include_v2)?, prune, include_v2).await?;
             return Ok(());
         }
 
         // 检查是否启动实时监控模式
         if matches.get_flag(MONITOR_ARG) {
-            let count: usize = matches.get_one::<String>("count")
-                .unwrap()
-                .parse()
-                .unwrap_or(100);
-            
+            let count = Self::count(&matches, MONITOR_DEFAULT_COUNT);
+
+            let addresses = |arg: &str| matches.get_many::<String>(arg).into_iter().flatten().cloned().collect();
             info!("启动实时监控模式...");
-            self.start_realtime_monitor(count).await?;
+            self.start_realtime_monitor(count, addresses(WATCH_ARG), addresses(UNWATCH_ARG)).await?;
             return Ok(());
         }
 
 
 
-        // 正常启动模式 - 初始化完整的监控系统
+        // 正常启动模式 - 初始化完整的监控系统；--scan 时只扫描一次
+        let (count, interval) = Self::count_and_interval(
+            &matches,
+            SCAN_DEFAULT_COUNT,
+            self.config.monitoring.scan_interval_seconds,
+        );
+        let interval = if matches.get_flag(SCAN_ARG) { 0 } else { interval };
+        let output_format = match matches.get_one::<String>(FORMAT_ARG).map(String::as_str) {
+            Some("json") => OutputFormat::Json,
+            _ => OutputFormat::Table,
+        };
+        let start_token = matches.get_one::<String>(START_TOKEN_ARG).cloned();
+        let serve_addr = matches.get_one::<String>(SERVE_ARG).cloned();
+        let demo = matches.get_flag(DEMO_ARG);
         info!("启动完整监控系统...");
-        self.start_monitoring().await?;
+        self.start_monitoring(count, interval, output_format, start_token, serve_addr, demo).await?;
 
         Ok(())
     }
 
+    /// 解析数据库路径：--db 优先，其次是 DATABASE_PATH 环境变量，最后使用默认路径
+    pub fn db_path(matches: &ArgMatches) -> String {
+        matches
+            .get_one::<String>(DB_ARG)
+            .cloned()
+            .or_else(|| std::env::var("DATABASE_PATH").ok())
+            .unwrap_or_else(|| DEFAULT_DB_PATH.to_string())
+    }
+
+    /// 读取 count 参数，未指定时使用当前命令的默认值
+    fn count(matches: &ArgMatches, default_count: usize) -> usize {
+        matches
+            .get_one::<u64>(COUNT_ARG)
+            .map(|count| *count as usize)
+            .unwrap_or(default_count)
+    }
+
+    /// 读取 count / interval 参数，未指定时使用当前命令的默认值
+    fn count_and_interval(matches: &ArgMatches, default_count: usize, default_interval_secs: u64) -> (usize, u64) {
+        let count = Self::count(matches, default_count);
+        let interval = matches
+            .get_one::<u64>(INTERVAL_ARG)
+            .copied()
+            .unwrap_or(default_interval_secs);
+        (count, interval)
+    }
+
     /// 启动实时监控模式
-    async fn start_realtime_monitor(&self, count: usize) -> Result<()> {
+    async fn start_realtime_monitor(&self, count: usize, watch: Vec<String>, unwatch: Vec<String>) -> Result<()> {
         println!("正在启动实时监控...");
         
         // 创建实时监控器
-        let monitor = RealTimeMonitor::new(self.config.clone(), self.database.clone()).await?;
+        let mut monitor = RealTimeMonitor::new(self.config.clone(), self.database.clone()).await?;
+        monitor.set_watch_changes(watch, unwatch);
         
         // 开始监控
         monitor.start_monitoring(count).await?;
@@ -134,7 +300,15 @@ impl CliApp {
 
 
     /// 启动完整的监控系统
-    async fn start_monitoring(&self) -> Result<()> {
+    async fn start_monitoring(
+        &self,
+        count: usize,
+        interval_secs: u64,
+        output_format: OutputFormat,
+        start_token: Option<String>,
+        serve_addr: Option<String>,
+        demo: bool,
+    ) -> Result<()> {
         // 初始化 Token 管理器
         let token_manager = TokenManager::new(&self.database);
 
@@ -146,6 +320,29 @@ impl CliApp {
         // 初始化套利监控器
         info!("初始化套利监控器...");
         let mut monitor = ArbitrageMonitor::new(self.config.clone()).await?;
+        monitor.set_display_limit(count);
+        monitor.set_output_format(output_format);
+        monitor.set_demo(demo);
+        monitor.set_database(self.database.clone());
+        if let Some(symbol) = start_token {
+            monitor.set_start_token(symbol);
+        }
+        if let Some(addr) = serve_addr {
+            let api_state = Arc::new(ApiState::with_database(self.database.clone()));
+            api::serve(&addr, api_state.clone()).await?;
+            monitor.set_api_state(api_state);
+        }
+        monitor.set_scan_interval(Duration::from_secs(interval_secs));
+
+        // 监听配置文件，修改后的配置在下一次扫描前生效，watcher 需在扫描期间保持存活
+        let _config_watcher = match &self.config_path {
+            Some(path) => {
+                let (sender, receiver) = watch::channel(self.config.clone());
+                monitor.set_config_updates(receiver);
+                Some(Config::watch(path, move |config| drop(sender.send(config)))?)
+            }
+            None => None,
+        };
         monitor.start_scan().await;
 
         // 开始监控
@@ -156,9 +353,11 @@ impl CliApp {
         Ok(())
     }
 
-    async fn update_data(&self) -> Result<()> {
+    async fn update_data(&self, chain: &str, prune: bool, include_v2: bool) -> Result<()> {
+        // 先确认该链有可用的子图，避免更新完 token 后才失败
+        let graph_client = Self::graph_client_for(chain, include_v2)?;
         self.update_tokens().await?;
-        self.update_pairs().await?;
+        self.update_pairs(&graph_client, prune, include_v2).await?;
         Ok(())
     }
 
@@ -169,14 +368,11 @@ impl CliApp {
         // 初始化 Token 管理器
         let token_manager = TokenManager::new(&self.database);
 
-        // 获取 token 列表 (限制为前100个以避免长时间等待)
+        // 增量更新：只为新增或市场数据已过期的 token 请求市场数据，结果直接写入数据库
         info!("从 CoinGecko API 获取 token 列表...");
-        let token_list = token_manager.fetch_tokens(None).await?;
-        info!("获取到 {} 个 token", token_list.tokens.len());
-
-        // 保存到数据库
-        self.database.save_tokens(&token_list.tokens)?;
-        info!("Token 数据已保存到数据库");
+        let known_ids = self.database.load_fresh_token_ids(TOKEN_MARKET_DATA_TTL)?;
+        let token_list = token_manager.fetch_tokens_since(&known_ids).await?;
+        info!("获取到 {} 个 token（{} 个无需刷新）", token_list.tokens.len(), known_ids.len());
 
         // 显示更新后的统计
         let (total_tokens, last_update) = self.database.get_stats()?;
@@ -188,89 +384,216 @@ impl CliApp {
         Ok(())
     }
 
-    /// 独立的 pairs 更新功能
-    async fn update_pairs(&self) -> Result<()> {
-        info!("开始更新 pairs 数据...");
+    /// 创建查询 `chain` 的 TheGraph 客户端；该链没有已知子图（或要求 V2 但没有 V2 子图）时返回错误
+    fn graph_client_for(chain: &str, include_v2: bool) -> Result<TheGraphClient> {
+        let graph_client = TheGraphClient::for_chain(chain)?;
+        if include_v2 && !graph_client.supports_uniswap_v2() {
+            return Err(anyhow::anyhow!(
+                "链 {} 没有已知的 Uniswap V2 子图，请设置 UNISWAP_V2_SUBGRAPH_ID_{} 或去掉 --{}",
+                chain,
+                chain.to_uppercase().replace('-', "_"),
+                V2_ARG
+            ));
+        }
+        Ok(graph_client)
+    }
+
+    /// 独立的 pairs 更新功能，按 token 在 `graph_client` 所查询的链上的地址查询交易对；`include_v2` 为真时同时查询 Uniswap V2 交易对，
+    /// `prune` 为真时在所有查询都成功且保存成功后删除该链上本次未刷新到的交易对。
+    /// 设置 `CANONICALIZE_PAIRS=false` 时按数据源原样保存 token0/token1 顺序
+    async fn update_pairs(&self, graph_client: &TheGraphClient, prune: bool, include_v2: bool) -> Result<()> {
+        let chain = graph_client.network();
+        info!("开始更新 {} 上的 pairs 数据...", chain);
+        let refresh_started = chrono::Utc::now();
 
-        // 通过遍历 token 表中的每个 token，查询 TheGraph 相关的交易对来更新数据
+        // 收集 token 表中各 token 在该链上的地址，一次性从 TheGraph 批量查询相关交易对
         info!("遍历 token 表，从 TheGraph 获取相关交易对...");
         let token_manager = TokenManager::new(&self.database);
-        let pair_manager = PairManager::new(&self.database);
-        let graph_client = TheGraphClient::new();
-        
+        let mut pair_manager = PairManager::new(&self.database);
+        if let Some(canonicalize) = std::env::var("CANONICALIZE_PAIRS").ok().and_then(|v| v.parse().ok()) {
+            pair_manager.set_canonicalize(canonicalize);
+        }
+
         // 只获取 market_cap_rank 前100的币种
-        match token_manager.get_tokens(Some(100)).await {
-            Ok(token_list) => {
-                info!("从数据库获取到 {} 个 token", token_list.tokens.len());
-                let mut total_pairs_saved = 0;
-                
-                for (index, token) in token_list.tokens.iter().enumerate() {
-                    // 需要从 token 的 platforms 中获取以太坊地址
-                    if let Some(ethereum_address) = token.platforms.get("ethereum").and_then(|addr| addr.as_ref()) {
-                        info!("[{}/{}] 正在查询 token {} ({}) 的相关交易对...", 
-                             index + 1, token_list.tokens.len(), token.symbol, ethereum_address);
-                        
-                        let mut all_pairs = Vec::new();
-                             
-                        // 从 TheGraph 查询该 token 相关的 V2 交易对
-                        // match graph_client.get_pairs_by_token(ethereum_address, 25).await {
-                        //     Ok(v2_pairs) => {
-                        //         if !v2_pairs.is_empty() {
-                        //             info!("Token {} 从 Uniswap V2 获取到 {} 个相关交易对", 
-                        //                  token.symbol, v2_pairs.len());
-                        //             all_pairs.extend(v2_pairs);
-                        //         }
-                        //     }
-                        //     Err(e) => {
-                        //         error!("从 TheGraph 查询 token {} 的 V2 交易对失败: {}", token.symbol, e);
-                        //     }
-                        // }
-                        
-                        // 从 TheGraph 查询该 token 相关的 V3 pools
-                        match graph_client.get_v3_pools_by_token(ethereum_address, 25).await {
-                            Ok(v3_pairs) => {
-                                if !v3_pairs.is_empty() {
-                                    info!("Token {} 从 Uniswap V3 获取到 {} 个相关交易对", 
-                                         token.symbol, v3_pairs.len());
-                                    all_pairs.extend(v3_pairs);
-                                }
-                            }
-                            Err(e) => {
-                                error!("从 TheGraph 查询 token {} 的 V3 交易对失败: {}", token.symbol, e);
-                            }
-                        }
-                        
-                        // 保存所有交易对到数据库
-                        if !all_pairs.is_empty() {
-                            info!("Token {} 总共获取到 {} 个交易对 (V2 + V3)", 
-                                 token.symbol, all_pairs.len());
-                            
-                            if let Err(e) = pair_manager.save_pairs(&all_pairs) {
-                                error!("保存 token {} 的交易对到数据库失败: {}", token.symbol, e);
-                            } else {
-                                total_pairs_saved += all_pairs.len();
-                                info!("Token {} 的 {} 个交易对已保存到数据库", token.symbol, all_pairs.len());
-                            }
-                        } else {
-                            info!("Token {} 未找到相关交易对", token.symbol);
-                        }
-                        
-                        // 添加延迟以避免请求过于频繁
-                        if index < token_list.tokens.len() - 1 {
-                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                        }
-                    } else {
-                        info!("Token {} 没有以太坊地址，跳过", token.symbol);
+        let token_list = match token_manager.get_tokens(Some(100)).await {
+            Ok(token_list) => token_list,
+            Err(e) => {
+                error!("从数据库获取 token 列表失败: {}", e);
+                return Ok(());
+            }
+        };
+        info!("从数据库获取到 {} 个 token", token_list.tokens.len());
+
+        // 需要从 token 的 platforms 中获取该链上的地址
+        let mut token_addresses = Vec::new();
+        for token in &token_list.tokens {
+            match token.address_on(chain) {
+                Some(address) => token_addresses.push(address.to_string()),
+                None => info!("Token {} 没有 {} 地址，跳过", token.symbol, chain),
+            }
+        }
+
+        // 任一查询失败时本次刷新不完整，不执行 prune，避免误删仍然存在的交易对
+        let mut complete = true;
+        info!("正在批量查询 {} 个 token 的 Uniswap V3 交易对...", token_addresses.len());
+        let mut pairs = match graph_client.get_v3_pools_by_tokens(&token_addresses, 25).await {
+            Ok(v3_pairs) => v3_pairs,
+            Err(e) => {
+                error!("从 TheGraph 批量查询 V3 交易对失败: {}", e);
+                complete = false;
+                Vec::new()
+            }
+        };
+
+        if include_v2 {
+            info!("正在逐个查询 {} 个 token 的 Uniswap V2 交易对...", token_addresses.len());
+            let mut seen: HashSet<String> = pairs.iter().map(|pair| pair.id.clone()).collect();
+            for address in &token_addresses {
+                match graph_client.get_all_pairs_by_token(address).await {
+                    Ok(v2_pairs) => pairs.extend(v2_pairs.into_iter().filter(|pair| seen.insert(pair.id.clone()))),
+                    Err(e) => {
+                        error!("从 TheGraph 查询 {} 的 V2 交易对失败: {}", address, e);
+                        complete = false;
                     }
                 }
-                
-                info!("更新完成！总共保存了 {} 个交易对到数据库", total_pairs_saved);
             }
-            Err(e) => {
-                error!("从数据库获取 token 列表失败: {}", e);
+        }
+
+        if pairs.is_empty() {
+            info!("未找到相关交易对");
+            return Ok(());
+        }
+
+        if let Err(e) = pair_manager.save_pairs(&pairs) {
+            error!("保存交易对到数据库失败: {}", e);
+        } else {
+            info!("更新完成！总共保存了 {} 个交易对到数据库", pairs.len());
+            if prune && complete {
+                let removed = self.database.prune_pairs_older_than(chain, refresh_started)?;
+                info!("已删除 {} 个不再出现在数据源中的交易对", removed);
+            } else if prune {
+                warn!("部分查询失败，跳过删除未刷新的交易对");
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<ArgMatches, clap::Error> {
+        CliApp::build_cli().try_get_matches_from(std::iter::once("arbitrage-spy").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_count_and_interval_parsing() {
+        // 扫描模式（无 --monitor）同样接受 --count / --interval
+        let matches = parse(&["--count", "5", "--interval", "0"]).unwrap();
+        assert_eq!(CliApp::count_and_interval(&matches, SCAN_DEFAULT_COUNT, 10), (5, 0));
+
+        // 未指定时使用各命令的默认值
+        let matches = parse(&[]).unwrap();
+        assert_eq!(CliApp::count_and_interval(&matches, SCAN_DEFAULT_COUNT, 10), (SCAN_DEFAULT_COUNT, 10));
+        let matches = parse(&["--monitor"]).unwrap();
+        assert_eq!(CliApp::count(&matches, MONITOR_DEFAULT_COUNT), 100);
+
+        // 实时监控由事件驱动，不接受扫描间隔
+        assert!(parse(&["--monitor", "--interval", "5"]).is_err());
+
+        // 范围校验
+        assert!(parse(&["--count", "0"]).is_err());
+        assert!(parse(&["--interval", "-1"]).is_err());
+
+        // 更新命令不使用 count
+        assert!(parse(&["--update-pairs", "--count", "5"]).is_err());
+
+        // 输出格式只作用于扫描模式
+        let matches = parse(&["--format", "json"]).unwrap();
+        assert_eq!(matches.get_one::<String>(FORMAT_ARG).map(String::as_str), Some("json"));
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["--monitor", "--format", "json"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_db_path_argument() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("nested").join("test.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let matches = parse(&["--db", db_path, "--scan"]).unwrap();
+        assert_eq!(CliApp::db_path(&matches), db_path);
+
+        // 父目录不存在时自动创建，并在指定路径生成数据库文件
+        CliApp::new(&CliApp::db_path(&matches)).await.unwrap();
+        assert!(std::path::Path::new(db_path).exists());
+    }
+
+    #[test]
+    fn test_scan_parsing() {
+        let matches = parse(&["--scan", "--count", "3", "--start-token", "WETH"]).unwrap();
+        assert!(matches.get_flag(SCAN_ARG));
+        assert_eq!(matches.get_one::<String>(START_TOKEN_ARG).map(String::as_str), Some("WETH"));
+        assert_eq!(CliApp::count_and_interval(&matches, SCAN_DEFAULT_COUNT, 10).0, 3);
+
+        // 单次扫描不接受间隔，也不能与监控或更新命令同时使用
+        assert!(parse(&["--scan", "--interval", "5"]).is_err());
+        assert!(parse(&["--scan", "--monitor"]).is_err());
+        assert!(parse(&["--monitor", "--start-token", "WETH"]).is_err());
+    }
+
+    #[test]
+    fn test_serve_parsing() {
+        let matches = parse(&["--serve"]).unwrap();
+        assert_eq!(matches.get_one::<String>(SERVE_ARG).map(String::as_str), Some(DEFAULT_API_ADDR));
+
+        let matches = parse(&["--serve", "0.0.0.0:9000", "--scan"]).unwrap();
+        assert_eq!(matches.get_one::<String>(SERVE_ARG).map(String::as_str), Some("0.0.0.0:9000"));
+        assert!(parse(&["--serve", "--monitor"]).is_err());
+    }
+
+    #[test]
+    fn test_demo_parsing() {
+        assert!(parse(&["--demo"]).unwrap().get_flag(DEMO_ARG));
+        assert!(!parse(&[]).unwrap().get_flag(DEMO_ARG));
+        // 演示数据不进入单次扫描、按代币过滤或 JSON 输出
+        assert!(parse(&["--demo", "--scan"]).is_err());
+        assert!(parse(&["--demo", "--start-token", "WETH"]).is_err());
+        assert!(parse(&["--demo", "--format", "json"]).is_err());
+    }
+
+    #[test]
+    fn test_prune_parsing() {
+        assert!(parse(&["--update-pairs", "--prune"]).unwrap().get_flag(PRUNE_ARG));
+        assert!(!parse(&["--update-pairs"]).unwrap().get_flag(PRUNE_ARG));
+        assert!(parse(&["--prune", "--monitor"]).is_err());
+    }
+
+    #[test]
+    fn test_v2_parsing() {
+        assert!(parse(&["--update-pairs", "--v2", "--prune"]).unwrap().get_flag(V2_ARG));
+        assert!(!parse(&["--update"]).unwrap().get_flag(V2_ARG));
+        assert!(parse(&["--v2", "--scan"]).is_err());
+    }
+
+    #[test]
+    fn test_watch_parsing() {
+        let matches = parse(&["--monitor", "--watch", "0xa", "--watch", "0xb", "--unwatch", "0xc"]).unwrap();
+        let watch: Vec<&String> = matches.get_many::<String>(WATCH_ARG).unwrap().collect();
+        assert_eq!(watch, ["0xa", "0xb"]);
+        assert_eq!(matches.get_many::<String>(UNWATCH_ARG).unwrap().count(), 1);
+        // 只有实时监控会订阅合约事件
+        assert!(parse(&["--watch", "0xa"]).is_err());
+    }
+
+    #[test]
+    fn test_graph_client_for_chain() {
+        assert_eq!(CliApp::graph_client_for("polygon-pos", false).unwrap().network(), "polygon-pos");
+        // 没有子图的链、以及要求 V2 但该链没有 V2 子图时直接报错，而不是静默查询以太坊子图
+        assert!(CliApp::graph_client_for("unknown-chain", false).is_err());
+        assert!(CliApp::graph_client_for("polygon-pos", true).is_err());
+        assert!(CliApp::graph_client_for("ethereum", true).is_ok());
+    }
+}
@@ -1,11 +1,16 @@
-use anyhow::Result;
+use ::notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 // 协议类型常量
 pub mod protocol_types {
     pub const AMM_V2: &str = "amm_v2";
     pub const AMM_V3: &str = "amm_v3";
+    pub const STABLE_SWAP: &str = "stable_swap";
+    pub const WEIGHTED: &str = "weighted";
 }
 
 // DEX类型常量
@@ -35,6 +40,33 @@ pub struct DexConfig {
     pub router_address: Option<String>,
     pub subgraph_url: Option<String>,
     pub rate_limit_ms: u64,
+    /// Uniswap V3 费率档位（如 500 表示 0.05%），未配置时尝试所有标准档位
+    #[serde(default)]
+    pub fee_tier: Option<u32>,
+    /// Multicall3 合约地址，配置后批量查询链上数据
+    #[serde(default)]
+    pub multicall_address: Option<String>,
+    /// 单次兑换的 gas 用量，未配置时使用 `default_gas_units` 的默认值
+    #[serde(default)]
+    pub gas_units: Option<u64>,
+}
+
+impl DexConfig {
+    /// 单次兑换的 gas 用量：优先使用配置值，否则按 DEX 类型取默认值
+    pub fn gas_units_or_default(&self, dex_key: &str) -> u64 {
+        self.gas_units.unwrap_or_else(|| default_gas_units(dex_key))
+    }
+}
+
+/// 各类 DEX 单次兑换的默认 gas 用量（按 DEX 键名，不区分大小写）：
+/// Uniswap V3 185k，Curve 250k，Balancer 200k，Uniswap V2 及其分叉 150k
+pub fn default_gas_units(dex_key: &str) -> u64 {
+    match dex_key.to_lowercase().as_str() {
+        "uniswap_v3" => 185_000,
+        dex_types::CURVE => 250_000,
+        dex_types::BALANCER => 200_000,
+        _ => 150_000,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +74,119 @@ pub struct MonitoringConfig {
     pub scan_interval_seconds: u64,
     pub max_concurrent_requests: usize,
     pub timeout_seconds: u64,
+    /// 事件监听时同时处理的日志数量上限
+    #[serde(default = "default_max_concurrent_logs")]
+    pub max_concurrent_logs: usize,
+    /// WebSocket 断线后的最大连续重连次数，未配置时不限制
+    #[serde(default)]
+    pub max_ws_reconnect_attempts: Option<u32>,
+    /// 是否跳过与当前显示内容相同的表格更新
+    #[serde(default = "default_dedup_display_updates")]
+    pub dedup_display_updates: bool,
+    /// RPC 节点链 ID 与配置不一致时拒绝启动（false 时仅警告并跳过该 DEX）
+    #[serde(default = "default_strict_chain_id")]
+    pub strict_chain_id: bool,
+    /// 每次扫描的套利机会快照输出，未配置时不写入
+    #[serde(default)]
+    pub snapshot: Option<SnapshotConfig>,
+    /// 实时监控启动时的冷启动设置
+    #[serde(default)]
+    pub cold_start: ColdStartConfig,
+    /// 套利机会的 Webhook 通知，未配置时不推送
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+    /// 套利机会的 Telegram 通知，未配置时不推送
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
+    /// DEX 价格查询连续失败该次数后熔断
+    #[serde(default = "default_circuit_breaker_failure_threshold")]
+    pub circuit_breaker_failure_threshold: u32,
+    /// 熔断后跳过该 DEX 的冷却时间，单位秒
+    #[serde(default = "default_circuit_breaker_cooldown_seconds")]
+    pub circuit_breaker_cooldown_seconds: u64,
+    /// 同一机会重复通知的最短间隔，单位秒
+    #[serde(default = "default_notification_dedup_seconds")]
+    pub notification_dedup_seconds: u64,
+    /// Prometheus 指标服务端口，未配置时不启动
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    /// 实时监听启动前从该区块回填 Swap 事件，未配置时不回填
+    #[serde(default)]
+    pub backfill_from_block: Option<u64>,
+}
+
+/// 套利机会快照配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotConfig {
+    /// 快照文件输出目录
+    pub directory: String,
+    /// 最多保留的快照文件数（0 表示不限制）
+    pub max_files: usize,
+    /// 快照文件总大小上限，单位字节（0 表示不限制）
+    pub max_total_bytes: u64,
+}
+
+/// Webhook 通知配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// 接收 POST 请求的地址
+    pub url: String,
+    /// 触发通知的最小利润率（百分比）
+    pub min_profit_percentage: f64,
+}
+
+/// Telegram 机器人通知配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+    /// 触发通知的最小利润率（百分比）
+    pub min_profit_percentage: f64,
+}
+
+fn default_circuit_breaker_failure_threshold() -> u32 {
+    crate::dex::circuit_breaker::DEFAULT_FAILURE_THRESHOLD
+}
+
+fn default_circuit_breaker_cooldown_seconds() -> u64 {
+    crate::dex::circuit_breaker::DEFAULT_COOLDOWN.as_secs()
+}
+
+fn default_notification_dedup_seconds() -> u64 {
+    300
+}
+
+pub fn default_max_concurrent_logs() -> usize {
+    16
+}
+
+fn default_dedup_display_updates() -> bool {
+    true
+}
+
+fn default_strict_chain_id() -> bool {
+    true
+}
+
+/// 冷启动配置：数据库中交易对不足时，先从 TheGraph 刷新再开始监听事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColdStartConfig {
+    /// 是否在启动时检查并刷新交易对数据
+    pub enabled: bool,
+    /// 数据库中交易对少于该数量时触发刷新
+    pub min_pairs: usize,
+    /// 每个监控 token 从 TheGraph 获取的交易对数量
+    pub pairs_per_token: i32,
+}
+
+impl Default for ColdStartConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_pairs: 10,
+            pairs_per_token: 25,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +212,24 @@ impl Config {
             router_address: Some("0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D".to_string()),
             subgraph_url: Some("https://api.thegraph.com/subgraphs/name/uniswap/uniswap-v2".to_string()),
             rate_limit_ms: 1000,
+            fee_tier: None,
+            multicall_address: None,
+            gas_units: None,
+        });
+        
+        // Uniswap V3 配置
+        dex_configs.insert("uniswap_v3".to_string(), DexConfig {
+            name: "Uniswap V3".to_string(),
+            enabled: true,
+            api_url: "https://eth.llamarpc.com".to_string(),
+            chain_id: 1,
+            factory_address: Some("0x1F98431c8aD98523631AE4a59f267346ea31F984".to_string()),
+            router_address: Some("0xE592427A0AEce9fE0C3AC5f3Ea30c6E7eDfF79Cd".to_string()),
+            subgraph_url: None,
+            rate_limit_ms: 1000,
+            fee_tier: None,
+            multicall_address: None,
+            gas_units: None,
         });
         
         // SushiSwap 配置
@@ -79,6 +242,9 @@ impl Config {
             router_address: Some("0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F".to_string()),
             subgraph_url: Some("https://api.thegraph.com/subgraphs/name/sushiswap/exchange".to_string()),
             rate_limit_ms: 1000,
+            fee_tier: None,
+            multicall_address: Some("0xcA11bde05977b3631167028862bE2a173976CA11".to_string()),
+            gas_units: None,
         });
         
         // PancakeSwap 配置 (BSC)
@@ -91,6 +257,9 @@ impl Config {
             router_address: Some("0x10ED43C718714eb63d5aA57B78B54704E256024E".to_string()),
             subgraph_url: Some("https://api.thegraph.com/subgraphs/name/pancakeswap/exchange".to_string()),
             rate_limit_ms: 1000,
+            fee_tier: None,
+            multicall_address: None,
+            gas_units: None,
         });
         
         // Curve 配置
@@ -103,6 +272,9 @@ impl Config {
             router_address: None,
             subgraph_url: Some("https://api.thegraph.com/subgraphs/name/curvefi/curve".to_string()),
             rate_limit_ms: 2000,
+            fee_tier: None,
+            multicall_address: None,
+            gas_units: None,
         });
         
         // Balancer 配置
@@ -115,14 +287,65 @@ impl Config {
             router_address: None,
             subgraph_url: Some("https://api.thegraph.com/subgraphs/name/balancer-labs/balancer-v2".to_string()),
             rate_limit_ms: 1500,
+            fee_tier: None,
+            multicall_address: None,
+            gas_units: None,
         });
         
+        // 允许通过 <DEX>_GAS_UNITS 环境变量（如 CURVE_GAS_UNITS）覆盖单次兑换的 gas 用量
+        for (key, dex_config) in dex_configs.iter_mut() {
+            if let Some(units) = std::env::var(format!("{}_GAS_UNITS", key.to_uppercase()))
+                .ok()
+                .and_then(|v| v.parse().ok())
+            {
+                dex_config.gas_units = Some(units);
+            }
+        }
+        
         Ok(Config {
             dex_configs,
             monitoring: MonitoringConfig {
                 scan_interval_seconds: 10,
                 max_concurrent_requests: 10,
                 timeout_seconds: 30,
+                max_concurrent_logs: default_max_concurrent_logs(),
+                max_ws_reconnect_attempts: None,
+                dedup_display_updates: default_dedup_display_updates(),
+                strict_chain_id: default_strict_chain_id(),
+                snapshot: None,
+                cold_start: ColdStartConfig::default(),
+                webhook: std::env::var("WEBHOOK_URL").ok().map(|url| WebhookConfig {
+                    url,
+                    min_profit_percentage: std::env::var("WEBHOOK_MIN_PROFIT_PERCENTAGE")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(1.0),
+                }),
+                telegram: match (std::env::var("TELEGRAM_BOT_TOKEN"), std::env::var("TELEGRAM_CHAT_ID")) {
+                    (Ok(bot_token), Ok(chat_id)) => Some(TelegramConfig {
+                        bot_token,
+                        chat_id,
+                        min_profit_percentage: std::env::var("TELEGRAM_MIN_PROFIT_PERCENTAGE")
+                            .ok()
+                            .and_then(|v| v.parse().ok())
+                            .unwrap_or(1.0),
+                    }),
+                    _ => None,
+                },
+                circuit_breaker_failure_threshold: std::env::var("CIRCUIT_BREAKER_FAILURE_THRESHOLD")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_circuit_breaker_failure_threshold),
+                circuit_breaker_cooldown_seconds: std::env::var("CIRCUIT_BREAKER_COOLDOWN_SECONDS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_circuit_breaker_cooldown_seconds),
+                notification_dedup_seconds: std::env::var("NOTIFICATION_DEDUP_SECONDS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_notification_dedup_seconds),
+                metrics_port: std::env::var("METRICS_PORT").ok().and_then(|v| v.parse().ok()),
+                backfill_from_block: std::env::var("BACKFILL_FROM_BLOCK").ok().and_then(|v| v.parse().ok()),
             },
             arbitrage: ArbitrageConfig {
                 min_profit_threshold: 0.01, // 1% 最小利润
@@ -138,10 +361,136 @@ impl Config {
         })
     }
     
+    /// 从 TOML/JSON 配置文件加载（按扩展名识别格式），文件中未出现的字段使用 `load` 的默认值
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let defaults = ::config::Config::try_from(&Self::load()?)?;
+        let config: Self = ::config::Config::builder()
+            .add_source(defaults)
+            .add_source(::config::File::from(path.as_ref()))
+            .build()?
+            .try_deserialize()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 检查配置取值是否合理
+    pub fn validate(&self) -> Result<()> {
+        let arbitrage = &self.arbitrage;
+        if !(0.0..).contains(&arbitrage.min_profit_threshold) {
+            return Err(anyhow!("min_profit_threshold must be non-negative, got {}", arbitrage.min_profit_threshold));
+        }
+        if arbitrage.max_gas_price_gwei.is_nan() || arbitrage.max_gas_price_gwei <= 0.0 {
+            return Err(anyhow!("max_gas_price_gwei must be positive, got {}", arbitrage.max_gas_price_gwei));
+        }
+        if !(0.0..1.0).contains(&arbitrage.slippage_tolerance) {
+            return Err(anyhow!("slippage_tolerance must be in [0, 1), got {}", arbitrage.slippage_tolerance));
+        }
+        let notify_thresholds = [
+            self.monitoring.webhook.as_ref().map(|webhook| webhook.min_profit_percentage),
+            self.monitoring.telegram.as_ref().map(|telegram| telegram.min_profit_percentage),
+        ];
+        if let Some(threshold) = notify_thresholds.into_iter().flatten().find(|threshold| !(0.0..).contains(threshold)) {
+            return Err(anyhow!("min_profit_percentage must be non-negative, got {}", threshold));
+        }
+        Ok(())
+    }
+
+    /// 监听配置文件，修改后重新加载并把新配置交给 `on_change`；
+    /// 解析或校验失败时只记录警告，继续使用旧配置。返回的 watcher 被丢弃后停止监听
+    pub fn watch<F>(path: impl AsRef<Path>, on_change: F) -> Result<RecommendedWatcher>
+    where
+        F: Fn(Config) + Send + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .ok_or_else(|| anyhow!("invalid config path: {}", path.display()))?;
+        // 监听所在目录，编辑器以替换文件的方式保存时也能收到事件
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        // 一次保存可能触发多个事件，内容与上次应用的配置相同时不重复回调
+        let mut last_applied = Self::from_file(&path).ok().and_then(|config| serde_json::to_string(&config).ok());
+        let mut watcher = ::notify::recommended_watcher(move |event: ::notify::Result<::notify::Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("配置文件监听出错: {}", e);
+                    return;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                || !event.paths.iter().any(|changed| changed.file_name() == Some(file_name.as_os_str()))
+            {
+                return;
+            }
+
+            match Self::from_file(&path) {
+                Ok(config) => {
+                    let serialized = serde_json::to_string(&config).ok();
+                    if serialized.is_some() && serialized == last_applied {
+                        return;
+                    }
+                    last_applied = serialized;
+                    info!("配置文件已更新: {}", path.display());
+                    on_change(config);
+                }
+                Err(e) => warn!("重新加载配置文件 {} 失败，继续使用旧配置: {}", path.display(), e),
+            }
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }
+
     pub fn get_enabled_dexes(&self) -> Vec<&DexConfig> {
         self.dex_configs
             .values()
             .filter(|config| config.enabled)
             .collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch_reloads_updated_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[arbitrage]\nmin_profit_threshold = 0.02\n").unwrap();
+        assert_eq!(Config::from_file(&path).unwrap().arbitrage.min_profit_threshold, 0.02);
+
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = Config::watch(&path, move |config| drop(sender.send(config))).unwrap();
+
+        // 无效配置不会触发回调
+        std::fs::write(&path, "[arbitrage]\nslippage_tolerance = 1.5\n").unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+
+        std::fs::write(&path, "[arbitrage]\nmin_profit_threshold = 0.05\n\n[monitoring]\nscan_interval_seconds = 3\n").unwrap();
+        let updated = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(updated.arbitrage.min_profit_threshold, 0.05);
+        assert_eq!(updated.monitoring.scan_interval_seconds, 3);
+        // 文件中未出现的字段保留默认值
+        assert_eq!(updated.arbitrage.slippage_tolerance, 0.005);
+    }
+
+    #[test]
+    fn test_circuit_breaker_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[monitoring]
+circuit_breaker_failure_threshold = 2
+").unwrap();
+
+        let monitoring = Config::from_file(&path).unwrap().monitoring;
+        assert_eq!(monitoring.circuit_breaker_failure_threshold, 2);
+        assert_eq!(monitoring.circuit_breaker_cooldown_seconds, 60);
+    }
+}
@@ -1,47 +1,157 @@
 use crate::token::{Token, TokenList, TokenManager};
+use crate::event_listener::ContractInfo;
 use crate::thegraph::PairData;
-use crate::utils::convert_decimal_to_integer_string;
-use crate::types::TokenPair;
+use crate::utils::{convert_decimal_to_scaled_integer, convert_integer_string_to_decimal};
+use crate::types::ArbitrageOpportunity;
 use crate::config::{protocol_types, dex_types};
 use anyhow::Result;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use log::info;
-use rusqlite::{params, Connection};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// 默认连接池大小
+pub const DEFAULT_POOL_SIZE: u32 = 8;
 
 #[derive(Clone)]
 pub struct Database {
-    conn: Arc<Mutex<Connection>>,
+    pool: Pool<SqliteConnectionManager>,
 }
 
 impl Database {
     /// 创建新的数据库实例
     pub fn new(db_path: Option<&str>) -> Result<Self> {
-        let conn = match db_path {
+        Self::with_pool_size(db_path, DEFAULT_POOL_SIZE)
+    }
+
+    /// 创建指定连接池大小的数据库实例
+    pub fn with_pool_size(db_path: Option<&str>, pool_size: u32) -> Result<Self> {
+        // `:memory:` 与 None 一样使用单连接内存库，否则池中每个连接都会打开各自独立的内存数据库
+        let (manager, pool_size) = match db_path.filter(|path| *path != ":memory:") {
             Some(path) => {
                 // 确保目录存在
                 if let Some(parent) = Path::new(path).parent() {
                     std::fs::create_dir_all(parent)?;
                 }
-                Connection::open(path)?
+                let manager = SqliteConnectionManager::file(path).with_init(|conn| {
+                    // 多个连接并发读写时等待锁释放，而不是立即返回 SQLITE_BUSY
+                    conn.busy_timeout(Duration::from_secs(5))?;
+                    conn.pragma_update(None, "journal_mode", "WAL")
+                });
+                (manager, pool_size.max(1))
             }
             None => {
-                let conn = Connection::open(":memory:")?;
-                // 启用外键约束
-                conn.execute("PRAGMA foreign_keys = ON", [])?;
-                conn
+                // 每个内存连接都是独立的数据库，因此内存模式只使用一个连接
+                let manager = SqliteConnectionManager::memory().with_init(|conn| {
+                    // 启用外键约束
+                    conn.execute_batch("PRAGMA foreign_keys = ON")
+                });
+                (manager, 1)
             }
         };
 
-        let db = Database { conn: Arc::new(Mutex::new(conn)) };
+        let pool = Pool::builder().max_size(pool_size).build(manager)?;
+
+        let db = Database { pool };
         db.init_tables()?;
         Ok(db)
     }
 
+    /// 把 `updated_at` 的 Unix 秒数转换为交易对的观测时间
+    fn observed_at(secs: Option<i64>) -> Option<chrono::DateTime<chrono::Utc>> {
+        secs.and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+    }
+
+    /// 交易对查询的列，`prefix` 为表别名前缀（如 `"p."`），顺序与 `pair_from_row` 读取的索引一致
+    fn pair_columns(prefix: &str) -> String {
+        const COLUMNS: [&str; 28] = [
+            "id", "network", "dex_type", "protocol_type",
+            "token0_id", "token0_symbol", "token0_name", "token0_decimals",
+            "token1_id", "token1_symbol", "token1_name", "token1_decimals",
+            "volume_usd", "reserve_usd", "tx_count", "reserve0", "reserve1", "fee_tier", "sqrt_price", "tick",
+            "reserve_usd_scale", "reserve0_scale", "reserve1_scale",
+            "weight0", "weight1", "amp_coefficient", "balances", "liquidity",
+        ];
+        let mut columns: Vec<String> = COLUMNS.iter().map(|column| format!("{}{}", prefix, column)).collect();
+        columns.push(format!("CAST(strftime('%s', {}updated_at) AS INTEGER)", prefix));
+        columns.join(", ")
+    }
+
+    /// 将 `pair_columns` 查询出的一行转换为 PairData
+    fn pair_from_row(row: &rusqlite::Row) -> rusqlite::Result<PairData> {
+        use crate::thegraph::TokenInfo;
+
+        Ok(PairData {
+            id: row.get(0)?,
+            network: row.get(1)?,
+            dex_type: row.get(2)?,
+            protocol_type: row.get(3)?,
+            token0: TokenInfo {
+                id: row.get(4)?,
+                symbol: row.get(5)?,
+                name: row.get(6)?,
+                decimals: row.get(7)?,
+            },
+            token1: TokenInfo {
+                id: row.get(8)?,
+                symbol: row.get(9)?,
+                name: row.get(10)?,
+                decimals: row.get(11)?,
+            },
+            volume_usd: row.get(12)?,
+            reserve_usd: Self::restore_reserve(row.get(13)?, row.get(20)?),
+            tx_count: row.get(14)?,
+            reserve0: Self::restore_reserve(row.get(15)?, row.get(21)?),
+            reserve1: Self::restore_reserve(row.get(16)?, row.get(22)?),
+            fee_tier: row.get(17)?,
+            sqrt_price: row.get(18)?,
+            tick: row.get(19)?,
+            amp_coefficient: row.get::<_, Option<i64>>(25)?.map(|amp| amp as u64),
+            balances: row
+                .get::<_, Option<String>>(26)?
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            weight0: row.get(23)?,
+            weight1: row.get(24)?,
+            liquidity: row.get(27)?,
+            updated_at: Self::observed_at(row.get(28)?),
+        })
+    }
+
+    /// 表中不存在指定列时通过 ALTER TABLE 添加
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .iter()
+            .any(|name| name == column);
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
+        Ok(())
+    }
+
+    /// 将整数字符串形式的储备量按 scale 还原为十进制字符串，无法解析时原样返回
+    fn restore_reserve(value: String, scale: i64) -> String {
+        if scale == 0 {
+            return value;
+        }
+        convert_integer_string_to_decimal(&value, scale)
+            .map(|decimal| decimal.to_plain_string())
+            .unwrap_or(value)
+    }
+
     /// 初始化数据库表
     fn init_tables(&self) -> Result<()> {
         // 创建tokens表
-        self.conn.lock().unwrap().execute(
+        self.pool.get()?.execute(
             r#"
             CREATE TABLE IF NOT EXISTS tokens (
                 id TEXT PRIMARY KEY,
@@ -61,8 +171,19 @@ impl Database {
             [],
         )?;
 
+        // 按符号（不区分大小写）和市值排名查找token时使用的索引
+        self.pool.get()?.execute(
+            "CREATE INDEX IF NOT EXISTS idx_tokens_symbol ON tokens (symbol COLLATE NOCASE)",
+            [],
+        )?;
+
+        self.pool.get()?.execute(
+            "CREATE INDEX IF NOT EXISTS idx_tokens_market_cap_rank ON tokens (market_cap_rank)",
+            [],
+        )?;
+
         // 创建token_updates表用于记录更新历史
-        self.conn.lock().unwrap().execute(
+        self.pool.get()?.execute(
             r#"
             CREATE TABLE IF NOT EXISTS token_updates (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -74,7 +195,7 @@ impl Database {
         )?;
 
         // 创建pairs表用于存储交易对数据
-        self.conn.lock().unwrap().execute(
+        self.pool.get()?.execute(
             r#"
             CREATE TABLE IF NOT EXISTS pairs (
                 id TEXT PRIMARY KEY,
@@ -97,6 +218,14 @@ impl Database {
                 fee_tier TEXT NOT NULL DEFAULT '3000',
                 sqrt_price TEXT,
                 tick TEXT,
+                reserve_usd_scale INTEGER NOT NULL DEFAULT 0,
+                reserve0_scale INTEGER NOT NULL DEFAULT 0,
+                reserve1_scale INTEGER NOT NULL DEFAULT 0,
+                weight0 TEXT,
+                weight1 TEXT,
+                amp_coefficient INTEGER,
+                balances TEXT, -- JSON array of StableSwap balances
+                liquidity TEXT,
                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                 updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
             )
@@ -104,6 +233,106 @@ impl Database {
             [],
         )?;
 
+        // 旧版本的数据库没有储备量的 scale 列，按需补齐
+        for column in ["reserve_usd_scale", "reserve0_scale", "reserve1_scale"] {
+            self.add_column_if_missing("pairs", column, "INTEGER NOT NULL DEFAULT 0")?;
+        }
+        // Balancer 加权池的归一化权重
+        for column in ["weight0", "weight1"] {
+            self.add_column_if_missing("pairs", column, "TEXT")?;
+        }
+        // StableSwap 池的放大系数与各币种余额
+        self.add_column_if_missing("pairs", "amp_coefficient", "INTEGER")?;
+        self.add_column_if_missing("pairs", "balances", "TEXT")?;
+        // V3 池当前 tick 的流动性 L
+        self.add_column_if_missing("pairs", "liquidity", "TEXT")?;
+
+        // 创建token_pairs反向索引表，用于按token快速查找交易对
+        self.pool.get()?.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS token_pairs (
+                token_id TEXT NOT NULL,
+                pair_id TEXT NOT NULL,
+                PRIMARY KEY (token_id, pair_id)
+            )
+            "#,
+            [],
+        )?;
+
+        self.pool.get()?.execute(
+            "CREATE INDEX IF NOT EXISTS idx_token_pairs_pair_id ON token_pairs (pair_id)",
+            [],
+        )?;
+
+        // 旧数据库中已有的交易对没有索引记录，按 pairs 表回填缺失的映射
+        self.pool.get()?.execute(
+            r#"
+            INSERT OR IGNORE INTO token_pairs (token_id, pair_id)
+            SELECT LOWER(token0_id), id FROM pairs
+            UNION
+            SELECT LOWER(token1_id), id FROM pairs
+            "#,
+            [],
+        )?;
+
+        // 创建opportunities表用于存储发现的套利机会
+        self.pool.get()?.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS opportunities (
+                id TEXT PRIMARY KEY,
+                token_pair TEXT NOT NULL,
+                buy_dex TEXT NOT NULL,
+                sell_dex TEXT NOT NULL,
+                buy_price TEXT NOT NULL,
+                sell_price TEXT NOT NULL,
+                profit_percentage REAL NOT NULL,
+                estimated_profit TEXT NOT NULL,
+                liquidity TEXT NOT NULL,
+                gas_cost_estimate TEXT NOT NULL,
+                confidence_score REAL NOT NULL,
+                timestamp INTEGER NOT NULL,
+                sandwich_risk REAL NOT NULL DEFAULT 0
+            )
+            "#,
+            [],
+        )?;
+
+        self.add_column_if_missing("opportunities", "sandwich_risk", "REAL NOT NULL DEFAULT 0")?;
+
+        self.pool.get()?.execute(
+            "CREATE INDEX IF NOT EXISTS idx_opportunities_timestamp ON opportunities (timestamp)",
+            [],
+        )?;
+
+        // 创建price_history表用于记录交易对价格时间序列
+        self.pool.get()?.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS price_history (
+                pair_id TEXT NOT NULL,
+                price TEXT NOT NULL,
+                timestamp INTEGER NOT NULL
+            )
+            "#,
+            [],
+        )?;
+
+        self.pool.get()?.execute(
+            "CREATE INDEX IF NOT EXISTS idx_price_history_pair_timestamp ON price_history (pair_id, timestamp)",
+            [],
+        )?;
+
+        // 创建watched_contracts表用于保存事件监听的合约集合
+        self.pool.get()?.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS watched_contracts (
+                address TEXT PRIMARY KEY,
+                protocol_type TEXT NOT NULL,
+                dex_type TEXT NOT NULL
+            )
+            "#,
+            [],
+        )?;
+
         info!("数据库表初始化完成");
         Ok(())
     }
@@ -112,7 +341,7 @@ impl Database {
     pub fn save_tokens(&self, tokens: &[Token]) -> Result<()> {
         let tokens_len = tokens.len();
         // 开始事务
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let tx = binding.unchecked_transaction()?;
 
         // 插入或更新tokens
@@ -159,6 +388,16 @@ impl Database {
         Ok(())
     }
 
+    /// 加载在 `max_age` 内更新过的 token id
+    pub fn load_fresh_token_ids(&self, max_age: Duration) -> Result<std::collections::HashSet<String>> {
+        let binding = self.pool.get()?;
+        let mut stmt = binding.prepare("SELECT id FROM tokens WHERE updated_at >= datetime('now', ?1)")?;
+        let ids = stmt
+            .query_map([format!("-{} seconds", max_age.as_secs())], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
     /// 从数据库加载token列表
     pub fn load_tokens(&self, limit: Option<usize>) -> Result<Vec<Token>> {
         let (query, params_vec): (&str, Vec<rusqlite::types::Value>) = if let Some(limit_val) = limit {
@@ -173,7 +412,7 @@ impl Database {
             )
         };
         
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(query)?;
         let rows = stmt.query_map(rusqlite::params_from_iter(params_vec), |row| {
             let platforms_json: String = row.get(8)?;
@@ -203,13 +442,13 @@ impl Database {
 
     /// 根据符号查找token
     pub fn find_token_by_symbol(&self, symbol: &str) -> Result<Option<Token>> {
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(
             r#"
             SELECT id, symbol, name, market_cap_rank, current_price,
                    market_cap, total_volume, price_change_percentage_24h, platforms
             FROM tokens
-            WHERE UPPER(symbol) = UPPER(?1)
+            WHERE symbol = ?1 COLLATE NOCASE
             LIMIT 1
             "#,
         )?;
@@ -238,20 +477,20 @@ impl Database {
         }
     }
 
-    /// 根据地址查找token - 直接数据库操作
-    pub fn find_token_by_address(&self, address: &str) -> Result<Option<Token>> {
-        let binding = self.conn.lock().unwrap();
+    /// 根据 `chain` 平台上的合约地址查找token（地址不区分大小写）- 直接数据库操作
+    pub fn find_token_by_address(&self, address: &str, chain: &str) -> Result<Option<Token>> {
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(
             r#"
             SELECT id, symbol, name, market_cap_rank, current_price,
                    market_cap, total_volume, price_change_percentage_24h, platforms
             FROM tokens
-            WHERE platforms LIKE '%' || ?1 || '%'
+            WHERE lower(json_extract(platforms, '$."' || ?2 || '"')) = lower(?1)
             LIMIT 1
             "#,
         )?;
 
-        let mut rows = stmt.query_map([address], |row| {
+        let mut rows = stmt.query_map([address, chain], |row| {
             let platforms_json: String = row.get(8)?;
             let platforms: std::collections::HashMap<String, Option<String>> =
                 serde_json::from_str(&platforms_json).unwrap_or_default();
@@ -277,14 +516,15 @@ impl Database {
 
     /// 获取token统计信息 - 直接数据库操作
     pub fn get_token_stats(&self) -> Result<(usize, chrono::DateTime<chrono::Utc>)> {
+        // 两次查询共用一个连接，单连接池下不会等待自身持有的连接
+        let binding = self.pool.get()?;
+
         // 获取token数量
-        let binding = self.conn.lock().unwrap();
         let mut stmt = binding.prepare("SELECT COUNT(*) FROM tokens")?;
         let count: i64 = stmt.query_row([], |row| row.get(0))?;
 
         // 获取最后更新时间
-        let binding2 = self.conn.lock().unwrap();
-        let mut update_stmt = binding2
+        let mut update_stmt = binding
             .prepare("SELECT timestamp FROM token_updates ORDER BY timestamp DESC LIMIT 1")?;
 
         let timestamp: i64 = update_stmt.query_row([], |row| row.get(0))?;
@@ -296,19 +536,20 @@ impl Database {
 
     /// 获取数据库统计信息
     pub fn get_stats(&self) -> Result<(usize, chrono::DateTime<chrono::Utc>)> {
+        // 两次查询共用一个连接
+        let binding = self.pool.get()?;
+
         // 获取token数量
         let count = {
-            let binding = self.conn.lock().unwrap();
             let mut stmt = binding.prepare("SELECT COUNT(*) FROM tokens")?;
             stmt.query_row([], |row| row.get(0))?
         };
 
         // 获取最后更新时间
         let last_update = {
-            let binding = self.conn.lock().unwrap();
             let mut update_stmt = binding
                 .prepare("SELECT timestamp FROM token_updates ORDER BY timestamp DESC LIMIT 1")?;
-    
+
             let timestamp: i64 = update_stmt.query_row([], |row| row.get(0))?;
             chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_else(chrono::Utc::now)
         };
@@ -320,7 +561,7 @@ impl Database {
     pub fn save_pairs(&self, pairs: &[PairData]) -> Result<()> {
         let pairs_len = pairs.len();
         // 开始事务
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let tx = binding.unchecked_transaction()?;
 
         // 插入或更新pairs
@@ -331,19 +572,23 @@ impl Database {
                 id, network, dex_type, protocol_type,
                 token0_id, token0_symbol, token0_name, token0_decimals,
                 token1_id, token1_symbol, token1_name, token1_decimals,
-                volume_usd, reserve_usd, tx_count, reserve0, reserve1, fee_tier, sqrt_price, tick
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+                volume_usd, reserve_usd, tx_count, reserve0, reserve1, fee_tier, sqrt_price, tick,
+                reserve_usd_scale, reserve0_scale, reserve1_scale, weight0, weight1, amp_coefficient, balances, liquidity, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, CURRENT_TIMESTAMP)
                 "#,
             )?;
 
             for pair in pairs {
-                // 将reserve字段从带小数点的字符串转换为整数型字符串
-                let reserve_usd_int = convert_decimal_to_integer_string(&pair.reserve_usd)
-                    .unwrap_or_else(|_| "0".to_string());
-                let reserve0_int = convert_decimal_to_integer_string(&pair.reserve0)
-                    .unwrap_or_else(|_| "0".to_string());
-                let reserve1_int = convert_decimal_to_integer_string(&pair.reserve1)
-                    .unwrap_or_else(|_| "0".to_string());
+                // 将reserve字段从带小数点的字符串转换为整数型字符串，并记录 scale 以便加载时还原
+                let to_scaled = |value: &str| {
+                    convert_decimal_to_scaled_integer(value).unwrap_or_else(|_| ("0".to_string(), 0))
+                };
+                let (reserve_usd_int, reserve_usd_scale) = to_scaled(&pair.reserve_usd);
+                let (reserve0_int, reserve0_scale) = to_scaled(&pair.reserve0);
+                let (reserve1_int, reserve1_scale) = to_scaled(&pair.reserve1);
+                let balances = (!pair.balances.is_empty())
+                    .then(|| serde_json::to_string(&pair.balances))
+                    .transpose()?;
                 
                 stmt.execute(params![
                     &pair.id,
@@ -366,10 +611,32 @@ impl Database {
                     &pair.fee_tier,
                     &pair.sqrt_price,
                     &pair.tick,
+                    reserve_usd_scale,
+                    reserve0_scale,
+                    reserve1_scale,
+                    &pair.weight0,
+                    &pair.weight1,
+                    pair.amp_coefficient.map(|amp| amp as i64),
+                    balances,
+                    &pair.liquidity,
                  ])?;
             }
         }
 
+        // 维护token_pairs反向索引（交易对被替换时先清除旧的映射）
+        {
+            let mut delete_stmt = tx.prepare("DELETE FROM token_pairs WHERE pair_id = ?1")?;
+            let mut insert_stmt = tx.prepare(
+                "INSERT OR IGNORE INTO token_pairs (token_id, pair_id) VALUES (LOWER(?1), ?2)",
+            )?;
+
+            for pair in pairs {
+                delete_stmt.execute(params![&pair.id])?;
+                insert_stmt.execute(params![&pair.token0.id, &pair.id])?;
+                insert_stmt.execute(params![&pair.token1.id, &pair.id])?;
+            }
+        }
+
         // 提交事务
         tx.commit()?;
         info!("Saved {} pairs to database", pairs_len);
@@ -378,46 +645,10 @@ impl Database {
 
     /// 从数据库加载交易对列表 - 直接数据库操作
     pub fn load_pairs(&self) -> Result<Vec<PairData>> {
-        use crate::thegraph::TokenInfo;
-        
-        let binding = self.conn.lock().unwrap();
-        let mut stmt = binding.prepare(
-            r#"
-            SELECT id, network, dex_type, protocol_type, token0_id, token0_symbol, token0_name, token0_decimals,
-                   token1_id, token1_symbol, token1_name, token1_decimals,
-                   volume_usd, reserve_usd, tx_count, reserve0, reserve1, fee_tier, sqrt_price, tick
-            FROM pairs
-            "#,
-        )?;
+        let binding = self.pool.get()?;
+        let mut stmt = binding.prepare(&format!("SELECT {} FROM pairs", Self::pair_columns("")))?;
 
-        let pair_iter = stmt.query_map([], |row| {
-            Ok(PairData {
-                id: row.get(0)?,
-                network: row.get(1)?,
-                dex_type: row.get(2)?,
-                protocol_type: row.get(3)?,
-                token0: TokenInfo {
-                    id: row.get(4)?,
-                    symbol: row.get(5)?,
-                    name: row.get(6)?,
-                    decimals: row.get(7)?,
-                },
-                token1: TokenInfo {
-                    id: row.get(8)?,
-                    symbol: row.get(9)?,
-                    name: row.get(10)?,
-                    decimals: row.get(11)?,
-                },
-                volume_usd: row.get(12)?,
-                reserve_usd: row.get(13)?,
-                tx_count: row.get(14)?,
-                reserve0: row.get(15)?,
-                reserve1: row.get(16)?,
-                fee_tier: row.get(17)?,
-                sqrt_price: row.get(18)?,
-                tick: row.get(19)?,
-            })
-        })?;
+        let pair_iter = stmt.query_map([], Self::pair_from_row)?;
 
         let mut pairs = Vec::new();
         for pair in pair_iter {
@@ -434,16 +665,7 @@ impl Database {
         dex_type: Option<&str>,
         limit: Option<usize>,
     ) -> Result<Vec<PairData>> {
-        use crate::thegraph::TokenInfo;
-        
-        let mut query = String::from(
-            r#"
-            SELECT id, network, dex_type, protocol_type, token0_id, token0_symbol, token0_name, token0_decimals,
-                   token1_id, token1_symbol, token1_name, token1_decimals,
-                   volume_usd, reserve_usd, tx_count, reserve0, reserve1, fee_tier, sqrt_price, tick
-            FROM pairs
-            "#,
-        );
+        let mut query = format!("SELECT {} FROM pairs", Self::pair_columns(""));
 
         let mut conditions = Vec::new();
         let mut params_vec = Vec::new();
@@ -467,38 +689,11 @@ impl Database {
             query.push_str(&format!(" LIMIT {}", lim));
         }
 
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(&query)?;
         let params: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
         
-        let pair_iter = stmt.query_map(params.as_slice(), |row| {
-            Ok(PairData {
-                id: row.get(0)?,
-                network: row.get(1)?,
-                dex_type: row.get(2)?,
-                protocol_type: row.get(3)?,
-                token0: TokenInfo {
-                    id: row.get(4)?,
-                    symbol: row.get(5)?,
-                    name: row.get(6)?,
-                    decimals: row.get(7)?,
-                },
-                token1: TokenInfo {
-                    id: row.get(8)?,
-                    symbol: row.get(9)?,
-                    name: row.get(10)?,
-                    decimals: row.get(11)?,
-                },
-                volume_usd: row.get(12)?,
-                reserve_usd: row.get(13)?,
-                tx_count: row.get(14)?,
-                reserve0: row.get(15)?,
-                reserve1: row.get(16)?,
-                fee_tier: row.get(17)?,
-                sqrt_price: row.get(18)?,
-                tick: row.get(19)?,
-            })
-        })?;
+        let pair_iter = stmt.query_map(params.as_slice(), Self::pair_from_row)?;
 
         let mut pairs = Vec::new();
         for pair in pair_iter {
@@ -515,16 +710,7 @@ impl Database {
         dex_type: Option<&str>,
         limit: Option<usize>,
     ) -> Result<Vec<PairData>> {
-        use crate::thegraph::TokenInfo;
-        
-        let mut query = String::from(
-            r#"
-            SELECT id, network, dex_type, protocol_type, token0_id, token0_symbol, token0_name, token0_decimals,
-                   token1_id, token1_symbol, token1_name, token1_decimals,
-                   volume_usd, reserve_usd, tx_count, reserve0, reserve1, fee_tier, sqrt_price, tick
-            FROM pairs
-            "#,
-        );
+        let mut query = format!("SELECT {} FROM pairs", Self::pair_columns(""));
 
         let mut conditions = Vec::new();
         let mut params_vec = Vec::new();
@@ -551,38 +737,11 @@ impl Database {
             query.push_str(&format!(" LIMIT {}", lim));
         }
 
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(&query)?;
         let params: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
         
-        let pair_iter = stmt.query_map(params.as_slice(), |row| {
-            Ok(PairData {
-                id: row.get(0)?,
-                network: row.get(1)?,
-                dex_type: row.get(2)?,
-                protocol_type: row.get(3)?,
-                token0: TokenInfo {
-                    id: row.get(4)?,
-                    symbol: row.get(5)?,
-                    name: row.get(6)?,
-                    decimals: row.get(7)?,
-                },
-                token1: TokenInfo {
-                    id: row.get(8)?,
-                    symbol: row.get(9)?,
-                    name: row.get(10)?,
-                    decimals: row.get(11)?,
-                },
-                volume_usd: row.get(12)?,
-                reserve_usd: row.get(13)?,
-                tx_count: row.get(14)?,
-                reserve0: row.get(15)?,
-                reserve1: row.get(16)?,
-                fee_tier: row.get(17)?,
-                sqrt_price: row.get(18)?,
-                tick: row.get(19)?,
-            })
-        })?;
+        let pair_iter = stmt.query_map(params.as_slice(), Self::pair_from_row)?;
 
         let mut pairs = Vec::new();
         for pair in pair_iter {
@@ -594,57 +753,232 @@ impl Database {
 
     /// 根据交易对ID查找特定交易对 - 直接数据库操作
     pub fn find_pair_by_id(&self, pair_id: &str) -> Result<Option<PairData>> {
-        use crate::thegraph::TokenInfo;
-        
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
+        let mut stmt = binding.prepare(&format!("SELECT {} FROM pairs WHERE id = ?", Self::pair_columns("")))?;
+
+        let mut pair_iter = stmt.query_map([pair_id], Self::pair_from_row)?;
+
+        match pair_iter.next() {
+            Some(pair) => Ok(Some(pair?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 根据token查找相关交易对（通过token_pairs反向索引） - 直接数据库操作
+    pub fn find_pairs_by_token(&self, token_id: &str) -> Result<Vec<PairData>> {
+        let binding = self.pool.get()?;
+        let mut stmt = binding.prepare(&format!(
+            "SELECT {} FROM token_pairs tp JOIN pairs p ON p.id = tp.pair_id WHERE tp.token_id = LOWER(?1)",
+            Self::pair_columns("p.")
+        ))?;
+
+        let pair_iter = stmt.query_map([token_id], Self::pair_from_row)?;
+
+        let mut pairs = Vec::new();
+        for pair in pair_iter {
+            pairs.push(pair?);
+        }
+
+        Ok(pairs)
+    }
+
+    /// 删除 `network` 上 `updated_at` 早于 `cutoff` 的交易对及其token_pairs索引，返回删除的交易对数量；
+    /// 其他网络的交易对不受影响 - 直接数据库操作
+    pub fn prune_pairs_older_than(&self, network: &str, cutoff: DateTime<Utc>) -> Result<usize> {
+        // updated_at 由 CURRENT_TIMESTAMP 写入，格式为 UTC 的 "YYYY-MM-DD HH:MM:SS"
+        let cutoff = cutoff.format("%Y-%m-%d %H:%M:%S").to_string();
+        let binding = self.pool.get()?;
+        let tx = binding.unchecked_transaction()?;
+
+        tx.execute(
+            "DELETE FROM token_pairs WHERE pair_id IN (SELECT id FROM pairs WHERE network = ?2 AND updated_at < ?1)",
+            [cutoff.as_str(), network],
+        )?;
+        let removed = tx.execute("DELETE FROM pairs WHERE network = ?2 AND updated_at < ?1", [cutoff.as_str(), network])?;
+
+        tx.commit()?;
+        info!("Pruned {} stale pairs", removed);
+        Ok(removed)
+    }
+
+    /// 在同一个事务中保存一次扫描发现的所有套利机会 - 直接数据库操作
+    pub fn save_opportunities(&self, opportunities: &[ArbitrageOpportunity]) -> Result<()> {
+        let binding = self.pool.get()?;
+        let tx = binding.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT OR REPLACE INTO opportunities
+                (id, token_pair, buy_dex, sell_dex, buy_price, sell_price, profit_percentage,
+                 estimated_profit, liquidity, gas_cost_estimate, confidence_score, timestamp, sandwich_risk)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                "#,
+            )?;
+            for opp in opportunities {
+                stmt.execute(params![
+                    opp.id,
+                    serde_json::to_string(&opp.token_pair)?,
+                    opp.buy_dex,
+                    opp.sell_dex,
+                    opp.buy_price.to_string(),
+                    opp.sell_price.to_string(),
+                    opp.profit_percentage,
+                    opp.estimated_profit.to_string(),
+                    opp.liquidity.to_string(),
+                    opp.gas_cost_estimate.to_string(),
+                    opp.confidence_score,
+                    opp.timestamp.timestamp_millis(),
+                    opp.sandwich_risk,
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// 按时间倒序加载最近的套利机会 - 直接数据库操作
+    pub fn load_recent_opportunities(&self, limit: usize) -> Result<Vec<ArbitrageOpportunity>> {
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(
             r#"
-            SELECT id, network, dex_type, protocol_type, token0_id, token0_symbol, token0_name, token0_decimals,
-                   token1_id, token1_symbol, token1_name, token1_decimals,
-                   volume_usd, reserve_usd, tx_count, reserve0, reserve1, fee_tier, sqrt_price, tick
-            FROM pairs
-            WHERE id = ?
+            SELECT id, token_pair, buy_dex, sell_dex, buy_price, sell_price, profit_percentage,
+                   estimated_profit, liquidity, gas_cost_estimate, confidence_score, timestamp, sandwich_risk
+            FROM opportunities
+            ORDER BY timestamp DESC
+            LIMIT ?1
             "#,
         )?;
 
-        let mut pair_iter = stmt.query_map([pair_id], |row| {
-            Ok(PairData {
-                id: row.get(0)?,
-                network: row.get(1)?,
-                dex_type: row.get(2)?,
-                protocol_type: row.get(3)?,
-                token0: TokenInfo {
-                    id: row.get(4)?,
-                    symbol: row.get(5)?,
-                    name: row.get(6)?,
-                    decimals: row.get(7)?,
-                },
-                token1: TokenInfo {
-                    id: row.get(8)?,
-                    symbol: row.get(9)?,
-                    name: row.get(10)?,
-                    decimals: row.get(11)?,
-                },
-                volume_usd: row.get(12)?,
-                reserve_usd: row.get(13)?,
-                tx_count: row.get(14)?,
-                reserve0: row.get(15)?,
-                reserve1: row.get(16)?,
-                fee_tier: row.get(17)?,
-                sqrt_price: row.get(18)?,
-                tick: row.get(19)?,
-            })
+        let rows = stmt.query_map([limit as i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, f64>(6)?,
+                row.get::<_, String>(7)?,
+                row.get::<_, String>(8)?,
+                row.get::<_, String>(9)?,
+                row.get::<_, f64>(10)?,
+                row.get::<_, i64>(11)?,
+                row.get::<_, f64>(12)?,
+            ))
         })?;
 
-        match pair_iter.next() {
-            Some(pair) => Ok(Some(pair?)),
-            None => Ok(None),
+        let mut opportunities = Vec::new();
+        for row in rows {
+            let (id, token_pair, buy_dex, sell_dex, buy_price, sell_price, profit_percentage,
+                 estimated_profit, liquidity, gas_cost_estimate, confidence_score, timestamp, sandwich_risk) = row?;
+
+            opportunities.push(ArbitrageOpportunity {
+                id,
+                token_pair: serde_json::from_str(&token_pair)?,
+                buy_dex,
+                sell_dex,
+                buy_price: BigDecimal::from_str(&buy_price)?,
+                sell_price: BigDecimal::from_str(&sell_price)?,
+                profit_percentage,
+                estimated_profit: BigDecimal::from_str(&estimated_profit)?,
+                liquidity: BigDecimal::from_str(&liquidity)?,
+                gas_cost_estimate: BigDecimal::from_str(&gas_cost_estimate)?,
+                timestamp: chrono::DateTime::from_timestamp_millis(timestamp)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid opportunity timestamp: {}", timestamp))?,
+                confidence_score,
+                sandwich_risk,
+            });
+        }
+
+        Ok(opportunities)
+    }
+
+    /// 用给定的合约集合替换已保存的监听合约 - 直接数据库操作
+    pub fn save_watched_contracts(&self, contracts: &[ContractInfo]) -> Result<()> {
+        let binding = self.pool.get()?;
+        let tx = binding.unchecked_transaction()?;
+
+        tx.execute("DELETE FROM watched_contracts", [])?;
+        for contract in contracts {
+            tx.execute(
+                "INSERT OR REPLACE INTO watched_contracts (address, protocol_type, dex_type) VALUES (?1, ?2, ?3)",
+                params![format!("{:#x}", contract.address), contract.protocol_type, contract.dex_type],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 加载已保存的监听合约 - 直接数据库操作
+    pub fn load_watched_contracts(&self) -> Result<Vec<ContractInfo>> {
+        let binding = self.pool.get()?;
+        let mut stmt = binding.prepare("SELECT address, protocol_type, dex_type FROM watched_contracts ORDER BY address")?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut contracts = Vec::new();
+        for row in rows {
+            let (address, protocol_type, dex_type) = row?;
+            contracts.push(ContractInfo {
+                address: address
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid watched contract address {}: {}", address, e))?,
+                protocol_type,
+                dex_type,
+            });
         }
+
+        Ok(contracts)
+    }
+
+    /// 记录交易对价格 - 直接数据库操作
+    pub fn record_price(&self, pair_id: &str, price: &BigDecimal, ts: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.pool.get()?.execute(
+            "INSERT INTO price_history (pair_id, price, timestamp) VALUES (LOWER(?1), ?2, ?3)",
+            params![pair_id, price.to_string(), ts.timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    /// 加载交易对在 [from, to] 时间范围内的价格记录，按时间升序 - 直接数据库操作
+    pub fn load_price_history(
+        &self,
+        pair_id: &str,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<(chrono::DateTime<chrono::Utc>, BigDecimal)>> {
+        let binding = self.pool.get()?;
+        let mut stmt = binding.prepare(
+            r#"
+            SELECT timestamp, price FROM price_history
+            WHERE pair_id = LOWER(?1) AND timestamp BETWEEN ?2 AND ?3
+            ORDER BY timestamp ASC
+            "#,
+        )?;
+
+        let rows = stmt.query_map(
+            params![pair_id, from.timestamp_millis(), to.timestamp_millis()],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )?;
+
+        let mut history = Vec::new();
+        for row in rows {
+            let (timestamp, price) = row?;
+            let ts = chrono::DateTime::from_timestamp_millis(timestamp)
+                .ok_or_else(|| anyhow::anyhow!("Invalid price timestamp: {}", timestamp))?;
+            history.push((ts, BigDecimal::from_str(&price)?));
+        }
+
+        Ok(history)
     }
 
     /// 获取交易对统计信息 - 直接数据库操作
     pub fn get_pairs_stats(&self) -> Result<(usize, f64, f64)> {
-        let binding = self.conn.lock().unwrap();
+        let binding = self.pool.get()?;
         let mut stmt = binding.prepare(
             r#"
             SELECT COUNT(*) as count,
@@ -675,6 +1009,7 @@ impl Database {
 mod tests {
     use super::*;
     use crate::thegraph::{PairData, TokenInfo};
+    use crate::types::TokenPair;
 
     #[test]
     fn test_save_pairs_with_decimal_reserves() {
@@ -707,19 +1042,406 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: None,
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         };
 
         // 保存数据
         db.save_pairs(&[test_pair]).unwrap();
 
-        // 验证数据是否正确保存（reserve字段应该被转换为整数）
+        // 验证数据是否正确保存（reserve字段以整数 + scale 存储，加载时还原为原始小数）
         let saved_pairs = db.load_pairs().unwrap();
         assert_eq!(saved_pairs.len(), 1);
         
         let saved_pair = &saved_pairs[0];
         assert_eq!(saved_pair.id, "test_pair_1");
-        assert_eq!(saved_pair.reserve_usd, "5000000123456"); // 保留所有数字
-        assert_eq!(saved_pair.reserve0, "1234567890123"); // 保留所有数字
-        assert_eq!(saved_pair.reserve1, "9876543210987"); // 保留所有数字
+        assert_eq!(saved_pair.reserve_usd, "5000000.123456");
+        assert_eq!(saved_pair.reserve0, "1234567.890123");
+        assert_eq!(saved_pair.reserve1, "9876543.210987");
+        // 观测时间取自保存时写入的 updated_at
+        let age = chrono::Utc::now() - saved_pair.updated_at.unwrap();
+        assert!(age.num_seconds().abs() < 60);
+        
+        // 底层存储的仍是整数字符串
+        let stored: (String, i64) = db.pool.get().unwrap()
+            .query_row("SELECT reserve0, reserve0_scale FROM pairs WHERE id = 'test_pair_1'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(stored, ("1234567890123".to_string(), 6));
+    }
+
+    fn make_pair(id: &str, token0_id: &str, token1_id: &str) -> PairData {
+        PairData {
+            id: id.to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::UNISWAP_V2.to_string(),
+            protocol_type: protocol_types::AMM_V2.to_string(),
+            token0: TokenInfo {
+                id: token0_id.to_string(),
+                symbol: "TOKEN0".to_string(),
+                name: "Token 0".to_string(),
+                decimals: "18".to_string(),
+            },
+            token1: TokenInfo {
+                id: token1_id.to_string(),
+                symbol: "TOKEN1".to_string(),
+                name: "Token 1".to_string(),
+                decimals: "18".to_string(),
+            },
+            volume_usd: "1000".to_string(),
+            reserve_usd: "1000".to_string(),
+            tx_count: "10".to_string(),
+            reserve0: "1000".to_string(),
+            reserve1: "1000".to_string(),
+            fee_tier: "3000".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        }
+    }
+
+    fn pair_ids_for(db: &Database, token_id: &str) -> Vec<String> {
+        let mut ids: Vec<String> = db
+            .find_pairs_by_token(token_id)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_token_pairs_index() {
+        let db = Database::new(Some(":memory:")).unwrap();
+
+        db.save_pairs(&[
+            make_pair("pair_ab", "0xA", "0xb"),
+            make_pair("pair_bc", "0xb", "0xc"),
+        ])
+        .unwrap();
+
+        assert_eq!(pair_ids_for(&db, "0xa"), vec!["pair_ab"]);
+        assert_eq!(pair_ids_for(&db, "0xB"), vec!["pair_ab", "pair_bc"]);
+        assert_eq!(pair_ids_for(&db, "0xc"), vec!["pair_bc"]);
+
+        // 替换交易对时旧的token映射应被清除
+        db.save_pairs(&[make_pair("pair_ab", "0xa", "0xd")]).unwrap();
+        assert_eq!(pair_ids_for(&db, "0xb"), vec!["pair_bc"]);
+        assert_eq!(pair_ids_for(&db, "0xd"), vec!["pair_ab"]);
+    }
+
+    #[test]
+    fn test_token_pairs_backfilled_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("legacy.db");
+        let db_path = db_path.to_str().unwrap();
+
+        // 模拟索引表出现之前写入的交易对
+        {
+            let db = Database::new(Some(db_path)).unwrap();
+            db.save_pairs(&[make_pair("pair_ab", "0xA", "0xb")]).unwrap();
+            db.pool.get().unwrap().execute("DELETE FROM token_pairs", []).unwrap();
+            assert!(pair_ids_for(&db, "0xa").is_empty());
+        }
+
+        let db = Database::new(Some(db_path)).unwrap();
+        assert_eq!(pair_ids_for(&db, "0xa"), vec!["pair_ab"]);
+        assert_eq!(pair_ids_for(&db, "0xb"), vec!["pair_ab"]);
+    }
+
+    fn make_opportunity(id: &str, profit: &str, timestamp: i64) -> ArbitrageOpportunity {
+        let token = |address: &str, symbol: &str, decimals: u8| crate::types::Token {
+            address: address.to_string(),
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals,
+            chain_id: 1,
+        };
+        ArbitrageOpportunity {
+            id: id.to_string(),
+            token_pair: TokenPair {
+                token_a: token("0xa", "WETH", 18),
+                token_b: token("0xb", "USDC", 6),
+            },
+            buy_dex: "Uniswap V2".to_string(),
+            sell_dex: "SushiSwap".to_string(),
+            buy_price: BigDecimal::from_str("2000.123456789012345678").unwrap(),
+            sell_price: BigDecimal::from_str("2010.5").unwrap(),
+            profit_percentage: 0.52,
+            estimated_profit: BigDecimal::from_str(profit).unwrap(),
+            liquidity: BigDecimal::from(1_000_000),
+            gas_cost_estimate: BigDecimal::from_str("0.01").unwrap(),
+            timestamp: chrono::DateTime::from_timestamp_millis(timestamp).unwrap(),
+            confidence_score: 0.8,
+            sandwich_risk: 0.02,
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_opportunities() {
+        let db = Database::new(None).unwrap();
+        db.save_opportunities(&[make_opportunity("older", "10.377", 1_700_000_000_000)]).unwrap();
+        db.save_opportunities(&[make_opportunity("newer", "20.5", 1_700_000_060_000)]).unwrap();
+
+        let loaded = db.load_recent_opportunities(10).unwrap();
+        let ids: Vec<&str> = loaded.iter().map(|o| o.id.as_str()).collect();
+        assert_eq!(ids, vec!["newer", "older"]);
+
+        // 价格与代币对按原值还原
+        let older = &loaded[1];
+        assert_eq!(older.buy_price, BigDecimal::from_str("2000.123456789012345678").unwrap());
+        assert_eq!(older.estimated_profit, BigDecimal::from_str("10.377").unwrap());
+        assert_eq!(older.token_pair.token_b.symbol, "USDC");
+        assert_eq!(older.timestamp.timestamp_millis(), 1_700_000_000_000);
+        assert_eq!(older.sandwich_risk, 0.02);
+
+        // 一次扫描的结果整体写入
+        db.save_opportunities(&[
+            make_opportunity("batch_a", "1", 1_700_000_120_000),
+            make_opportunity("batch_b", "2", 1_700_000_120_001),
+        ])
+        .unwrap();
+        let ids: Vec<String> = db.load_recent_opportunities(2).unwrap().into_iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec!["batch_b", "batch_a"]);
+
+        assert_eq!(db.load_recent_opportunities(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_price_history_range_query() {
+        let db = Database::new(None).unwrap();
+        let at = |secs: i64| chrono::DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+
+        for (secs, price) in [(0, "100"), (60, "101.5"), (120, "102"), (180, "103")] {
+            db.record_price("0xABC", &BigDecimal::from_str(price).unwrap(), at(secs)).unwrap();
+        }
+        db.record_price("0xdef", &BigDecimal::from(1), at(60)).unwrap();
+
+        // 区间两端包含在内，区间外与其它交易对的记录被排除
+        let history = db.load_price_history("0xabc", at(60), at(120)).unwrap();
+        assert_eq!(history, vec![
+            (at(60), BigDecimal::from_str("101.5").unwrap()),
+            (at(120), BigDecimal::from(102)),
+        ]);
+
+        assert!(db.load_price_history("0xabc", at(200), at(300)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_load_and_save_pairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("concurrent.db");
+        let db = Database::with_pool_size(Some(path.to_str().unwrap()), 4).unwrap();
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let db = db.clone();
+                std::thread::spawn(move || {
+                    for j in 0..10 {
+                        let id = format!("0x{}{}", i, j);
+                        db.save_pairs(&[make_pair(&id, "0xaaa", "0xbbb")]).unwrap();
+                        db.load_pairs().unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(db.load_pairs().unwrap().len(), 80);
+    }
+
+    #[test]
+    fn test_find_token_by_address_on_chain() {
+        let db = Database::new(None).unwrap();
+        let mut platforms = std::collections::HashMap::new();
+        platforms.insert("ethereum".to_string(), Some("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string()));
+        platforms.insert("polygon-pos".to_string(), Some("0x3c499c542cef5e3811e1192ce70d8cc03d5c3359".to_string()));
+        db.save_tokens(&[Token {
+            id: "usd-coin".to_string(),
+            symbol: "usdc".to_string(),
+            name: "USDC".to_string(),
+            platforms,
+            market_cap_rank: Some(6),
+            current_price: Some(1.0),
+            market_cap: None,
+            total_volume: None,
+            price_change_percentage_24h: None,
+        }])
+        .unwrap();
+
+        let polygon_address = "0x3C499c542cEF5E3811e1192ce70d8cC03d5c3359";
+        let token = db.find_token_by_address(polygon_address, "polygon-pos").unwrap().unwrap();
+        assert_eq!(token.id, "usd-coin");
+        let token = db.find_token_by_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "ethereum").unwrap();
+        assert!(token.is_some());
+
+        // 地址只在所选链上匹配
+        assert!(db.find_token_by_address(polygon_address, "ethereum").unwrap().is_none());
+        assert!(db.find_token_by_address(polygon_address, "binance-smart-chain").unwrap().is_none());
+        // 不再按 JSON 子串模糊匹配
+        assert!(db.find_token_by_address("0x3c499c", "polygon-pos").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_token_by_address_exact_match() {
+        let db = Database::new(None).unwrap();
+        let token = |id: &str, address: &str| {
+            let mut platforms = std::collections::HashMap::new();
+            platforms.insert("ethereum".to_string(), Some(address.to_string()));
+            Token {
+                id: id.to_string(),
+                symbol: id.to_string(),
+                name: id.to_string(),
+                platforms,
+                market_cap_rank: Some(1),
+                current_price: None,
+                market_cap: None,
+                total_volume: None,
+                price_change_percentage_24h: None,
+            }
+        };
+        // 较长地址以较短地址为前缀，且先写入
+        db.save_tokens(&[token("long", "0xabcdef0123"), token("short", "0xABCDEF")]).unwrap();
+
+        assert_eq!(db.find_token_by_address("0xabcdef", "ethereum").unwrap().unwrap().id, "short");
+        assert_eq!(db.find_token_by_address("0xABCDEF0123", "ethereum").unwrap().unwrap().id, "long");
+        // 其他字段中的文本不会被当作地址匹配
+        assert!(db.find_token_by_address("ethereum", "ethereum").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_prune_pairs_older_than() {
+        let db = Database::new(None).unwrap();
+        let mut other_network = make_pair("polygon_stale", "0xd", "0xe");
+        other_network.network = "polygon-pos".to_string();
+        db.save_pairs(&[make_pair("stale", "0xa", "0xb"), make_pair("fresh", "0xb", "0xc"), other_network]).unwrap();
+        // 模拟一天前保存后再未出现在数据源中的交易对
+        db.pool
+            .get()
+            .unwrap()
+            .execute("UPDATE pairs SET updated_at = datetime('now', '-1 day') WHERE id IN ('stale', 'polygon_stale')", [])
+            .unwrap();
+
+        // 只删除本次刷新的网络上的过期交易对，其他网络的交易对保留
+        let removed = db.prune_pairs_older_than("ethereum", Utc::now() - chrono::Duration::hours(1)).unwrap();
+        assert_eq!(removed, 1);
+        let mut ids: Vec<String> = db.load_pairs().unwrap().into_iter().map(|p| p.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["fresh".to_string(), "polygon_stale".to_string()]);
+        assert_eq!(pair_ids_for(&db, "0xd"), vec!["polygon_stale"]);
+        assert!(pair_ids_for(&db, "0xa").is_empty());
+        assert_eq!(pair_ids_for(&db, "0xb"), vec!["fresh"]);
+
+        // 重新保存会刷新 updated_at，不会被删除
+        db.save_pairs(&[make_pair("fresh", "0xb", "0xc")]).unwrap();
+        assert_eq!(db.prune_pairs_older_than("ethereum", Utc::now() - chrono::Duration::hours(1)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_find_token_by_symbol_with_many_tokens() {
+        let db = Database::new(None).unwrap();
+        let tokens: Vec<Token> = (1..=500)
+            .map(|rank| Token {
+                id: format!("token-{}", rank),
+                symbol: format!("tk{}", rank),
+                name: format!("Token {}", rank),
+                platforms: std::collections::HashMap::new(),
+                market_cap_rank: Some(rank),
+                current_price: None,
+                market_cap: None,
+                total_volume: None,
+                price_change_percentage_24h: None,
+            })
+            .collect();
+        db.save_tokens(&tokens).unwrap();
+
+        assert_eq!(db.find_token_by_symbol("TK250").unwrap().unwrap().id, "token-250");
+        assert_eq!(db.find_token_by_symbol("tk7").unwrap().unwrap().id, "token-7");
+        assert!(db.find_token_by_symbol("tk501").unwrap().is_none());
+
+        let top: Vec<String> = db.load_tokens(Some(3)).unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(top, vec!["token-1", "token-2", "token-3"]);
+
+        // 查询走符号索引而不是全表扫描
+        let plan: String = db
+            .pool
+            .get()
+            .unwrap()
+            .query_row("EXPLAIN QUERY PLAN SELECT id FROM tokens WHERE symbol = 'TK1' COLLATE NOCASE", [], |row| row.get(3))
+            .unwrap();
+        assert!(plan.contains("idx_tokens_symbol"), "{}", plan);
+    }
+
+    #[test]
+    fn test_stableswap_fields_round_trip() {
+        let db = Database::new(None).unwrap();
+        let mut pair = make_pair("curve_3pool", "0xa", "0xb");
+        pair.dex_type = dex_types::CURVE.to_string();
+        pair.amp_coefficient = Some(2000);
+        pair.balances = vec!["1000000.5".to_string(), "990000".to_string(), "1010000".to_string()];
+        db.save_pairs(&[pair.clone(), make_pair("plain", "0xc", "0xd")]).unwrap();
+
+        let loaded = db.find_pair_by_id("curve_3pool").unwrap().unwrap();
+        assert_eq!(loaded.amp_coefficient, Some(2000));
+        assert_eq!(loaded.balances, pair.balances);
+        // 从数据库加载的 Curve 交易对同样按 StableSwap 曲线定价
+        assert_eq!(
+            crate::price_calculator::PriceCalculator::calculate_price_from_pair(&loaded).unwrap(),
+            crate::price_calculator::PriceCalculator::calculate_price_from_pair(&pair).unwrap()
+        );
+
+        let plain = db.find_pair_by_id("plain").unwrap().unwrap();
+        assert_eq!(plain.amp_coefficient, None);
+        assert!(plain.balances.is_empty());
+    }
+
+    #[test]
+    fn test_v3_liquidity_round_trip() {
+        let db = Database::new(None).unwrap();
+        let mut pair = make_pair("v3_pool", "0xa", "0xb");
+        pair.protocol_type = protocol_types::AMM_V3.to_string();
+        pair.tick = Some("200000".to_string());
+        pair.liquidity = Some("20000000000000000000".to_string());
+        db.save_pairs(&[pair]).unwrap();
+
+        // 从数据库加载的 V3 池仍可估算活跃流动性
+        let loaded = db.find_pair_by_id("v3_pool").unwrap().unwrap();
+        assert_eq!(loaded.liquidity.as_deref(), Some("20000000000000000000"));
+        assert!(crate::price_calculator::PriceCalculator::v3_active_liquidity_usd(&loaded).is_some());
+    }
+
+    #[test]
+    fn test_memory_path_shares_single_connection() {
+        // 即使请求多连接，`:memory:` 也只能看到同一个数据库
+        let db = Database::with_pool_size(Some(":memory:"), 4).unwrap();
+        db.save_tokens(&[Token {
+            id: "weth".to_string(),
+            symbol: "weth".to_string(),
+            name: "Wrapped Ether".to_string(),
+            platforms: std::collections::HashMap::new(),
+            market_cap_rank: Some(1),
+            current_price: None,
+            market_cap: None,
+            total_volume: None,
+            price_change_percentage_24h: None,
+        }])
+        .unwrap();
+        assert_eq!(db.load_tokens(None).unwrap().len(), 1);
+
+        // 统计查询在单连接池上不会等待自身持有的连接
+        assert_eq!(db.get_token_stats().unwrap().0, 1);
+        assert_eq!(db.get_stats().unwrap().0, 1);
     }
 }
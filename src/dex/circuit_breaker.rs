@@ -0,0 +1,135 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// 连续失败达到该次数后断开
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// 断开后跳过请求的冷却时间
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// 正常放行请求
+    Closed,
+    /// 冷却中，跳过所有请求
+    Open,
+    /// 冷却结束，只放行一次试探请求
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// 指标中使用的数值：0 为关闭，1 为半开，2 为断开
+    pub fn as_gauge(&self) -> u64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        }
+    }
+}
+
+/// 单个 DEX 提供者的熔断器：连续失败达到阈值后在冷却时间内跳过请求，冷却结束后半开试探一次
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    state: BreakerState,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            consecutive_failures: 0,
+            state: BreakerState::Closed,
+            opened_at: None,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// 是否放行本次请求；冷却结束时转为半开并放行一次试探请求
+    pub fn allow_request(&mut self, now: Instant) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            BreakerState::HalfOpen => false,
+            BreakerState::Open => {
+                let cooled_down = self.opened_at.is_none_or(|opened_at| now.duration_since(opened_at) >= self.cooldown);
+                if cooled_down {
+                    self.state = BreakerState::HalfOpen;
+                }
+                cooled_down
+            }
+        }
+    }
+
+    /// 记录一次成功请求，熔断器恢复关闭
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.state = BreakerState::Closed;
+        self.opened_at = None;
+    }
+
+    /// 记录一次失败请求；达到阈值或半开试探失败时断开，返回本次是否新断开
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures += 1;
+        let trip = self.state == BreakerState::HalfOpen || self.consecutive_failures >= self.failure_threshold;
+        if trip && self.state != BreakerState::Open {
+            self.state = BreakerState::Open;
+            self.opened_at = Some(now);
+            return true;
+        }
+        false
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_FAILURE_THRESHOLD, DEFAULT_COOLDOWN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_state_transitions() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(10));
+
+        // 未达到阈值前放行，中间一次成功会清零计数
+        assert!(!breaker.record_failure(start));
+        breaker.record_success();
+        assert!(!breaker.record_failure(start));
+        assert!(!breaker.record_failure(start));
+        assert!(breaker.allow_request(start));
+        assert!(breaker.record_failure(start));
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // 冷却期内跳过，冷却结束后只放行一次试探
+        assert!(!breaker.allow_request(start + Duration::from_secs(9)));
+        assert!(breaker.allow_request(start + Duration::from_secs(10)));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(!breaker.allow_request(start + Duration::from_secs(10)));
+
+        // 试探失败立即重新断开，试探成功则恢复
+        assert!(breaker.record_failure(start + Duration::from_secs(10)));
+        assert!(!breaker.allow_request(start + Duration::from_secs(15)));
+        assert!(breaker.allow_request(start + Duration::from_secs(20)));
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow_request(start + Duration::from_secs(20)));
+    }
+}
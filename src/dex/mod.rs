@@ -1,13 +1,21 @@
 pub mod uniswap;
+pub mod uniswap_v3;
 pub mod sushiswap;
 pub mod pancakeswap;
 pub mod curve;
 pub mod balancer;
+pub mod circuit_breaker;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use ethers::providers::Middleware;
 use crate::types::{Pool, Price, TokenPair};
+use circuit_breaker::{BreakerState, CircuitBreaker};
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 #[async_trait]
 pub trait DexProvider {
@@ -26,6 +34,24 @@ pub trait DexProvider {
     /// 获取多个代币对的价格
     async fn get_prices(&self, token_pairs: &[TokenPair]) -> Result<HashMap<TokenPair, Price>>;
     
+    /// 获取代币对的原始储备量 (reserve_a, reserve_b)，顺序与 token_a/token_b 一致；
+    /// 无法提供储备量的 DEX 返回 None；供库的调用方做按数量的模拟，命令行本身不调用
+    #[allow(dead_code)]
+    async fn get_pair_reserves(&self, _token_pair: &TokenPair) -> Result<Option<(BigDecimal, BigDecimal)>> {
+        Ok(None)
+    }
+    
+    /// 按输入的 token_a 数量报价可得的 token_b 数量；默认按现货价格扣除手续费估算，
+    /// 能获取储备量的 DEX 按自身的定价曲线计算；供库的调用方在执行前校验路径，命令行本身不调用
+    #[allow(dead_code)]
+    async fn quote_amount_out(&self, token_pair: &TokenPair, amount_in: &BigDecimal) -> Result<Option<BigDecimal>> {
+        let fee_multiplier = BigDecimal::from_str(&(1.0 - self.get_fee_percentage()).to_string())?;
+        Ok(self
+            .get_price(token_pair)
+            .await?
+            .map(|price| amount_in * &price.price * fee_multiplier))
+    }
+    
     /// 获取指定池的详细信息
     async fn get_pool_info(&self, pool_id: &str) -> Result<Option<Pool>>;
     
@@ -36,40 +62,121 @@ pub trait DexProvider {
     fn get_fee_percentage(&self) -> f64;
 }
 
+/// 检查 RPC 节点的链 ID 是否与配置一致，不一致时返回节点实际的链 ID；
+/// 节点无法返回合法的链 ID 时返回错误，由调用方当作未知处理
+pub async fn check_chain_id<M: Middleware>(provider: &M, expected_chain_id: u64) -> Result<Option<u64>> {
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .map_err(|e| anyhow!("Failed to get chain id: {}", e))?
+        .as_u64();
+
+    if chain_id == expected_chain_id {
+        Ok(None)
+    } else {
+        Ok(Some(chain_id))
+    }
+}
+
+/// DEX 提供者的唯一标识：(链 ID, DEX 名称)，同一 DEX 可以在多条链上同时运行
+pub type DexKey = (u64, String);
+
+/// 一次跨 DEX 价格查询的结果：成功的报价与失败 DEX 的错误信息分开保存
+#[derive(Debug, Default)]
+pub struct PriceFetchReport {
+    pub prices: HashMap<DexKey, HashMap<TokenPair, Price>>,
+    pub errors: HashMap<DexKey, String>,
+}
+
+impl PriceFetchReport {
+    /// 成功返回价格的 DEX 数量
+    pub fn responded(&self) -> usize {
+        self.prices.len()
+    }
+
+    /// 参与查询的 DEX 总数
+    pub fn total(&self) -> usize {
+        self.prices.len() + self.errors.len()
+    }
+}
+
 pub struct DexManager {
-    providers: HashMap<String, Box<dyn DexProvider + Send + Sync>>,
+    providers: HashMap<DexKey, Box<dyn DexProvider + Send + Sync>>,
+    /// 新提供者使用的熔断器参数
+    breaker_template: CircuitBreaker,
+    /// 每个提供者的价格查询熔断器
+    breakers: Mutex<HashMap<DexKey, CircuitBreaker>>,
 }
 
 impl DexManager {
     pub fn new() -> Self {
         Self {
             providers: HashMap::new(),
+            breaker_template: CircuitBreaker::default(),
+            breakers: Mutex::new(HashMap::new()),
         }
     }
     
+    /// 设置熔断参数：连续失败 `failure_threshold` 次后在 `cooldown` 内跳过该提供者
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.breaker_template = CircuitBreaker::new(failure_threshold, cooldown);
+        self
+    }
+    
+    /// 每个提供者当前的熔断状态，尚未请求过的提供者视为关闭
+    pub fn circuit_states(&self) -> HashMap<DexKey, BreakerState> {
+        let breakers = self.breakers.lock().unwrap();
+        self.providers
+            .keys()
+            .map(|key| (key.clone(), breakers.get(key).map_or(BreakerState::Closed, CircuitBreaker::state)))
+            .collect()
+    }
+    
     pub fn add_provider(&mut self, provider: Box<dyn DexProvider + Send + Sync>) {
-        let name = provider.name().to_string();
-        self.providers.insert(name, provider);
+        let key = (provider.chain_id(), provider.name().to_string());
+        self.providers.insert(key, provider);
     }
     
-    pub fn get_provider(&self, name: &str) -> Option<&Box<dyn DexProvider + Send + Sync>> {
-        self.providers.get(name)
+    pub fn remove_provider(&mut self, key: &DexKey) -> Option<Box<dyn DexProvider + Send + Sync>> {
+        self.providers.remove(key)
     }
     
-    pub fn get_all_providers(&self) -> &HashMap<String, Box<dyn DexProvider + Send + Sync>> {
+    /// 并发执行所有提供者的健康检查，返回每个提供者的结果
+    pub async fn health_check_all(&self) -> HashMap<DexKey, Result<bool>> {
+        let checks = self.providers.iter().map(|(key, provider)| async move {
+            (key.clone(), provider.health_check().await)
+        });
+        futures::future::join_all(checks).await.into_iter().collect()
+    }
+    
+    pub fn get_provider(&self, chain_id: u64, name: &str) -> Option<&Box<dyn DexProvider + Send + Sync>> {
+        self.providers.get(&(chain_id, name.to_string()))
+    }
+    
+    pub fn get_all_providers(&self) -> &HashMap<DexKey, Box<dyn DexProvider + Send + Sync>> {
         &self.providers
     }
     
-    pub async fn get_all_pools(&self) -> Result<HashMap<String, Vec<Pool>>> {
+    /// 获取指定链上的所有提供者；供库的调用方按链使用，监控本身按 DexKey 统一分组，不调用
+    #[allow(dead_code)]
+    pub fn get_providers_for_chain(&self, chain_id: u64) -> Vec<&(dyn DexProvider + Send + Sync)> {
+        self.providers
+            .iter()
+            .filter(|((provider_chain_id, _), _)| *provider_chain_id == chain_id)
+            .map(|(_, provider)| provider.as_ref())
+            .collect()
+    }
+    
+    pub async fn get_all_pools(&self) -> Result<HashMap<DexKey, Vec<Pool>>> {
         let mut all_pools = HashMap::new();
         
-        for (name, provider) in &self.providers {
+        for (key, provider) in &self.providers {
             match provider.get_pools().await {
                 Ok(pools) => {
-                    all_pools.insert(name.clone(), pools);
+                    all_pools.insert(key.clone(), pools);
                 }
                 Err(e) => {
-                    log::warn!("Failed to get pools from {}: {}", name, e);
+                    log::warn!("Failed to get pools from {} (chain {}): {}", key.1, key.0, e);
                 }
             }
         }
@@ -77,20 +184,334 @@ impl DexManager {
         Ok(all_pools)
     }
     
-    pub async fn get_prices_from_all_dexes(&self, token_pairs: &[TokenPair]) -> Result<HashMap<String, HashMap<TokenPair, Price>>> {
-        let mut all_prices = HashMap::new();
+    /// 从所有链上的所有 DEX 获取价格，同时返回每个失败 DEX 的错误信息
+    pub async fn get_prices_from_all_dexes_detailed(&self, token_pairs: &[TokenPair]) -> Result<PriceFetchReport> {
+        let mut report = PriceFetchReport::default();
         
-        for (name, provider) in &self.providers {
-            match provider.get_prices(token_pairs).await {
+        // 各提供者并发请求，总耗时取决于最慢的 DEX；限流仍由各提供者自行控制
+        let fetches = self.providers.iter().map(|(key, provider)| async move {
+            (key, self.fetch_prices(key, provider.as_ref(), token_pairs).await)
+        });
+        for (key, result) in futures::future::join_all(fetches).await {
+            match result {
                 Ok(prices) => {
-                    all_prices.insert(name.clone(), prices);
+                    report.prices.insert(key.clone(), prices);
                 }
                 Err(e) => {
-                    log::warn!("Failed to get prices from {}: {}", name, e);
+                    report.errors.insert(key.clone(), e.to_string());
                 }
             }
         }
         
-        Ok(all_prices)
+        Ok(report)
     }
-}
\ No newline at end of file
+    
+    /// 只从指定链上的 DEX 获取价格，结果按 DEX 名称区分；供库的调用方按链查询，
+    /// 监控需要失败详情，使用 `get_prices_from_all_dexes_detailed`
+    #[allow(dead_code)]
+    pub async fn get_prices_from_chain(&self, chain_id: u64, token_pairs: &[TokenPair]) -> Result<HashMap<String, HashMap<TokenPair, Price>>> {
+        let fetches = self
+            .providers
+            .iter()
+            .filter(|((provider_chain_id, _), _)| *provider_chain_id == chain_id)
+            .map(|(key, provider)| async move {
+                (key.1.clone(), self.fetch_prices(key, provider.as_ref(), token_pairs).await)
+            });
+        
+        Ok(futures::future::join_all(fetches)
+            .await
+            .into_iter()
+            .filter_map(|(name, result)| result.ok().map(|prices| (name, prices)))
+            .collect())
+    }
+    
+    /// 获取单个 DEX 的价格，失败时记录日志和指标；熔断器断开时直接跳过请求
+    async fn fetch_prices(
+        &self,
+        key: &DexKey,
+        provider: &(dyn DexProvider + Send + Sync),
+        token_pairs: &[TokenPair],
+    ) -> Result<HashMap<TokenPair, Price>> {
+        if !self.allow_request(key) {
+            return Err(anyhow!("circuit breaker open for {} (chain {})", key.1, key.0));
+        }
+        
+        let result = provider.get_prices(token_pairs).await;
+        if let Err(e) = &result {
+            log::warn!("Failed to get prices from {} (chain {}): {}", provider.name(), provider.chain_id(), e);
+            crate::metrics::global().inc_dex_price_fetch_error(provider.name());
+        }
+        self.record_outcome(key, result.is_ok());
+        result
+    }
+    
+    /// 查询熔断器是否放行对该提供者的请求
+    fn allow_request(&self, key: &DexKey) -> bool {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(key.clone()).or_insert_with(|| self.breaker_template.clone());
+        let was_open = breaker.state() == BreakerState::Open;
+        let allowed = breaker.allow_request(Instant::now());
+        if was_open && allowed {
+            log::info!("Circuit breaker half-open for {} (chain {}), retrying one request", key.1, key.0);
+            crate::metrics::global().set_dex_circuit_state(key.0, &key.1, breaker.state().as_gauge());
+        }
+        allowed
+    }
+    
+    /// 记录请求结果并更新熔断状态
+    fn record_outcome(&self, key: &DexKey, success: bool) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(key.clone()).or_insert_with(|| self.breaker_template.clone());
+        if success {
+            breaker.record_success();
+        } else if breaker.record_failure(Instant::now()) {
+            log::warn!("Circuit breaker open for {} (chain {}), skipping it for {:?}", key.1, key.0, self.breaker_template.cooldown());
+        }
+        crate::metrics::global().set_dex_circuit_state(key.0, &key.1, breaker.state().as_gauge());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use ethers::providers::Provider;
+    use ethers::types::U64;
+    use std::time::{Duration, Instant};
+
+    /// 固定返回同一价格的测试提供者，`fail` 为真时所有查询都返回错误
+    struct FixedPriceProvider {
+        name: String,
+        chain_id: u64,
+        price: BigDecimal,
+        fail: bool,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl DexProvider for FixedPriceProvider {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn chain_id(&self) -> u64 {
+            self.chain_id
+        }
+
+        async fn get_pools(&self) -> Result<Vec<Pool>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_price(&self, token_pair: &TokenPair) -> Result<Option<Price>> {
+            Ok(Some(Price {
+                token_pair: token_pair.clone(),
+                price: self.price.clone(),
+                liquidity: BigDecimal::from(1000),
+                dex: self.name.clone(),
+                timestamp: Utc::now(),
+                block_number: None,
+            }))
+        }
+
+        async fn get_prices(&self, token_pairs: &[TokenPair]) -> Result<HashMap<TokenPair, Price>> {
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                return Err(anyhow!("{} unavailable", self.name));
+            }
+            let mut prices = HashMap::new();
+            for token_pair in token_pairs {
+                if let Some(price) = self.get_price(token_pair).await? {
+                    prices.insert(token_pair.clone(), price);
+                }
+            }
+            Ok(prices)
+        }
+
+        async fn get_pool_info(&self, _pool_id: &str) -> Result<Option<Pool>> {
+            Ok(None)
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            if self.fail {
+                return Err(anyhow!("{} unreachable", self.name));
+            }
+            Ok(true)
+        }
+
+        fn get_fee_percentage(&self) -> f64 {
+            0.003
+        }
+    }
+
+    fn token_pair() -> TokenPair {
+        let token = |symbol: &str| crate::types::Token {
+            address: format!("0x{}", symbol),
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals: 18,
+            chain_id: 1,
+        };
+        TokenPair {
+            token_a: token("WETH"),
+            token_b: token("USDT"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_providers_isolated_by_chain() {
+        let mut manager = DexManager::new();
+        for (chain_id, price) in [(1, 2000), (56, 2100)] {
+            manager.add_provider(Box::new(FixedPriceProvider {
+                name: "uniswap_v2".to_string(),
+                chain_id,
+                price: BigDecimal::from(price),
+                fail: false,
+                delay: Duration::ZERO,
+            }));
+        }
+
+        // 同名 DEX 在不同链上不会互相覆盖
+        assert_eq!(manager.get_all_providers().len(), 2);
+        assert_eq!(manager.get_providers_for_chain(56).len(), 1);
+        assert!(manager.get_providers_for_chain(137).is_empty());
+        assert_eq!(manager.get_provider(56, "uniswap_v2").unwrap().chain_id(), 56);
+
+        let pairs = vec![token_pair()];
+        let bsc_prices = manager.get_prices_from_chain(56, &pairs).await.unwrap();
+        assert_eq!(bsc_prices.len(), 1);
+        assert_eq!(bsc_prices["uniswap_v2"][&pairs[0]].price, BigDecimal::from(2100));
+
+        let all_prices = manager.get_prices_from_all_dexes_detailed(&pairs).await.unwrap().prices;
+        assert_eq!(all_prices.len(), 2);
+        assert_eq!(all_prices[&(1, "uniswap_v2".to_string())][&pairs[0]].price, BigDecimal::from(2000));
+
+        // 未实现储备量查询的提供者默认返回 None
+        let provider = manager.get_provider(1, "uniswap_v2").unwrap();
+        assert!(provider.get_pair_reserves(&pairs[0]).await.unwrap().is_none());
+
+        // 默认报价按现货价格扣除 0.3% 手续费：2 × 2000 × 0.997
+        let quote = provider.quote_amount_out(&pairs[0], &BigDecimal::from(2)).await.unwrap();
+        assert_eq!(quote, Some(BigDecimal::from(3988)));
+    }
+
+    #[tokio::test]
+    async fn test_prices_fetched_concurrently() {
+        let mut manager = DexManager::new();
+        for name in ["uniswap_v2", "sushiswap"] {
+            manager.add_provider(Box::new(FixedPriceProvider {
+                name: name.to_string(),
+                chain_id: 1,
+                price: BigDecimal::from(2000),
+                fail: false,
+                delay: Duration::from_millis(300),
+            }));
+        }
+
+        let started = Instant::now();
+        let prices = manager.get_prices_from_all_dexes_detailed(&[token_pair()]).await.unwrap().prices;
+        let elapsed = started.elapsed();
+
+        // 两个各耗时 300ms 的 DEX 并发请求，总耗时接近 300ms 而不是 600ms
+        assert_eq!(prices.len(), 2);
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed < Duration::from_millis(550), "took {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_all() {
+        let mut manager = DexManager::new();
+        for (name, fail) in [("uniswap_v2", false), ("sushiswap", true)] {
+            manager.add_provider(Box::new(FixedPriceProvider {
+                name: name.to_string(),
+                chain_id: 1,
+                price: BigDecimal::from(2000),
+                fail,
+                delay: Duration::ZERO,
+            }));
+        }
+
+        let results = manager.health_check_all().await;
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[&(1, "uniswap_v2".to_string())], Ok(true)));
+        assert_eq!(
+            results[&(1, "sushiswap".to_string())].as_ref().unwrap_err().to_string(),
+            "sushiswap unreachable"
+        );
+
+        assert!(manager.remove_provider(&(1, "sushiswap".to_string())).is_some());
+        assert_eq!(manager.get_all_providers().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_detailed_prices_report_failures() {
+        let mut manager = DexManager::new();
+        for (name, fail) in [("uniswap_v2", false), ("sushiswap", true)] {
+            manager.add_provider(Box::new(FixedPriceProvider {
+                name: name.to_string(),
+                chain_id: 1,
+                price: BigDecimal::from(2000),
+                fail,
+                delay: Duration::ZERO,
+            }));
+        }
+
+        let pairs = vec![token_pair()];
+        let report = manager.get_prices_from_all_dexes_detailed(&pairs).await.unwrap();
+        assert_eq!((report.responded(), report.total()), (1, 2));
+        assert!(report.prices.contains_key(&(1, "uniswap_v2".to_string())));
+        assert_eq!(report.errors[&(1, "sushiswap".to_string())], "sushiswap unavailable");
+        assert_eq!(report.prices.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_skips_failing_provider() {
+        let mut manager = DexManager::new().with_circuit_breaker(2, Duration::from_millis(200));
+        for (name, fail) in [("uniswap_v2", false), ("sushiswap", true)] {
+            manager.add_provider(Box::new(FixedPriceProvider {
+                name: name.to_string(),
+                chain_id: 1,
+                price: BigDecimal::from(2000),
+                fail,
+                delay: Duration::ZERO,
+            }));
+        }
+        let sushiswap = (1, "sushiswap".to_string());
+        let pairs = vec![token_pair()];
+
+        // 连续两次失败后断开
+        for _ in 0..2 {
+            let report = manager.get_prices_from_all_dexes_detailed(&pairs).await.unwrap();
+            assert_eq!(report.errors[&sushiswap], "sushiswap unavailable");
+        }
+        assert_eq!(manager.circuit_states()[&sushiswap], BreakerState::Open);
+        assert_eq!(manager.circuit_states()[&(1, "uniswap_v2".to_string())], BreakerState::Closed);
+
+        // 冷却期内不再请求该提供者，其他提供者不受影响
+        let report = manager.get_prices_from_all_dexes_detailed(&pairs).await.unwrap();
+        assert_eq!(report.errors[&sushiswap], "circuit breaker open for sushiswap (chain 1)");
+        assert_eq!(report.responded(), 1);
+        assert!(manager.get_prices_from_chain(1, &pairs).await.unwrap().contains_key("uniswap_v2"));
+
+        // 冷却结束后半开试探一次，仍失败则重新断开
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let report = manager.get_prices_from_all_dexes_detailed(&pairs).await.unwrap();
+        assert_eq!(report.errors[&sushiswap], "sushiswap unavailable");
+        assert_eq!(manager.circuit_states()[&sushiswap], BreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_check_chain_id() {
+        let (provider, mock) = Provider::mocked();
+
+        // 配置主网，但节点返回 Sepolia
+        mock.push(U64::from(11155111u64)).unwrap();
+        assert_eq!(check_chain_id(&provider, 1).await.unwrap(), Some(11155111));
+
+        mock.push(U64::from(1u64)).unwrap();
+        assert_eq!(check_chain_id(&provider, 1).await.unwrap(), None);
+
+        // 非 RPC 的响应无法确定链 ID，不能当作不一致
+        mock.push(serde_json::json!({ "success": true })).unwrap();
+        assert!(check_chain_id(&provider, 1).await.is_err());
+    }
+}
@@ -20,6 +20,48 @@ use crate::dex::DexProvider;
 use crate::types::{Pool, Price, Token, TokenPair};
 use crate::utils::{adjust_for_decimals, str_to_bigdecimal};
 
+/// PancakeSwap V2 Factory 合约地址 (BSC)，未配置 factory_address 时使用
+const DEFAULT_FACTORY_ADDRESS: &str = "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73";
+
+/// PancakeSwap V2 的标准费率 0.25%
+const PANCAKESWAP_FEE_PERCENTAGE: f64 = 0.0025;
+
+/// PancakeSwap V2 Factory ABI (与Uniswap V2兼容)
+const FACTORY_ABI: &str = r#"[
+    {
+        "constant": true,
+        "inputs": [
+            {"name": "tokenA", "type": "address"},
+            {"name": "tokenB", "type": "address"}
+        ],
+        "name": "getPair",
+        "outputs": [{"name": "pair", "type": "address"}],
+        "type": "function"
+    }
+]"#;
+
+/// PancakeSwap V2 Pair ABI (与Uniswap V2兼容)
+const PAIR_ABI: &str = r#"[
+    {
+        "constant": true,
+        "inputs": [],
+        "name": "getReserves",
+        "outputs": [
+            {"name": "reserve0", "type": "uint112"},
+            {"name": "reserve1", "type": "uint112"},
+            {"name": "blockTimestampLast", "type": "uint32"}
+        ],
+        "type": "function"
+    },
+    {
+        "constant": true,
+        "inputs": [],
+        "name": "token0",
+        "outputs": [{"name": "", "type": "address"}],
+        "type": "function"
+    }
+]"#;
+
 pub struct PancakeSwapProvider {
     config: DexConfig,
     client: Client,
@@ -35,7 +77,7 @@ impl PancakeSwapProvider {
             .build()
             .expect("Failed to create HTTP client");
         
-        // 创建Web3提供者
+        // 创建Web3提供者（BSC RPC，来自 DexConfig.api_url）
         let provider = Provider::<Http>::try_from(&config.api_url)
             .expect("Failed to create Web3 provider");
         let web3_provider = Arc::new(provider);
@@ -44,49 +86,30 @@ impl PancakeSwapProvider {
     }
     
     async fn get_price_from_blockchain(&self, token_pair: &TokenPair) -> Result<Option<Price>> {
-        // PancakeSwap V2 Factory 合约地址 (BSC)
-        let factory_address: Address = "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73".parse()?;
-        
-        // PancakeSwap V2 Factory ABI (简化版，只包含getPair函数)
-        let factory_abi: Abi = serde_json::from_str(r#"[
-            {
-                "constant": true,
-                "inputs": [
-                    {"name": "tokenA", "type": "address"},
-                    {"name": "tokenB", "type": "address"}
-                ],
-                "name": "getPair",
-                "outputs": [{"name": "pair", "type": "address"}],
-                "type": "function"
-            }
-        ]"#)?;
-        
-        // Pair合约ABI (简化版，只包含getReserves函数)
-        let pair_abi: Abi = serde_json::from_str(r#"[
-            {
-                "constant": true,
-                "inputs": [],
-                "name": "getReserves",
-                "outputs": [
-                    {"name": "reserve0", "type": "uint112"},
-                    {"name": "reserve1", "type": "uint112"},
-                    {"name": "blockTimestampLast", "type": "uint32"}
-                ],
-                "type": "function"
-            }
-        ]"#)?;
+        let factory_address = Address::from_str(
+            self.config.factory_address.as_deref().unwrap_or(DEFAULT_FACTORY_ADDRESS),
+        )
+        .map_err(|e| anyhow!("Invalid factory address: {}", e))?;
         
-        let token0_address: Address = token_pair.token_a.address.parse()?;
-        let token1_address: Address = token_pair.token_b.address.parse()?;
+        let factory_abi: Abi = serde_json::from_str(FACTORY_ABI)
+            .map_err(|e| anyhow!("Invalid factory ABI: {}", e))?;
+        let pair_abi: Abi = serde_json::from_str(PAIR_ABI)
+            .map_err(|e| anyhow!("Invalid pair ABI: {}", e))?;
+        
+        let token_a = Address::from_str(&token_pair.token_a.address)
+            .map_err(|e| anyhow!("Invalid token A address: {}", e))?;
+        let token_b = Address::from_str(&token_pair.token_b.address)
+            .map_err(|e| anyhow!("Invalid token B address: {}", e))?;
         
         // 创建Factory合约实例
         let factory_contract = Contract::new(factory_address, factory_abi, self.web3_provider.clone());
         
         // 调用getPair获取配对合约地址
         let pair_address: Address = factory_contract
-            .method::<_, Address>("getPair", (token0_address, token1_address))?
+            .method::<_, Address>("getPair", (token_a, token_b))?
             .call()
-            .await?;
+            .await
+            .map_err(|e| anyhow!("Failed to get pair address: {}", e))?;
         
         // 检查配对是否存在
         if pair_address == Address::zero() {
@@ -97,45 +120,63 @@ impl PancakeSwapProvider {
         // 创建Pair合约实例
         let pair_contract = Contract::new(pair_address, pair_abi, self.web3_provider.clone());
         
+        // 获取 token0 地址以确定储备量顺序
+        let token0: Address = pair_contract
+            .method::<_, Address>("token0", ())?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to get token0: {}", e))?;
+        
         // 调用getReserves获取储备量
         let (reserve0, reserve1, _): (U256, U256, u32) = pair_contract
             .method::<_, (U256, U256, u32)>("getReserves", ())?
             .call()
-            .await?;
+            .await
+            .map_err(|e| anyhow!("Failed to get reserves: {}", e))?;
+        
+        let price = self.price_from_reserves(token_pair, token_a, token0, reserve0, reserve1)?;
+        if let Some(price) = &price {
+            log::debug!("PancakeSwap: 从区块链获取价格 {}/{} = {}", 
+                       token_pair.token_a.symbol, token_pair.token_b.symbol, price.price);
+        }
+        
+        Ok(price)
+    }
+    
+    /// 根据储备量计算 token_b / token_a 的价格
+    fn price_from_reserves(
+        &self,
+        token_pair: &TokenPair,
+        token_a: Address,
+        token0: Address,
+        reserve0: U256,
+        reserve1: U256,
+    ) -> Result<Option<Price>> {
+        // 确定哪个储备量对应哪个代币
+        let (reserve_a, reserve_b) = if token0 == token_a {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        };
         
-        if reserve0.is_zero() || reserve1.is_zero() {
+        if reserve_a.is_zero() || reserve_b.is_zero() {
             log::warn!("PancakeSwap: 代币对 {}/{} 储备量为零", token_pair.token_a.symbol, token_pair.token_b.symbol);
             return Ok(None);
         }
         
         // 转换为BigDecimal并调整小数位
-        let reserve0_decimal = BigDecimal::from_str(&reserve0.to_string())?;
-        let reserve1_decimal = BigDecimal::from_str(&reserve1.to_string())?;
-        
-        let adjusted_reserve0 = adjust_for_decimals(&reserve0_decimal, token_pair.token_a.decimals);
-        let adjusted_reserve1 = adjust_for_decimals(&reserve1_decimal, token_pair.token_b.decimals);
+        let adjusted_reserve_a = adjust_for_decimals(&BigDecimal::from_str(&reserve_a.to_string())?, token_pair.token_a.decimals);
+        let adjusted_reserve_b = adjust_for_decimals(&BigDecimal::from_str(&reserve_b.to_string())?, token_pair.token_b.decimals);
         
-        // 计算价格 (token1/token0)
-        let price_value = &adjusted_reserve1 / &adjusted_reserve0;
-        
-        let price = Price {
+        Ok(Some(Price {
             token_pair: token_pair.clone(),
-            price: price_value,
-            liquidity: &adjusted_reserve0 + &adjusted_reserve1,
-            dex: "PancakeSwap".to_string(),
+            price: &adjusted_reserve_b / &adjusted_reserve_a,
+            liquidity: &adjusted_reserve_a + &adjusted_reserve_b,
+            dex: self.name().to_string(),
             timestamp: Utc::now(),
             block_number: None,
-        };
-        
-        log::debug!("PancakeSwap: 从区块链获取价格 {}/{} = {}", 
-                   token_pair.token_a.symbol, token_pair.token_b.symbol, price.price);
-        
-        Ok(Some(price))
+        }))
     }
-    
-
-    
-
 }
 
 #[async_trait]
@@ -185,7 +226,7 @@ impl DexProvider for PancakeSwapProvider {
             
             // 添加延迟以避免过于频繁的请求
             if i < token_pairs.len() - 1 {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                tokio::time::sleep(Duration::from_millis(self.config.rate_limit_ms)).await;
             }
         }
         
@@ -217,6 +258,65 @@ impl DexProvider for PancakeSwapProvider {
     }
     
     fn get_fee_percentage(&self) -> f64 {
-        0.0025 // PancakeSwap 的标准费率是 0.25%
+        PANCAKESWAP_FEE_PERCENTAGE
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::ToPrimitive;
+
+    const WBNB: &str = "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c";
+    const BUSD: &str = "0xe9e7cea3dedca5984780bafc599bd69add087d56";
+
+    fn test_provider() -> PancakeSwapProvider {
+        PancakeSwapProvider::new(DexConfig {
+            name: "PancakeSwap".to_string(),
+            enabled: true,
+            api_url: "http://localhost:8545".to_string(),
+            chain_id: 56,
+            factory_address: None,
+            router_address: None,
+            subgraph_url: None,
+            rate_limit_ms: 0,
+            fee_tier: None,
+            multicall_address: None,
+            gas_units: None,
+        })
+    }
+
+    #[test]
+    fn test_provider_from_config() {
+        let provider = test_provider();
+        assert_eq!(provider.name(), "PancakeSwap");
+        assert_eq!(provider.chain_id(), 56);
+        assert_eq!(provider.get_fee_percentage(), 0.0025);
+    }
+
+    #[test]
+    fn test_price_from_reserves_respects_token0() {
+        let provider = test_provider();
+        let token_pair = TokenPair {
+            token_a: Token::new(WBNB.to_string(), "WBNB".to_string(), "Wrapped BNB".to_string(), 18, 56),
+            token_b: Token::new(BUSD.to_string(), "BUSD".to_string(), "Binance USD".to_string(), 18, 56),
+        };
+        let wbnb = Address::from_str(WBNB).unwrap();
+        let busd = Address::from_str(BUSD).unwrap();
+        let one = U256::exp10(18);
+
+        // token0 为 BUSD：储备量 600 BUSD / 1 WBNB
+        let price = provider
+            .price_from_reserves(&token_pair, wbnb, busd, one * 600, one)
+            .unwrap()
+            .unwrap();
+        assert!((price.price.to_f64().unwrap() - 600.0).abs() < 1e-9);
+        assert_eq!(price.dex, "PancakeSwap");
+
+        // 储备量为零时没有价格
+        assert!(provider
+            .price_from_reserves(&token_pair, wbnb, wbnb, U256::zero(), one)
+            .unwrap()
+            .is_none());
+    }
+}
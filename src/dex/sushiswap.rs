@@ -1,12 +1,13 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, RoundingMode};
+use num_traits::Zero;
 use chrono::Utc;
 use ethers::{
-    abi::Abi,
+    abi::{decode, Abi, ParamType, Token as AbiToken},
     contract::Contract,
     providers::{Http, Provider, Middleware},
-    types::{Address, U256},
+    types::{Address, Bytes, U256},
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -18,7 +19,73 @@ use std::time::Duration;
 use crate::config::DexConfig;
 use crate::dex::DexProvider;
 use crate::types::{Pool, Price, Token, TokenPair};
-use crate::utils::{adjust_for_decimals, str_to_bigdecimal};
+use crate::utils::{adjust_for_decimals, calculate_amount_out, str_to_bigdecimal};
+
+/// SushiSwap V2 Factory ABI (与Uniswap V2兼容)
+const FACTORY_ABI: &str = r#"[
+    {
+        "constant": true,
+        "inputs": [
+            {"name": "tokenA", "type": "address"},
+            {"name": "tokenB", "type": "address"}
+        ],
+        "name": "getPair",
+        "outputs": [{"name": "pair", "type": "address"}],
+        "type": "function"
+    }
+]"#;
+
+/// SushiSwap V2 Pair ABI (与Uniswap V2兼容)
+const PAIR_ABI: &str = r#"[
+    {
+        "constant": true,
+        "inputs": [],
+        "name": "getReserves",
+        "outputs": [
+            {"name": "reserve0", "type": "uint112"},
+            {"name": "reserve1", "type": "uint112"},
+            {"name": "blockTimestampLast", "type": "uint32"}
+        ],
+        "type": "function"
+    },
+    {
+        "constant": true,
+        "inputs": [],
+        "name": "token0",
+        "outputs": [{"name": "", "type": "address"}],
+        "type": "function"
+    }
+]"#;
+
+/// Multicall3 ABI (简化版，只包含aggregate3函数)
+const MULTICALL3_ABI: &str = r#"[
+    {
+        "inputs": [
+            {
+                "components": [
+                    {"name": "target", "type": "address"},
+                    {"name": "allowFailure", "type": "bool"},
+                    {"name": "callData", "type": "bytes"}
+                ],
+                "name": "calls",
+                "type": "tuple[]"
+            }
+        ],
+        "name": "aggregate3",
+        "outputs": [
+            {
+                "components": [
+                    {"name": "success", "type": "bool"},
+                    {"name": "returnData", "type": "bytes"}
+                ],
+                "name": "returnData",
+                "type": "tuple[]"
+            }
+        ],
+        "stateMutability": "payable",
+        "type": "function"
+    }
+]"#;
 
 pub struct SushiSwapProvider {
     config: DexConfig,
@@ -50,6 +117,16 @@ impl SushiSwapProvider {
     }
     
     async fn get_price_from_blockchain(&self, token_pair: &TokenPair) -> Result<Option<Price>> {
+        match self.fetch_raw_reserves(token_pair).await? {
+            Some((token_a, token0, reserve0, reserve1)) => {
+                self.price_from_reserves(token_pair, token_a, token0, reserve0, reserve1)
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// 查询代币对的配对合约，返回 (token_a 地址, token0 地址, reserve0, reserve1)，配对不存在时返回 None
+    async fn fetch_raw_reserves(&self, token_pair: &TokenPair) -> Result<Option<(Address, Address, U256, U256)>> {
         // 获取工厂合约地址
         let factory_address = self.config.factory_address.as_ref()
             .ok_or_else(|| anyhow!("Factory address not configured"))?;
@@ -57,21 +134,7 @@ impl SushiSwapProvider {
         let factory_addr = Address::from_str(factory_address)
             .map_err(|e| anyhow!("Invalid factory address: {}", e))?;
         
-        // SushiSwap V2 Factory ABI (与Uniswap V2兼容)
-        let factory_abi = r#"[
-            {
-                "constant": true,
-                "inputs": [
-                    {"name": "tokenA", "type": "address"},
-                    {"name": "tokenB", "type": "address"}
-                ],
-                "name": "getPair",
-                "outputs": [{"name": "pair", "type": "address"}],
-                "type": "function"
-            }
-        ]"#;
-        
-        let factory_abi: Abi = serde_json::from_str(factory_abi)
+        let factory_abi: Abi = serde_json::from_str(FACTORY_ABI)
             .map_err(|e| anyhow!("Invalid factory ABI: {}", e))?;
         
         let factory_contract = Contract::new(
@@ -98,29 +161,7 @@ impl SushiSwapProvider {
             return Ok(None);
         }
         
-        // SushiSwap V2 Pair ABI (与Uniswap V2兼容)
-        let pair_abi = r#"[
-            {
-                "constant": true,
-                "inputs": [],
-                "name": "getReserves",
-                "outputs": [
-                    {"name": "reserve0", "type": "uint112"},
-                    {"name": "reserve1", "type": "uint112"},
-                    {"name": "blockTimestampLast", "type": "uint32"}
-                ],
-                "type": "function"
-            },
-            {
-                "constant": true,
-                "inputs": [],
-                "name": "token0",
-                "outputs": [{"name": "", "type": "address"}],
-                "type": "function"
-            }
-        ]"#;
-        
-        let pair_abi: Abi = serde_json::from_str(pair_abi)
+        let pair_abi: Abi = serde_json::from_str(PAIR_ABI)
             .map_err(|e| anyhow!("Invalid pair ABI: {}", e))?;
         
         let pair_contract = Contract::new(
@@ -143,6 +184,54 @@ impl SushiSwapProvider {
             .await
             .map_err(|e| anyhow!("Failed to get reserves: {}", e))?;
         
+        Ok(Some((token_a, token0, reserve0, reserve1)))
+    }
+    
+    /// 按 token_a/token_b 的顺序排列储备量，并按各自小数位转换为代币数量
+    fn reserves_for_pair(
+        token_pair: &TokenPair,
+        token_a: Address,
+        token0: Address,
+        reserve0: U256,
+        reserve1: U256,
+    ) -> Result<(BigDecimal, BigDecimal)> {
+        let (reserve_a, reserve_b) = if token0 == token_a {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        };
+        
+        Ok((
+            adjust_for_decimals(&BigDecimal::from_str(&reserve_a.to_string())?, token_pair.token_a.decimals),
+            adjust_for_decimals(&BigDecimal::from_str(&reserve_b.to_string())?, token_pair.token_b.decimals),
+        ))
+    }
+    
+    /// 按恒定乘积公式报价，结果按 token_b 的小数位向下取整，与路由合约 getAmountsOut 一致；
+    /// 任一储备量为零时返回 None
+    fn quote_from_reserves(
+        amount_in: &BigDecimal,
+        reserve_in: &BigDecimal,
+        reserve_out: &BigDecimal,
+        fee_percentage: f64,
+        decimals_out: u8,
+    ) -> Option<BigDecimal> {
+        if reserve_in.is_zero() || reserve_out.is_zero() {
+            return None;
+        }
+        let amount_out = calculate_amount_out(amount_in, reserve_in, reserve_out, fee_percentage);
+        Some(amount_out.with_scale_round(decimals_out as i64, RoundingMode::Down))
+    }
+    
+    /// 根据储备量计算 token_b / token_a 的价格
+    fn price_from_reserves(
+        &self,
+        token_pair: &TokenPair,
+        token_a: Address,
+        token0: Address,
+        reserve0: U256,
+        reserve1: U256,
+    ) -> Result<Option<Price>> {
         // 确定哪个储备量对应哪个代币
         let (reserve_a, reserve_b) = if token0 == token_a {
             (reserve0, reserve1)
@@ -177,8 +266,126 @@ impl SushiSwapProvider {
         }))
     }
     
-
-
+    /// 通过 Multicall3 批量获取价格：一轮查询 getPair，一轮查询 token0 和 getReserves
+    async fn get_prices_via_multicall(
+        &self,
+        multicall_address: &str,
+        token_pairs: &[TokenPair],
+    ) -> Result<HashMap<TokenPair, Price>> {
+        let factory_address = self.config.factory_address.as_ref()
+            .ok_or_else(|| anyhow!("Factory address not configured"))?;
+        let factory_addr = Address::from_str(factory_address)
+            .map_err(|e| anyhow!("Invalid factory address: {}", e))?;
+        let multicall_addr = Address::from_str(multicall_address)
+            .map_err(|e| anyhow!("Invalid multicall address: {}", e))?;
+        
+        let factory_abi: Abi = serde_json::from_str(FACTORY_ABI)
+            .map_err(|e| anyhow!("Invalid factory ABI: {}", e))?;
+        let pair_abi: Abi = serde_json::from_str(PAIR_ABI)
+            .map_err(|e| anyhow!("Invalid pair ABI: {}", e))?;
+        let multicall_abi: Abi = serde_json::from_str(MULTICALL3_ABI)
+            .map_err(|e| anyhow!("Invalid multicall ABI: {}", e))?;
+        
+        let get_pair = factory_abi.function("getPair")?;
+        let token0_fn = pair_abi.function("token0")?;
+        let get_reserves = pair_abi.function("getReserves")?;
+        let multicall = Contract::new(multicall_addr, multicall_abi, self.web3_provider.clone());
+        
+        // 第一轮：批量查询所有代币对的配对地址
+        let mut requests = Vec::new();
+        let mut calls: Vec<(Address, bool, Bytes)> = Vec::new();
+        for token_pair in token_pairs {
+            let (Ok(token_a), Ok(token_b)) = (
+                Address::from_str(&token_pair.token_a.address),
+                Address::from_str(&token_pair.token_b.address),
+            ) else {
+                log::warn!("SushiSwap: 代币对 {}/{} 地址无效，跳过", token_pair.token_a.symbol, token_pair.token_b.symbol);
+                continue;
+            };
+            let call_data = get_pair.encode_input(&[AbiToken::Address(token_a), AbiToken::Address(token_b)])?;
+            calls.push((factory_addr, true, call_data.into()));
+            requests.push((token_pair, token_a));
+        }
+        
+        let results: Vec<(bool, Bytes)> = multicall
+            .method::<_, Vec<(bool, Bytes)>>("aggregate3", calls)?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to aggregate getPair calls: {}", e))?;
+        
+        let mut existing = Vec::new();
+        for ((token_pair, token_a), (success, data)) in requests.into_iter().zip(results) {
+            if !success {
+                continue;
+            }
+            match get_pair.decode_output(&data)?.first() {
+                Some(AbiToken::Address(pair_address)) if !pair_address.is_zero() => {
+                    existing.push((token_pair, token_a, *pair_address));
+                }
+                _ => {
+                    log::warn!("SushiSwap: 代币对 {}/{} 不存在流动性池", token_pair.token_a.symbol, token_pair.token_b.symbol);
+                }
+            }
+        }
+        
+        // 第二轮：批量查询每个配对的 token0 和储备量
+        let mut calls: Vec<(Address, bool, Bytes)> = Vec::with_capacity(existing.len() * 2);
+        for (_, _, pair_address) in &existing {
+            calls.push((*pair_address, true, token0_fn.encode_input(&[])?.into()));
+            calls.push((*pair_address, true, get_reserves.encode_input(&[])?.into()));
+        }
+        
+        let results: Vec<(bool, Bytes)> = if calls.is_empty() {
+            Vec::new()
+        } else {
+            multicall
+                .method::<_, Vec<(bool, Bytes)>>("aggregate3", calls)?
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to aggregate reserve calls: {}", e))?
+        };
+        
+        let mut prices = HashMap::new();
+        for ((token_pair, token_a, _), chunk) in existing.into_iter().zip(results.chunks(2)) {
+            let [(true, token0_data), (true, reserves_data)] = chunk else {
+                log::warn!("SushiSwap: 代币对 {}/{} 的储备量查询失败", token_pair.token_a.symbol, token_pair.token_b.symbol);
+                continue;
+            };
+            match self.decode_multicall_reserves(token_pair, token_a, token0_data, reserves_data) {
+                Ok(Some(price)) => {
+                    prices.insert(token_pair.clone(), price);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::error!("SushiSwap: 解析代币对 {}/{} 的储备量失败: {}", token_pair.token_a.symbol, token_pair.token_b.symbol, e);
+                }
+            }
+        }
+        
+        Ok(prices)
+    }
+    
+    /// 解析 Multicall 返回的 token0 与 getReserves 数据并计算价格
+    fn decode_multicall_reserves(
+        &self,
+        token_pair: &TokenPair,
+        token_a: Address,
+        token0_data: &[u8],
+        reserves_data: &[u8],
+    ) -> Result<Option<Price>> {
+        let token0 = match decode(&[ParamType::Address], token0_data)?.first() {
+            Some(AbiToken::Address(address)) => *address,
+            _ => return Err(anyhow!("Invalid token0 return data")),
+        };
+        
+        let reserves = decode(&[ParamType::Uint(112), ParamType::Uint(112), ParamType::Uint(32)], reserves_data)?;
+        let (reserve0, reserve1) = match (reserves.first(), reserves.get(1)) {
+            (Some(AbiToken::Uint(reserve0)), Some(AbiToken::Uint(reserve1))) => (*reserve0, *reserve1),
+            _ => return Err(anyhow!("Invalid getReserves return data")),
+        };
+        
+        self.price_from_reserves(token_pair, token_a, token0, reserve0, reserve1)
+    }
 }
 
 #[async_trait]
@@ -208,6 +415,19 @@ impl DexProvider for SushiSwapProvider {
         
         log::info!("SushiSwap: 开始从区块链获取 {} 个代币对的价格", token_pairs.len());
         
+        // 配置了 Multicall 地址时批量查询，失败则回退到逐个查询
+        if let Some(multicall_address) = &self.config.multicall_address {
+            match self.get_prices_via_multicall(multicall_address, token_pairs).await {
+                Ok(prices) => {
+                    log::info!("SushiSwap: 通过 Multicall 成功获取 {} 个代币对的价格", prices.len());
+                    return Ok(prices);
+                }
+                Err(e) => {
+                    log::warn!("SushiSwap: Multicall 批量查询失败，回退到逐个查询: {}", e);
+                }
+            }
+        }
+        
         // 从区块链直接获取价格
         for token_pair in token_pairs {
             log::info!("SushiSwap: 正在获取代币对 {}/{} 的价格", token_pair.token_a.symbol, token_pair.token_b.symbol);
@@ -232,6 +452,21 @@ impl DexProvider for SushiSwapProvider {
         Ok(prices)
     }
     
+    async fn get_pair_reserves(&self, token_pair: &TokenPair) -> Result<Option<(BigDecimal, BigDecimal)>> {
+        match self.fetch_raw_reserves(token_pair).await? {
+            Some((token_a, token0, reserve0, reserve1)) => {
+                Ok(Some(Self::reserves_for_pair(token_pair, token_a, token0, reserve0, reserve1)?))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    async fn quote_amount_out(&self, token_pair: &TokenPair, amount_in: &BigDecimal) -> Result<Option<BigDecimal>> {
+        Ok(self.get_pair_reserves(token_pair).await?.and_then(|(reserve_a, reserve_b)| {
+            Self::quote_from_reserves(amount_in, &reserve_a, &reserve_b, self.get_fee_percentage(), token_pair.token_b.decimals)
+        }))
+    }
+    
     async fn get_pool_info(&self, pool_id: &str) -> Result<Option<Pool>> {
         // Pool info is now retrieved on-demand via blockchain queries
         // This method returns None as pool discovery is done dynamically
@@ -256,4 +491,103 @@ impl DexProvider for SushiSwapProvider {
     fn get_fee_percentage(&self) -> f64 {
         0.003 // SushiSwap 的标准费率是 0.3%
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::ToPrimitive;
+    use ethers::utils::hex;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    fn test_provider() -> SushiSwapProvider {
+        SushiSwapProvider::new(DexConfig {
+            name: "SushiSwap".to_string(),
+            enabled: true,
+            api_url: "http://localhost:8545".to_string(),
+            chain_id: 1,
+            factory_address: Some("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac".to_string()),
+            router_address: None,
+            subgraph_url: None,
+            rate_limit_ms: 0,
+            fee_tier: None,
+            multicall_address: Some("0xcA11bde05977b3631167028862bE2a173976CA11".to_string()),
+            gas_units: None,
+        })
+    }
+
+    #[test]
+    fn test_reserves_for_pair_ordering() {
+        let token_pair = TokenPair {
+            token_a: Token::new(WETH.to_string(), "WETH".to_string(), "Wrapped Ether".to_string(), 18, 1),
+            token_b: Token::new(USDC.to_string(), "USDC".to_string(), "USD Coin".to_string(), 6, 1),
+        };
+        let weth = Address::from_str(WETH).unwrap();
+        let usdc = Address::from_str(USDC).unwrap();
+        let usdc_reserve = U256::from(2_000_000_000u64); // 2000 USDC
+        let weth_reserve = U256::exp10(18); // 1 WETH
+
+        // 无论 token0 是哪个代币，结果都按 (token_a, token_b) 排列
+        let expected = (BigDecimal::from(1), BigDecimal::from(2000));
+        let (a, b) = SushiSwapProvider::reserves_for_pair(&token_pair, weth, usdc, usdc_reserve, weth_reserve).unwrap();
+        assert_eq!((a.normalized(), b.normalized()), (expected.0.clone(), expected.1.clone()));
+        let (a, b) = SushiSwapProvider::reserves_for_pair(&token_pair, weth, weth, weth_reserve, usdc_reserve).unwrap();
+        assert_eq!((a.normalized(), b.normalized()), expected);
+    }
+
+    #[test]
+    fn test_quote_matches_constant_product() {
+        let amount_in = BigDecimal::from(1);
+        let (reserve_in, reserve_out) = (BigDecimal::from(100), BigDecimal::from(200_000));
+        let quote = SushiSwapProvider::quote_from_reserves(&amount_in, &reserve_in, &reserve_out, 0.003, 6).unwrap();
+
+        // 与闭式解一致，只按 USDC 的 6 位小数向下取整
+        let closed_form = calculate_amount_out(&amount_in, &reserve_in, &reserve_out, 0.003);
+        assert!(quote <= closed_form);
+        assert!(&closed_form - &quote < BigDecimal::from_str("0.000001").unwrap());
+
+        // 与路由合约 getAmountOut 的整数计算结果相同
+        let raw_in = U256::exp10(18);
+        let (raw_reserve_in, raw_reserve_out) = (U256::exp10(20), U256::from(200_000u64) * U256::exp10(6));
+        let with_fee = raw_in * U256::from(997);
+        let router_out: U256 = with_fee * raw_reserve_out / (raw_reserve_in * U256::from(1000) + with_fee);
+        assert_eq!(quote, BigDecimal::from_str(&router_out.to_string()).unwrap() / BigDecimal::from(1_000_000));
+
+        assert!(SushiSwapProvider::quote_from_reserves(&amount_in, &BigDecimal::from(0), &reserve_out, 0.003, 6).is_none());
+    }
+
+    #[test]
+    fn test_decode_multicall_reserves() {
+        let provider = test_provider();
+        let token_pair = TokenPair {
+            token_a: Token::new(WETH.to_string(), "WETH".to_string(), "Wrapped Ether".to_string(), 18, 1),
+            token_b: Token::new(USDC.to_string(), "USDC".to_string(), "USD Coin".to_string(), 6, 1),
+        };
+
+        // token0() 返回 USDC；getReserves() 返回 2000 USDC / 1 WETH
+        let token0_data = hex::decode(
+            "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+        ).unwrap();
+        let reserves_data = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000077359400",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "0000000000000000000000000000000000000000000000000000000065f0a1b0",
+        )).unwrap();
+
+        let token_a = Address::from_str(WETH).unwrap();
+        let price = provider
+            .decode_multicall_reserves(&token_pair, token_a, &token0_data, &reserves_data)
+            .unwrap()
+            .unwrap();
+
+        assert!((price.price.to_f64().unwrap() - 2000.0).abs() < 1e-6);
+        assert_eq!(price.token_pair, token_pair);
+
+        // 返回数据长度不足时报错
+        assert!(provider
+            .decode_multicall_reserves(&token_pair, token_a, &token0_data, &reserves_data[..32])
+            .is_err());
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use ethers::{
+    abi::Abi,
+    contract::Contract,
+    providers::{Http, Middleware, Provider},
+    types::{Address, I256, U256},
+};
+use num_traits::Zero;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::DexConfig;
+use crate::dex::DexProvider;
+use crate::price_calculator::PriceCalculator;
+use crate::types::{Pool, Price, TokenPair};
+
+/// Uniswap V3 标准费率档位 (0.05% / 0.3% / 1%)
+const STANDARD_FEE_TIERS: [u32; 3] = [500, 3000, 10000];
+
+/// 未配置费率档位且尚未选出过池时使用的费率档位
+const DEFAULT_FEE_TIER: u32 = 3000;
+
+/// Uniswap V3 Factory ABI (简化版，只包含getPool函数)
+const FACTORY_ABI: &str = r#"[
+    {
+        "inputs": [
+            {"name": "tokenA", "type": "address"},
+            {"name": "tokenB", "type": "address"},
+            {"name": "fee", "type": "uint24"}
+        ],
+        "name": "getPool",
+        "outputs": [{"name": "pool", "type": "address"}],
+        "stateMutability": "view",
+        "type": "function"
+    }
+]"#;
+
+/// Uniswap V3 Pool ABI (简化版，只包含slot0和liquidity函数)
+const POOL_ABI: &str = r#"[
+    {
+        "inputs": [],
+        "name": "slot0",
+        "outputs": [
+            {"name": "sqrtPriceX96", "type": "uint160"},
+            {"name": "tick", "type": "int24"},
+            {"name": "observationIndex", "type": "uint16"},
+            {"name": "observationCardinality", "type": "uint16"},
+            {"name": "observationCardinalityNext", "type": "uint16"},
+            {"name": "feeProtocol", "type": "uint8"},
+            {"name": "unlocked", "type": "bool"}
+        ],
+        "stateMutability": "view",
+        "type": "function"
+    },
+    {
+        "inputs": [],
+        "name": "liquidity",
+        "outputs": [{"name": "", "type": "uint128"}],
+        "stateMutability": "view",
+        "type": "function"
+    }
+]"#;
+
+/// 候选池信息：(费率档位, 池地址, 流动性)
+type PoolCandidate = (u32, Address, u128);
+
+pub struct UniswapV3Provider {
+    config: DexConfig,
+    web3_provider: Arc<Provider<Http>>,
+    /// 最近一次报价所选池的费率档位，未配置 fee_tier 时用于 get_fee_percentage
+    last_fee_tier: Mutex<Option<u32>>,
+}
+
+impl UniswapV3Provider {
+    pub fn new(config: DexConfig) -> Self {
+        // 创建 Web3 提供者
+        let web3_provider = match Provider::<Http>::try_from(&config.api_url) {
+            Ok(provider) => Arc::new(provider),
+            Err(e) => {
+                log::error!("Failed to create Web3 provider for Uniswap V3: {}", e);
+                panic!("Failed to create Web3 provider: {}", e);
+            }
+        };
+
+        Self { config, web3_provider, last_fee_tier: Mutex::new(None) }
+    }
+
+    /// 费率档位以百万分之一为单位（3000 = 0.3%）
+    fn fee_fraction(fee_tier: u32) -> f64 {
+        fee_tier as f64 / 1_000_000.0
+    }
+
+    /// 需要查询的费率档位：配置了 fee_tier 时只查询该档位，否则尝试所有标准档位
+    fn fee_tiers_to_try(&self) -> Vec<u32> {
+        match self.config.fee_tier {
+            Some(fee_tier) => vec![fee_tier],
+            None => STANDARD_FEE_TIERS.to_vec(),
+        }
+    }
+
+    /// 从候选池中选出流动性最高的池
+    fn select_deepest_pool(candidates: &[PoolCandidate]) -> Option<PoolCandidate> {
+        candidates
+            .iter()
+            .filter(|(_, _, liquidity)| *liquidity > 0)
+            .max_by_key(|(_, _, liquidity)| *liquidity)
+            .copied()
+    }
+
+    /// 把 sqrtPriceX96 换算为 token_b / token_a 的价格；池未初始化（sqrtPrice 为 0）时返回 None
+    fn price_b_per_a(sqrt_price_x96: U256, token_pair: &TokenPair, a_is_token0: bool) -> Result<Option<BigDecimal>> {
+        if sqrt_price_x96.is_zero() {
+            return Ok(None);
+        }
+
+        // V3 池中 token0 为地址较小的代币，sqrtPrice 对应 token1/token0 的价格
+        let (token0, token1) = if a_is_token0 {
+            (&token_pair.token_a, &token_pair.token_b)
+        } else {
+            (&token_pair.token_b, &token_pair.token_a)
+        };
+
+        let price_1_per_0 = PriceCalculator::calculate_price_from_sqrt_price(
+            &sqrt_price_x96.to_string(),
+            token0.decimals as u32,
+            token1.decimals as u32,
+        )?;
+        if price_1_per_0.is_zero() {
+            return Ok(None);
+        }
+
+        // 统一返回 token_b / token_a 的价格
+        Ok(Some(if a_is_token0 {
+            price_1_per_0
+        } else {
+            BigDecimal::from(1) / price_1_per_0
+        }))
+    }
+
+    /// 获取流动性最高的池的价格，同时返回该池的费率档位
+    async fn get_price_from_blockchain(&self, token_pair: &TokenPair) -> Result<Option<(Price, u32)>> {
+        // 获取工厂合约地址
+        let factory_address = self.config.factory_address.as_ref()
+            .ok_or_else(|| anyhow!("Factory address not configured"))?;
+
+        let factory_addr = Address::from_str(factory_address)
+            .map_err(|e| anyhow!("Invalid factory address: {}", e))?;
+
+        let factory_abi: Abi = serde_json::from_str(FACTORY_ABI)
+            .map_err(|e| anyhow!("Invalid factory ABI: {}", e))?;
+        let pool_abi: Abi = serde_json::from_str(POOL_ABI)
+            .map_err(|e| anyhow!("Invalid pool ABI: {}", e))?;
+
+        let factory_contract = Contract::new(factory_addr, factory_abi, self.web3_provider.clone());
+
+        // 获取代币地址
+        let token_a = Address::from_str(&token_pair.token_a.address)
+            .map_err(|e| anyhow!("Invalid token A address: {}", e))?;
+        let token_b = Address::from_str(&token_pair.token_b.address)
+            .map_err(|e| anyhow!("Invalid token B address: {}", e))?;
+
+        // 依次查询每个费率档位的池及其流动性
+        let mut candidates: Vec<PoolCandidate> = Vec::new();
+        for fee_tier in self.fee_tiers_to_try() {
+            let pool_address: Address = factory_contract
+                .method::<_, Address>("getPool", (token_a, token_b, fee_tier))?
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to get pool address: {}", e))?;
+
+            // 检查池是否存在
+            if pool_address == Address::zero() {
+                continue;
+            }
+
+            let pool_contract = Contract::new(pool_address, pool_abi.clone(), self.web3_provider.clone());
+            let liquidity: u128 = pool_contract
+                .method::<_, u128>("liquidity", ())?
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to get liquidity: {}", e))?;
+
+            candidates.push((fee_tier, pool_address, liquidity));
+        }
+
+        let (fee_tier, pool_address, liquidity) = match Self::select_deepest_pool(&candidates) {
+            Some(candidate) => candidate,
+            None => return Ok(None),
+        };
+
+        log::debug!(
+            "Uniswap V3: 选择 {}/{} 费率 {} 的池 {:?} (流动性 {})",
+            token_pair.token_a.symbol, token_pair.token_b.symbol, fee_tier, pool_address, liquidity
+        );
+
+        // 读取 slot0 中的 sqrtPriceX96
+        let pool_contract = Contract::new(pool_address, pool_abi, self.web3_provider.clone());
+        let (sqrt_price_x96, _, _, _, _, _, _): (U256, I256, u16, u16, u16, u8, bool) = pool_contract
+            .method::<_, (U256, I256, u16, u16, u16, u8, bool)>("slot0", ())?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to get slot0: {}", e))?;
+
+        let Some(price) = Self::price_b_per_a(sqrt_price_x96, token_pair, token_a < token_b)? else {
+            log::warn!("Uniswap V3: 池 {:?} 的 sqrtPrice 为 0，跳过", pool_address);
+            return Ok(None);
+        };
+        *self.last_fee_tier.lock().unwrap() = Some(fee_tier);
+
+        Ok(Some((
+            Price {
+                token_pair: token_pair.clone(),
+                price,
+                liquidity: BigDecimal::from(liquidity),
+                dex: self.name().to_string(),
+                timestamp: Utc::now(),
+                block_number: None,
+            },
+            fee_tier,
+        )))
+    }
+}
+
+#[async_trait]
+impl DexProvider for UniswapV3Provider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.config.chain_id
+    }
+
+    async fn get_pools(&self) -> Result<Vec<Pool>> {
+        // Uniswap V3 pools are fetched on-demand via get_price_from_blockchain
+        Ok(Vec::new())
+    }
+
+    async fn get_price(&self, token_pair: &TokenPair) -> Result<Option<Price>> {
+        // 直接调用区块链价格获取方法
+        Ok(self.get_price_from_blockchain(token_pair).await?.map(|(price, _)| price))
+    }
+
+    async fn get_prices(&self, token_pairs: &[TokenPair]) -> Result<HashMap<TokenPair, Price>> {
+        let mut prices: HashMap<TokenPair, Price> = HashMap::new();
+
+        log::info!("Uniswap V3: 开始从区块链获取 {} 个代币对的价格", token_pairs.len());
+
+        // 从区块链直接获取价格
+        for token_pair in token_pairs {
+            log::info!("Uniswap V3: 正在获取代币对 {}/{} 的价格", token_pair.token_a.symbol, token_pair.token_b.symbol);
+            match self.get_price_from_blockchain(token_pair).await {
+                Ok(Some((price, _))) => {
+                    log::info!("Uniswap V3: 成功获取价格 {} for {}/{}", price.price, token_pair.token_a.symbol, token_pair.token_b.symbol);
+                    prices.insert(token_pair.clone(), price);
+                }
+                Ok(None) => {
+                    log::warn!("Uniswap V3: 代币对 {}/{} 不存在流动性池", token_pair.token_a.symbol, token_pair.token_b.symbol);
+                }
+                Err(e) => {
+                    log::error!("Uniswap V3: 获取代币对 {}/{} 价格失败: {}", token_pair.token_a.symbol, token_pair.token_b.symbol, e);
+                }
+            }
+
+            // 添加速率限制
+            tokio::time::sleep(Duration::from_millis(self.config.rate_limit_ms)).await;
+        }
+
+        log::info!("Uniswap V3: 成功获取 {} 个代币对的价格", prices.len());
+        Ok(prices)
+    }
+
+    /// 按所选池自身的费率档位扣除手续费
+    async fn quote_amount_out(&self, token_pair: &TokenPair, amount_in: &BigDecimal) -> Result<Option<BigDecimal>> {
+        let Some((price, fee_tier)) = self.get_price_from_blockchain(token_pair).await? else {
+            return Ok(None);
+        };
+        let fee_multiplier = BigDecimal::from_str(&(1.0 - Self::fee_fraction(fee_tier)).to_string())?;
+        Ok(Some(amount_in * &price.price * fee_multiplier))
+    }
+
+    async fn get_pool_info(&self, _pool_id: &str) -> Result<Option<Pool>> {
+        // Pool info is fetched on-demand via blockchain
+        Ok(None)
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        // 检查区块链连接是否正常
+        match self.web3_provider.get_block_number().await {
+            Ok(_) => {
+                log::info!("Uniswap V3: 区块链连接健康检查通过");
+                Ok(true)
+            }
+            Err(e) => {
+                log::error!("Uniswap V3: 区块链连接健康检查失败: {}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    fn get_fee_percentage(&self) -> f64 {
+        // 配置的档位优先，其次是最近一次选出的池的档位
+        let fee_tier = self.config.fee_tier.or(*self.last_fee_tier.lock().unwrap());
+        Self::fee_fraction(fee_tier.unwrap_or(DEFAULT_FEE_TIER))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(fee_tier: Option<u32>) -> DexConfig {
+        DexConfig {
+            name: "Uniswap V3".to_string(),
+            enabled: true,
+            api_url: "http://localhost:8545".to_string(),
+            chain_id: 1,
+            factory_address: Some("0x1F98431c8aD98523631AE4a59f267346ea31F984".to_string()),
+            router_address: None,
+            subgraph_url: None,
+            rate_limit_ms: 0,
+            fee_tier,
+            multicall_address: None,
+            gas_units: None,
+        }
+    }
+
+    #[test]
+    fn test_fee_tiers_and_fee_percentage() {
+        let provider = UniswapV3Provider::new(test_config(None));
+        assert_eq!(provider.fee_tiers_to_try(), vec![500, 3000, 10000]);
+        assert_eq!(provider.get_fee_percentage(), 0.003);
+
+        // 未配置档位时使用最近选出的池的档位
+        *provider.last_fee_tier.lock().unwrap() = Some(10000);
+        assert_eq!(provider.get_fee_percentage(), 0.01);
+
+        let provider = UniswapV3Provider::new(test_config(Some(500)));
+        assert_eq!(provider.fee_tiers_to_try(), vec![500]);
+        assert_eq!(provider.get_fee_percentage(), 0.0005);
+    }
+
+    #[test]
+    fn test_zero_sqrt_price_has_no_price() {
+        let token = |address: &str, symbol: &str| crate::types::Token::new(address.to_string(), symbol.to_string(), symbol.to_string(), 18, 1);
+        let token_pair = TokenPair {
+            token_a: token("0x0000000000000000000000000000000000000002", "B"),
+            token_b: token("0x0000000000000000000000000000000000000001", "A"),
+        };
+
+        // 未初始化的池 sqrtPrice 为 0
+        assert_eq!(UniswapV3Provider::price_b_per_a(U256::zero(), &token_pair, false).unwrap(), None);
+        assert_eq!(UniswapV3Provider::price_b_per_a(U256::zero(), &token_pair, true).unwrap(), None);
+
+        // 最小 sqrtPrice 的价格被截断到下限，取倒数后为有限值
+        let min_sqrt_ratio = U256::from(4_295_128_739u64);
+        assert!(UniswapV3Provider::price_b_per_a(min_sqrt_ratio, &token_pair, false).unwrap().is_some());
+
+        // sqrtPriceX96 = 2^96 表示价格 1
+        let one = U256::from(2).pow(U256::from(96));
+        assert_eq!(UniswapV3Provider::price_b_per_a(one, &token_pair, false).unwrap(), Some(BigDecimal::from(1)));
+    }
+
+    #[test]
+    fn test_select_deepest_pool() {
+        let pool_a = Address::from_low_u64_be(1);
+        let pool_b = Address::from_low_u64_be(2);
+        let pool_c = Address::from_low_u64_be(3);
+
+        let candidates = vec![(500, pool_a, 1_000), (3000, pool_b, 50_000), (10000, pool_c, 0)];
+        assert_eq!(UniswapV3Provider::select_deepest_pool(&candidates), Some((3000, pool_b, 50_000)));
+
+        // 没有任何流动性时视为不存在可用的池
+        assert_eq!(UniswapV3Provider::select_deepest_pool(&[(500, pool_a, 0)]), None);
+        assert_eq!(UniswapV3Provider::select_deepest_pool(&[]), None);
+    }
+}
@@ -1,21 +1,27 @@
 use anyhow::Result;
 use log::{error, info, debug, warn};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch, RwLock};
 use ethers::{
     prelude::*,
     providers::{Provider, StreamExt},
     types::{Filter, Log, H160, U256, I256},
 };
-use std::sync::Arc;
+use futures::Stream;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
-use std::env;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
 
 use crate::database::Database;
 use crate::price_calculator::PriceCalculator;
+use crate::provider_pool::ProviderPool;
 use crate::table_display::{DisplayMessage, PairDisplay, PairDisplayConverter};
 use crate::thegraph::PairData;
 use crate::config::{protocol_types, dex_types};
-use chrono;
+use chrono::{self, DateTime, Utc};
 
 #[derive(Debug, Clone)]
 pub enum EventType {
@@ -65,8 +71,39 @@ pub struct EventListener {
     sender: mpsc::Sender<DisplayMessage>,
     count: usize,
     provider: Option<Arc<Provider<ethers::providers::Ws>>>,
+    /// WSS_URLS 中的端点及其健康统计，断线重连时据此选择端点
+    provider_pool: Arc<Mutex<ProviderPool>>,
     contracts: HashMap<String, ContractInfo>,
-    pairs: Vec<PairData>,
+    pairs: SharedPairs,
+    max_concurrent_logs: usize,
+    max_reconnect_attempts: Option<u32>,
+    shutdown_tx: watch::Sender<bool>,
+}
+
+/// 监听任务之间共享的交易对数据，Sync 事件会原地更新其中的储备量
+type SharedPairs = Arc<RwLock<Vec<PairData>>>;
+
+/// WebSocket 重连的初始等待时间与上限
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// 回填历史事件时每次 get_logs 查询的区块数
+const BACKFILL_CHUNK_SIZE: u64 = 2000;
+
+/// V2/V3 Swap 事件签名
+const V2_SWAP_EVENT: &str = "Swap(address,uint256,uint256,uint256,uint256,address)";
+const V3_SWAP_EVENT: &str = "Swap(address,address,int256,int256,uint160,uint128,int24)";
+
+/// V2 Sync 事件签名，携带交易后的绝对储备量
+const V2_SYNC_EVENT: &str = "Sync(uint112,uint112)";
+
+/// 事件监听循环的运行参数
+#[derive(Clone)]
+struct ListenOptions {
+    max_concurrent_logs: usize,
+    max_reconnect_attempts: Option<u32>,
+    provider_pool: Arc<Mutex<ProviderPool>>,
+    shutdown: watch::Receiver<bool>,
 }
 
 impl EventListener {
@@ -77,8 +114,19 @@ impl EventListener {
         initial_pairs: Vec<PairData>,
     ) -> Self {
         // 尝试连接到以太坊节点
-        let provider = Self::try_connect_to_ethereum().await;
-        
+        let provider_pool = Arc::new(Mutex::new(ProviderPool::from_env()));
+        let provider = ProviderPool::connect(&provider_pool).await;
+        Self::with_provider(database, sender, count, initial_pairs, provider, provider_pool)
+    }
+
+    fn with_provider(
+        database: Database,
+        sender: mpsc::Sender<DisplayMessage>,
+        count: usize,
+        initial_pairs: Vec<PairData>,
+        provider: Option<Arc<Provider<ethers::providers::Ws>>>,
+        provider_pool: Arc<Mutex<ProviderPool>>,
+    ) -> Self {
         // 从初始交易对数据中提取合约地址和协议信息
         let mut contracts: HashMap<String, ContractInfo> = HashMap::new();
         info!("开始从 {} 个初始交易对中提取合约地址", initial_pairs.len());
@@ -98,15 +146,30 @@ impl EventListener {
             }
         }
         info!("合约地址提取完成，共添加 {} 个合约", contracts.len());
-        
+
+        // 恢复之前手动添加的合约，以地址作为名称
+        match database.load_watched_contracts() {
+            Ok(watched) => {
+                for contract_info in watched {
+                    if !contracts.values().any(|existing| existing.address == contract_info.address) {
+                        contracts.insert(format!("{:#x}", contract_info.address), contract_info);
+                    }
+                }
+            }
+            Err(e) => warn!("加载已保存的监听合约失败: {}", e),
+        }
 
         let event_listener = Self {
             database: database.clone(),
             sender,
             count,
             provider,
+            provider_pool,
             contracts,
-            pairs: initial_pairs,
+            pairs: Arc::new(RwLock::new(initial_pairs)),
+            max_concurrent_logs: crate::config::default_max_concurrent_logs(),
+            max_reconnect_attempts: None,
+            shutdown_tx: watch::channel(false).0,
         };
         
         event_listener
@@ -126,13 +189,16 @@ impl EventListener {
         
         self.contracts.insert(name.clone(), contract_info);
         info!("已添加合约监听: {} -> {} ({})", name, address, protocol_type);
-        Ok(())
+        self.persist_contracts()
     }
     
     /// 移除DEX合约地址
     pub fn remove_contract(&mut self, name: &str) -> bool {
         if let Some(contract_info) = self.contracts.remove(name) {
             info!("已移除合约监听: {} -> {:?} ({})", name, contract_info.address, contract_info.protocol_type);
+            if let Err(e) = self.persist_contracts() {
+                warn!("保存监听合约失败: {}", e);
+            }
             true
         } else {
             warn!("未找到要移除的合约: {}", name);
@@ -140,6 +206,37 @@ impl EventListener {
         }
     }
     
+    /// 按交易对地址添加监听，协议和 DEX 类型取自数据库中保存的交易对；已在监听时不重复添加
+    pub fn watch_pair(&mut self, pair_address: &str) -> Result<()> {
+        let pair_id = pair_address.to_lowercase();
+        let pair = self
+            .database
+            .find_pair_by_id(&pair_id)?
+            .ok_or_else(|| anyhow::anyhow!("数据库中没有交易对 {}，请先运行 --update-pairs", pair_address))?;
+
+        let address: H160 = pair_id.parse().map_err(|e| anyhow::anyhow!("无效的合约地址 {}: {}", pair_address, e))?;
+        if self.contracts.values().any(|contract| contract.address == address) {
+            info!("合约 {} 已在监听中", pair_id);
+            return Ok(());
+        }
+        self.add_contract(pair_id.clone(), &pair_id, pair.protocol_type, pair.dex_type)
+    }
+
+    /// 按地址移除监听中的合约（同一地址可能以不同名称加入），返回是否有合约被移除
+    pub fn unwatch_address(&mut self, address: &str) -> Result<bool> {
+        let address: H160 = address.parse().map_err(|e| anyhow::anyhow!("无效的合约地址 {}: {}", address, e))?;
+        let names: Vec<String> = self
+            .contracts
+            .iter()
+            .filter(|(_, contract)| contract.address == address)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &names {
+            self.remove_contract(name);
+        }
+        Ok(!names.is_empty())
+    }
+    
     /// 批量添加合约地址（需要指定协议类型）
     pub fn add_contracts(&mut self, contracts: HashMap<String, (String, String, String)>) -> Result<()> {
         for (name, (address, protocol_type, dex_type)) in contracts {
@@ -153,46 +250,38 @@ impl EventListener {
         &self.contracts
     }
     
+    /// 设置同时处理的日志数量上限（至少为 1）
+    pub fn set_max_concurrent_logs(&mut self, max_concurrent_logs: usize) {
+        self.max_concurrent_logs = max_concurrent_logs.max(1);
+        info!("日志并发处理上限设置为 {}", self.max_concurrent_logs);
+    }
+    
+    /// 设置 WebSocket 断线后的最大连续重连次数（None 表示不限制）
+    pub fn set_max_reconnect_attempts(&mut self, max_reconnect_attempts: Option<u32>) {
+        self.max_reconnect_attempts = max_reconnect_attempts;
+    }
+    
+    /// 当前使用的 WebSocket 端点
+    pub fn active_endpoint(&self) -> String {
+        self.provider_pool.lock().unwrap().active_endpoint().to_string()
+    }
+    
     /// 清空所有合约地址
     pub fn clear_contracts(&mut self) {
         let count = self.contracts.len();
         self.contracts.clear();
         info!("已清空所有合约地址，共移除 {} 个合约", count);
+        if let Err(e) = self.persist_contracts() {
+            warn!("保存监听合约失败: {}", e);
+        }
     }
 
-    async fn try_connect_to_ethereum() -> Option<Arc<Provider<ethers::providers::Ws>>> {
-        // 从环境变量读取WebSocket端点
-        let wss_urls = match env::var("WSS_URLS") {
-            Ok(urls_str) => {
-                urls_str.split(',').map(|s| s.trim().to_string()).collect::<Vec<String>>()
-            },
-            Err(_) => {
-                warn!("未找到环境变量 WSS_URLS，使用默认WebSocket端点");
-                vec![
-                    "wss://mainnet.infura.io/ws/v3/".to_string(),
-                ]
-            }
-        };
-        
-        for wss_url in wss_urls {
-            match Provider::<ethers::providers::Ws>::connect(&wss_url).await {
-                Ok(provider) => {
-                    // 测试连接
-                    if let Ok(_) = provider.get_block_number().await {
-                        info!("成功连接到以太坊WebSocket节点: {}", wss_url);
-                        return Some(Arc::new(provider));
-                    }
-                }
-                Err(e) => {
-                    info!("WebSocket连接失败 {}: {}", wss_url, e);
-                }
-            }
-        }
-        
-        warn!("无法连接到任何以太坊WebSocket节点");
-        None
+    /// 把当前合约集合写入数据库，重启后在 `new` 中恢复
+    fn persist_contracts(&self) -> Result<()> {
+        let contracts: Vec<ContractInfo> = self.contracts.values().cloned().collect();
+        self.database.save_watched_contracts(&contracts)
     }
-    
+
     pub async fn start_listening(&mut self) -> Result<()> {
         info!("启动区块链事件监听器...");
         
@@ -225,16 +314,35 @@ impl EventListener {
         
         info!("分离合约: V2={} 个, V3={} 个", v2_contracts.len(), v3_contracts.len());
         
+        // Sync 事件同样来自 V2 合约
+        let v2_sync_contracts = v2_contracts.clone();
+        
         // 启动事件监听循环
         let sender = self.sender.clone();
         let pairs = self.pairs.clone();
+        let database = self.database.clone();
+        let options = ListenOptions {
+            max_concurrent_logs: self.max_concurrent_logs,
+            max_reconnect_attempts: self.max_reconnect_attempts,
+            provider_pool: self.provider_pool.clone(),
+            shutdown: self.shutdown_tx.subscribe(),
+        };
         
         tokio::select! {
-            _ = Self::listen_v2_swap_events(v2_contracts, provider.clone(), sender.clone(), pairs.clone()) => {
-                error!("V2 Swap事件监听意外停止");
+            result = Self::listen_v2_swap_events(v2_contracts, provider.clone(), sender.clone(), pairs.clone(), database.clone(), options.clone()) => {
+                if let Err(e) = result {
+                    error!("V2 Swap事件监听意外停止: {}", e);
+                }
             }
-            _ = Self::listen_v3_swap_events(v3_contracts, provider.clone(), sender.clone(), pairs.clone()) => {
-                error!("V3 Swap事件监听意外停止");
+            result = Self::listen_v3_swap_events(v3_contracts, provider.clone(), sender.clone(), pairs.clone(), database.clone(), options.clone()) => {
+                if let Err(e) = result {
+                    error!("V3 Swap事件监听意外停止: {}", e);
+                }
+            }
+            result = Self::listen_v2_sync_events(v2_sync_contracts, provider.clone(), sender.clone(), pairs.clone(), database.clone(), options.clone()) => {
+                if let Err(e) = result {
+                    error!("V2 Sync事件监听意外停止: {}", e);
+                }
             }
         }
         
@@ -242,12 +350,152 @@ impl EventListener {
         Ok(())
     }
     
+    /// 回填 [from_block, to_block] 区间内错过的状态，用于补齐停机期间的数据：V2 取每个交易对最后一次
+    /// Sync 事件的绝对储备量，V3 取最后一次 Swap 事件的价格与 tick，观测时间使用事件所在区块的时间戳
+    pub async fn backfill(&self, from_block: u64, to_block: u64) -> Result<()> {
+        let provider = self.provider.clone()
+            .ok_or_else(|| anyhow::anyhow!("WebSocket连接未建立，无法回填历史事件"))?;
+        
+        for (protocol_type, event) in [(protocol_types::AMM_V2, V2_SYNC_EVENT), (protocol_types::AMM_V3, V3_SWAP_EVENT)] {
+            let addresses: Vec<H160> = self.contracts.values()
+                .filter(|c| c.protocol_type == protocol_type)
+                .map(|c| c.address)
+                .collect();
+            if addresses.is_empty() {
+                continue;
+            }
+            
+            let filter = Filter::new().event(event).address(addresses);
+            let logs = Self::fetch_logs_in_chunks(&provider, &filter, from_block, to_block).await?;
+            info!("回填 {} 事件: 区块 {}-{} 共 {} 条", protocol_type, from_block, to_block, logs.len());
+            
+            let provider = provider.clone();
+            self.replay_latest_logs(protocol_type, logs, move |block_number| {
+                let provider = provider.clone();
+                async move {
+                    let block = provider.get_block(block_number).await?
+                        .ok_or_else(|| anyhow::anyhow!("区块 {} 不存在", block_number))?;
+                    DateTime::from_timestamp(block.timestamp.as_u64() as i64, 0)
+                        .ok_or_else(|| anyhow::anyhow!("区块 {} 时间戳无效", block_number))
+                }
+            }).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// 只应用每个交易对在区间内的最后一条日志（V2 为 Sync，V3 为 Swap），两者都携带绝对状态，
+    /// 重放历史增量反而会在当前储备量上重复累加；`block_time` 返回区块时间戳作为观测时间
+    async fn replay_latest_logs<F, Fut>(&self, protocol_type: &str, logs: Vec<Log>, block_time: F)
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = Result<DateTime<Utc>>>,
+    {
+        for log in Self::latest_log_per_pair(logs) {
+            let Some(block_number) = log.block_number.map(|number| number.as_u64()) else {
+                continue;
+            };
+            let observed_at = match block_time(block_number).await {
+                Ok(observed_at) => observed_at,
+                Err(e) => {
+                    warn!("获取区块 {} 时间戳失败，使用当前时间: {}", block_number, e);
+                    Utc::now()
+                }
+            };
+            let result = if protocol_type == protocol_types::AMM_V2 {
+                Self::process_v2_sync_event(&log, &self.sender, &self.pairs, &self.database, observed_at).await
+            } else {
+                Self::process_v3_swap_event(&log, &self.contracts, &self.sender, &self.pairs, &self.database, observed_at).await
+            };
+            if let Err(e) = result {
+                error!("回填处理{}事件失败: {}", protocol_type, e);
+            }
+        }
+    }
+    
+    /// 按 (区块号, 日志索引) 取每个合约地址的最后一条日志
+    fn latest_log_per_pair(logs: Vec<Log>) -> Vec<Log> {
+        let position = |log: &Log| (log.block_number.map(|n| n.as_u64()), log.log_index);
+        let mut latest: HashMap<H160, Log> = HashMap::new();
+        for log in logs {
+            match latest.get(&log.address) {
+                Some(current) if position(current) >= position(&log) => {}
+                _ => {
+                    latest.insert(log.address, log);
+                }
+            }
+        }
+        latest.into_values().collect()
+    }
+    
+    /// 从 from_block 回填到节点当前最新区块
+    pub async fn backfill_since(&self, from_block: u64) -> Result<()> {
+        let provider = self.provider.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("WebSocket连接未建立，无法回填历史事件"))?;
+        let latest_block = provider.get_block_number().await?.as_u64();
+        self.backfill(from_block, latest_block).await
+    }
+    
+    /// 按区块分段查询日志；节点提示结果过多时将该段对半拆分后重试
+    async fn fetch_logs_in_chunks(
+        provider: &Provider<ethers::providers::Ws>,
+        filter: &Filter,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>> {
+        let mut pending = Self::split_block_range(from_block, to_block, BACKFILL_CHUNK_SIZE);
+        pending.reverse();
+        let mut logs = Vec::new();
+        
+        while let Some((start, end)) = pending.pop() {
+            let chunk_filter = filter.clone().from_block(start).to_block(end);
+            match provider.get_logs(&chunk_filter).await {
+                Ok(chunk_logs) => logs.extend(chunk_logs),
+                Err(e) if start < end && Self::is_too_many_results_error(&e.to_string()) => {
+                    debug!("区块 {}-{} 日志过多，拆分后重试", start, end);
+                    let mid = start + (end - start) / 2;
+                    pending.push((mid + 1, end));
+                    pending.push((start, mid));
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!("查询区块 {}-{} 日志失败: {}", start, end, e));
+                }
+            }
+        }
+        
+        Ok(logs)
+    }
+    
+    /// 将闭区间 [from_block, to_block] 切分为不超过 chunk_size 个区块的若干段
+    fn split_block_range(from_block: u64, to_block: u64, chunk_size: u64) -> Vec<(u64, u64)> {
+        let chunk_size = chunk_size.max(1);
+        let mut ranges = Vec::new();
+        let mut start = from_block;
+        while start <= to_block {
+            let end = start.saturating_add(chunk_size - 1).min(to_block);
+            ranges.push((start, end));
+            if end == u64::MAX {
+                break;
+            }
+            start = end + 1;
+        }
+        ranges
+    }
+    
+    /// 判断节点返回的错误是否为日志数量超限（如 "query returned more than 10000 results"）
+    fn is_too_many_results_error(message: &str) -> bool {
+        let message = message.to_lowercase();
+        message.contains("query returned more than") || message.contains("too many results")
+    }
+    
     // V2 Swap事件监听
     async fn listen_v2_swap_events(
         contracts: HashMap<String, ContractInfo>,
         provider: Arc<Provider<ethers::providers::Ws>>,
         sender: mpsc::Sender<DisplayMessage>,
-        pairs: Vec<PairData>,
+        pairs: SharedPairs,
+        database: Database,
+        options: ListenOptions,
     ) -> Result<()> {
         if contracts.is_empty() {
             info!("没有V2合约需要监听");
@@ -258,7 +506,7 @@ impl EventListener {
         
         // V2 Swap事件签名: Swap(address,uint256,uint256,uint256,uint256,address)
         let v2_filter = Filter::new()
-            .event("Swap(address,uint256,uint256,uint256,uint256,address)")
+            .event(V2_SWAP_EVENT)
             .address(contract_addresses.clone())
             .from_block(BlockNumber::Latest);
         
@@ -267,15 +515,19 @@ impl EventListener {
             info!("V2合约: {} -> {:?}", name, contract_info.address);
         }
         
-        let mut stream = provider.subscribe_logs(&v2_filter).await?;
+        let contracts = Arc::new(contracts);
         
-        while let Some(log) = stream.next().await {
-            if let Err(e) = Self::process_v2_swap_event(&log, &contracts, &sender, &pairs).await {
-                error!("处理V2 Swap事件失败: {}", e);
+        Self::subscribe_with_reconnect("V2 Swap", v2_filter, provider, options, move |log: Log| {
+            let contracts = contracts.clone();
+            let sender = sender.clone();
+            let pairs = pairs.clone();
+            let database = database.clone();
+            async move {
+                if let Err(e) = Self::process_v2_swap_event(&log, &contracts, &sender, &pairs, &database, Utc::now()).await {
+                    error!("处理V2 Swap事件失败: {}", e);
+                }
             }
-        }
-        
-        Ok(())
+        }).await
     }
     
     // V3 Swap事件监听
@@ -283,7 +535,9 @@ impl EventListener {
         contracts: HashMap<String, ContractInfo>,
         provider: Arc<Provider<ethers::providers::Ws>>,
         sender: mpsc::Sender<DisplayMessage>,
-        pairs: Vec<PairData>,
+        pairs: SharedPairs,
+        database: Database,
+        options: ListenOptions,
     ) -> Result<()> {
         if contracts.is_empty() {
             info!("没有V3合约需要监听");
@@ -294,7 +548,7 @@ impl EventListener {
         
         // V3 Swap事件签名: Swap(address,address,int256,int256,uint160,uint128,int24)
         let v3_filter = Filter::new()
-            .event("Swap(address,address,int256,int256,uint160,uint128,int24)")
+            .event(V3_SWAP_EVENT)
             .address(contract_addresses.clone())
             .from_block(BlockNumber::Latest);
         
@@ -303,15 +557,176 @@ impl EventListener {
             info!("V3合约: {} -> {:?}", name, contract_info.address);
         }
         
-        let mut stream = provider.subscribe_logs(&v3_filter).await?;
+        let contracts = Arc::new(contracts);
+        
+        Self::subscribe_with_reconnect("V3 Swap", v3_filter, provider, options, move |log: Log| {
+            let contracts = contracts.clone();
+            let sender = sender.clone();
+            let pairs = pairs.clone();
+            let database = database.clone();
+            async move {
+                if let Err(e) = Self::process_v3_swap_event(&log, &contracts, &sender, &pairs, &database, Utc::now()).await {
+                    error!("处理V3 Swap事件失败: {}", e);
+                }
+            }
+        }).await
+    }
+
+    // V2 Sync事件监听
+    async fn listen_v2_sync_events(
+        contracts: HashMap<String, ContractInfo>,
+        provider: Arc<Provider<ethers::providers::Ws>>,
+        sender: mpsc::Sender<DisplayMessage>,
+        pairs: SharedPairs,
+        database: Database,
+        options: ListenOptions,
+    ) -> Result<()> {
+        if contracts.is_empty() {
+            info!("没有V2合约需要监听Sync事件");
+            return Ok(());
+        }
+        
+        let contract_addresses: Vec<H160> = contracts.values().map(|c| c.address).collect();
+        
+        // V2 Sync事件签名: Sync(uint112,uint112)
+        let sync_filter = Filter::new()
+            .event(V2_SYNC_EVENT)
+            .address(contract_addresses.clone())
+            .from_block(BlockNumber::Latest);
+        
+        info!("开始监听V2 Sync事件，监听 {} 个合约...", contract_addresses.len());
+        
+        Self::subscribe_with_reconnect("V2 Sync", sync_filter, provider, options, move |log: Log| {
+            let sender = sender.clone();
+            let pairs = pairs.clone();
+            let database = database.clone();
+            async move {
+                if let Err(e) = Self::process_v2_sync_event(&log, &sender, &pairs, &database, Utc::now()).await {
+                    error!("处理V2 Sync事件失败: {}", e);
+                }
+            }
+        }).await
+    }
+
+    /// 订阅日志并在事件流断开时自动重连：按指数退避重新连接节点并以相同的过滤条件重新订阅，
+    /// 直到收到关闭信号或连续重连次数超过上限
+    async fn subscribe_with_reconnect<F, Fut>(
+        label: &str,
+        filter: Filter,
+        mut provider: Arc<Provider<ethers::providers::Ws>>,
+        mut options: ListenOptions,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(Log) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut attempt: u32 = 0;
+        loop {
+            let started = Instant::now();
+            match provider.subscribe_logs(&filter).await {
+                Ok(stream) => {
+                    attempt = 0;
+                    {
+                        let mut pool = options.provider_pool.lock().unwrap();
+                        pool.record_active_success(started.elapsed());
+                        info!("{} 事件订阅已建立: {}", label, pool.active_endpoint());
+                    }
+                    tokio::select! {
+                        _ = Self::process_logs_bounded(stream, options.max_concurrent_logs, |log: &Log| log.address, &handler) => {
+                            warn!("{} 事件流已断开", label);
+                        }
+                        _ = options.shutdown.changed() => {
+                            info!("{} 事件监听收到关闭信号", label);
+                            return Ok(());
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("{} 事件订阅失败: {}", label, e);
+                }
+            }
+            // 当前端点连续出错时轮换到其他端点
+            options.provider_pool.lock().unwrap().report_active_error();
+            
+            // 重新连接节点，成功后重新订阅
+            loop {
+                if let Some(max_attempts) = options.max_reconnect_attempts {
+                    if attempt >= max_attempts {
+                        return Err(anyhow::anyhow!("{} 重连失败，已达到最大重试次数 {}", label, max_attempts));
+                    }
+                }
+                
+                let delay = Self::reconnect_backoff(attempt);
+                attempt += 1;
+                warn!("{} 将在 {}s 后进行第 {} 次重连", label, delay.as_secs(), attempt);
+                
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = options.shutdown.changed() => {
+                        info!("{} 重连等待期间收到关闭信号", label);
+                        return Ok(());
+                    }
+                }
+                
+                if let Some(new_provider) = ProviderPool::connect(&options.provider_pool).await {
+                    provider = new_provider;
+                    break;
+                }
+            }
+        }
+    }
+    
+    /// 第 attempt 次重连前的等待时间：从 1s 开始翻倍，最长 60s
+    fn reconnect_backoff(attempt: u32) -> Duration {
+        RECONNECT_BASE_DELAY
+            .checked_mul(1u32 << attempt.min(16))
+            .unwrap_or(RECONNECT_MAX_DELAY)
+            .min(RECONNECT_MAX_DELAY)
+    }
+
+    /// 以有限并发处理日志流：按 `shard_key`（交易对地址）把日志分到 `max_concurrent` 个分片，
+    /// 同一分片内按到达顺序依次处理，保证同一交易对的事件不会乱序；分片队列占满时暂停读取，使背压传导到事件流
+    async fn process_logs_bounded<S, K, A, F, Fut>(mut stream: S, max_concurrent: usize, shard_key: K, handler: F)
+    where
+        S: Stream + Unpin,
+        K: Fn(&S::Item) -> A,
+        A: Hash,
+        F: Fn(S::Item) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let shard_count = max_concurrent.max(1);
+        let mut shards = Vec::with_capacity(shard_count);
+        let mut workers = JoinSet::new();
+        for _ in 0..shard_count {
+            let (sender, mut receiver) = mpsc::channel::<Fut>(1);
+            workers.spawn(async move {
+                while let Some(task) = receiver.recv().await {
+                    // 单独的任务中执行，处理出错时不影响该分片后续的日志
+                    if let Err(e) = tokio::spawn(task).await {
+                        error!("日志处理任务异常退出: {}", e);
+                    }
+                }
+            });
+            shards.push(sender);
+        }
         
         while let Some(log) = stream.next().await {
-            if let Err(e) = Self::process_v3_swap_event(&log, &contracts, &sender, &pairs).await {
-                error!("处理V3 Swap事件失败: {}", e);
+            let mut hasher = DefaultHasher::new();
+            shard_key(&log).hash(&mut hasher);
+            let shard = (hasher.finish() % shard_count as u64) as usize;
+            if shards[shard].send(handler(log)).await.is_err() {
+                break;
             }
         }
         
-        Ok(())
+        // 事件流结束后等待各分片处理完剩余日志
+        drop(shards);
+        while let Some(result) = workers.join_next().await {
+            if let Err(e) = result {
+                error!("日志分片任务异常退出: {}", e);
+            }
+        }
     }
 
     async fn listen_swap_events_static(
@@ -355,7 +770,9 @@ impl EventListener {
         log: &Log,
         contracts: &HashMap<String, ContractInfo>,
         msg_sender: &mpsc::Sender<DisplayMessage>,
-        pairs: &Vec<PairData>,
+        pairs: &RwLock<Vec<PairData>>,
+        database: &Database,
+        observed_at: DateTime<Utc>,
     ) -> Result<()> {
         let contract_name = contracts.iter()
             .find(|(_, contract_info)| contract_info.address == log.address)
@@ -391,7 +808,7 @@ impl EventListener {
             };
             
             // 处理事件并发送更新
-             Self::handle_swap_event_update(swap_event, msg_sender, pairs).await
+             Self::handle_swap_event_update(swap_event, msg_sender, pairs, database, observed_at).await
          } else {
              warn!("V2 Swap事件数据格式不正确: topics={}, data_len={}", log.topics.len(), log.data.len());
              Ok(())
@@ -402,7 +819,9 @@ impl EventListener {
           log: &Log,
           contracts: &HashMap<String, ContractInfo>,
           msg_sender: &mpsc::Sender<DisplayMessage>,
-          pairs: &Vec<PairData>,
+          pairs: &RwLock<Vec<PairData>>,
+          database: &Database,
+          observed_at: DateTime<Utc>,
       ) -> Result<()> {
          let contract_name = contracts.iter()
              .find(|(_, contract_info)| contract_info.address == log.address)
@@ -426,11 +845,7 @@ impl EventListener {
                  bytes.copy_from_slice(&log.data[96..112]);
                  bytes
              });
-             let tick = i32::from_be_bytes({
-                 let mut bytes = [0u8; 4];
-                 bytes.copy_from_slice(&log.data[156..160]);
-                 bytes
-             });
+             let tick = Self::decode_tick(&log.data[128..160])?;
              
              info!("V3 Swap: sender={:?}, recipient={:?}, amount0={}, amount1={}, sqrtPriceX96={}, liquidity={}, tick={}", 
                    sender_addr, recipient, amount0, amount1, sqrt_price_x96, liquidity, tick);
@@ -448,7 +863,7 @@ impl EventListener {
              };
              
              // 处理事件并发送更新
-              Self::handle_swap_event_update(swap_event, msg_sender, pairs).await
+              Self::handle_swap_event_update(swap_event, msg_sender, pairs, database, observed_at).await
          } else {
              warn!("V3 Swap事件数据格式不正确: topics={}, data_len={}", log.topics.len(), log.data.len());
              Ok(())
@@ -459,7 +874,9 @@ impl EventListener {
      async fn handle_swap_event_update(
          swap_event: EventType,
          msg_sender: &mpsc::Sender<DisplayMessage>,
-         pairs: &Vec<PairData>,
+         pairs: &RwLock<Vec<PairData>>,
+         database: &Database,
+         observed_at: DateTime<Utc>,
      ) -> Result<()> {
          // 根据事件类型获取交易对地址
          let pair_address = match &swap_event {
@@ -468,21 +885,47 @@ impl EventListener {
              _ => return Ok(()),
          };
          
-         // 查找对应的交易对数据
-         if let Some((index, pair)) = pairs.iter().enumerate()
-             .find(|(_, pair)| {
-                 if let Ok(addr) = pair.id.parse::<H160>() {
-                     addr == pair_address
-                 } else {
-                     false
-                 }
-             }) {
-             
+         // 查找对应的交易对并应用事件中的最新状态，复制后立即释放写锁
+         let matched = {
+             let mut pairs = pairs.write().await;
+             let index = Self::find_pair_index(&pairs, pair_address).map(|(index, _)| index);
+             index.map(|index| {
+                 let pair = &mut pairs[index];
+                 Self::apply_swap_to_pair(pair, &swap_event, observed_at);
+                 (index, pair.clone())
+             })
+         };
+         if let Some((index, pair)) = matched {
              let pair_name = format!("{}/{}", pair.token0.symbol, pair.token1.symbol);
              debug!("找到匹配的交易对: {} (索引: {})", pair_name, index);
              
+             // 将更新后的交易对写回数据库，保证重启后状态与界面一致
+             if let Err(e) = database.save_pairs(std::slice::from_ref(&pair)) {
+                 error!("保存交易对 {} 失败: {}", pair_name, e);
+             }
+             
+             // 记录价格历史：V3 使用事件中的最新 sqrtPriceX96，V2 使用交易对数据计算
+             let price = match &swap_event {
+                 EventType::V3SwapEvent { sqrt_price_x96, .. } => {
+                     match (pair.token0.decimals.parse::<u32>(), pair.token1.decimals.parse::<u32>()) {
+                         (Ok(decimals0), Ok(decimals1)) => PriceCalculator::calculate_price_from_sqrt_price(
+                             &sqrt_price_x96.to_string(), decimals0, decimals1),
+                         _ => Err(anyhow::anyhow!("Invalid token decimals")),
+                     }
+                 }
+                 _ => PriceCalculator::calculate_price_from_pair(&pair),
+             };
+             match price {
+                 Ok(price) => {
+                     if let Err(e) = database.record_price(&pair.id, &price, observed_at) {
+                         error!("记录交易对 {} 价格历史失败: {}", pair_name, e);
+                     }
+                 }
+                 Err(e) => debug!("无法计算交易对 {} 的价格: {}", pair_name, e),
+             }
+             
              // 将 PairData 转换为 PairDisplay
-             let pair_display = PairDisplayConverter::convert_for_event(pair, index + 1);
+             let pair_display = PairDisplayConverter::convert_for_event(&pair, index + 1);
              
              // 显示pair详细信息
              info!("📊 交易对更新: {} | 协议: {} | DEX: {} | 价格: {} | 成交量: ${:.2} | 储备: ${:.2}", 
@@ -512,6 +955,100 @@ impl EventListener {
          Ok(())
      }
 
+    /// 解析 Sync(uint112 reserve0, uint112 reserve1) 事件数据，返回 (reserve0, reserve1)
+    fn decode_sync_data(data: &[u8]) -> Result<(U256, U256)> {
+        if data.len() < 64 {
+            return Err(anyhow::anyhow!("Sync事件数据长度不足: {}", data.len()));
+        }
+        let reserve0 = U256::from_big_endian(&data[0..32]);
+        let reserve1 = U256::from_big_endian(&data[32..64]);
+        Ok((reserve0, reserve1))
+    }
+    
+    /// 将 Swap 事件应用到交易对：V3 更新 sqrtPriceX96 与 tick；V2 合约在 Swap 之前已发出带绝对储备量的
+    /// Sync 事件，储备量只由 Sync 更新，Swap 仅刷新观测时间（价格随后按当前储备量重新计算）
+    fn apply_swap_to_pair(pair: &mut PairData, swap_event: &EventType, observed_at: DateTime<Utc>) {
+        pair.updated_at = Some(observed_at);
+        match swap_event {
+            EventType::V3SwapEvent { sqrt_price_x96, tick, liquidity, .. } => {
+                pair.sqrt_price = Some(sqrt_price_x96.to_string());
+                pair.tick = Some(tick.to_string());
+                pair.liquidity = Some(liquidity.to_string());
+            }
+            EventType::V2SwapEvent { amount0_in, amount1_in, amount0_out, amount1_out, .. } => {
+                debug!(
+                    "交易对 {} V2 Swap: in=({}, {}) out=({}, {})，储备量以 Sync 事件为准",
+                    pair.id, amount0_in, amount1_in, amount0_out, amount1_out
+                );
+            }
+            _ => {}
+        }
+    }
+    
+    /// 解析 ABI 编码的 int24 tick：32 字节补码整数，需落在 int24 范围内
+    fn decode_tick(word: &[u8]) -> Result<i32> {
+        if word.len() != 32 {
+            return Err(anyhow::anyhow!("tick 字段长度错误: {}", word.len()));
+        }
+        let tick = I256::from_raw(U256::from_big_endian(word));
+        let (min, max) = (I256::from(-(1i32 << 23)), I256::from((1i32 << 23) - 1));
+        if tick < min || tick > max {
+            return Err(anyhow::anyhow!("tick 超出 int24 范围: {}", tick));
+        }
+        Ok(tick.as_i32())
+    }
+    
+    /// 根据合约地址查找交易对及其索引
+    fn find_pair_index(pairs: &[PairData], pair_address: H160) -> Option<(usize, &PairData)> {
+        pairs.iter().enumerate().find(|(_, pair)| {
+            pair.id.parse::<H160>().map(|addr| addr == pair_address).unwrap_or(false)
+        })
+    }
+    
+    /// 处理 V2 Sync 事件：更新内存中的储备量并写回数据库，然后推送局部更新
+    async fn process_v2_sync_event(
+        log: &Log,
+        msg_sender: &mpsc::Sender<DisplayMessage>,
+        pairs: &RwLock<Vec<PairData>>,
+        database: &Database,
+        observed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let (reserve0, reserve1) = Self::decode_sync_data(&log.data)?;
+        debug!("V2 Sync: pair={:?}, reserve0={}, reserve1={}", log.address, reserve0, reserve1);
+        
+        // 在写锁内更新储备量，复制出更新后的数据后释放锁
+        let updated = {
+            let mut pairs = pairs.write().await;
+            let index = Self::find_pair_index(&pairs, log.address).map(|(index, _)| index);
+            index.map(|index| {
+                let pair = &mut pairs[index];
+                pair.reserve0 = reserve0.to_string();
+                pair.reserve1 = reserve1.to_string();
+                pair.updated_at = Some(observed_at);
+                (index, pair.clone())
+            })
+        };
+        
+        let Some((index, pair)) = updated else {
+            debug!("未找到匹配的交易对，地址: {:?}", log.address);
+            return Ok(());
+        };
+        
+        if let Err(e) = database.save_pairs(std::slice::from_ref(&pair)) {
+            error!("保存交易对 {}/{} 储备量失败: {}", pair.token0.symbol, pair.token1.symbol, e);
+        }
+        
+        let message = DisplayMessage::PartialUpdate {
+            index,
+            data: PairDisplayConverter::convert_for_event(&pair, index + 1),
+        };
+        if let Err(e) = msg_sender.send(message).await {
+            error!("发送局部更新消息失败: {}", e);
+        }
+        
+        Ok(())
+    }
+
     async fn process_swap_event(
         log: &Log, 
         sender: &mpsc::Sender<DisplayMessage>, 
@@ -623,8 +1160,305 @@ impl EventListener {
     
     pub async fn shutdown(&self) -> Result<()> {
         info!("正在关闭事件监听器...");
+        // 通知事件监听与重连循环退出
+        self.shutdown_tx.send_replace(true);
         self.sender.send(DisplayMessage::Shutdown).await
             .map_err(|e| anyhow::anyhow!("发送关闭消息失败: {}", e))?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let delays: Vec<u64> = (0..8).map(|attempt| EventListener::reconnect_backoff(attempt).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(EventListener::reconnect_backoff(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_split_block_range() {
+        assert_eq!(
+            EventListener::split_block_range(100, 4599, 2000),
+            vec![(100, 2099), (2100, 4099), (4100, 4599)]
+        );
+        assert_eq!(EventListener::split_block_range(10, 10, 2000), vec![(10, 10)]);
+        assert!(EventListener::split_block_range(11, 10, 2000).is_empty());
+        assert_eq!(
+            EventListener::split_block_range(u64::MAX - 1, u64::MAX, 2000),
+            vec![(u64::MAX - 1, u64::MAX)]
+        );
+
+        assert!(EventListener::is_too_many_results_error(
+            "(code: -32005, message: query returned more than 10000 results, data: None)"
+        ));
+        assert!(!EventListener::is_too_many_results_error("connection reset"));
+    }
+
+    #[test]
+    fn test_watched_contracts_restored_from_database() {
+        let pool = || Arc::new(Mutex::new(ProviderPool::new(Vec::new())));
+        let database = Database::new(None).unwrap();
+        let address = "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640";
+
+        let mut listener = EventListener::with_provider(database.clone(), mpsc::channel(1).0, 10, Vec::new(), None, pool());
+        listener
+            .add_contract("USDC-WETH".to_string(), address, protocol_types::AMM_V3.to_string(), dex_types::UNISWAP_V3.to_string())
+            .unwrap();
+
+        let restored = EventListener::with_provider(database.clone(), mpsc::channel(1).0, 10, Vec::new(), None, pool());
+        let contract = &restored.get_contracts()[address];
+        assert_eq!(contract.address, address.parse::<H160>().unwrap());
+        assert_eq!(contract.protocol_type, protocol_types::AMM_V3);
+        assert_eq!(contract.dex_type, dex_types::UNISWAP_V3);
+
+        // 移除后重启不再恢复
+        let mut restored = restored;
+        assert!(restored.remove_contract(address));
+        assert!(database.load_watched_contracts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_watch_pair_uses_saved_pair() {
+        let pool = || Arc::new(Mutex::new(ProviderPool::new(Vec::new())));
+        let database = Database::new(None).unwrap();
+        let address = "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640";
+        let mut pair = test_pair(address);
+        pair.protocol_type = protocol_types::AMM_V3.to_string();
+        pair.dex_type = dex_types::UNISWAP_V3.to_string();
+        database.save_pairs(&[pair]).unwrap();
+
+        let mut listener = EventListener::with_provider(database.clone(), mpsc::channel(1).0, 10, Vec::new(), None, pool());
+        // 数据库中没有的交易对无法推断协议，直接报错
+        assert!(listener.watch_pair("0x0000000000000000000000000000000000000001").is_err());
+
+        listener.watch_pair(&address.to_uppercase().replace("0X", "0x")).unwrap();
+        listener.watch_pair(address).unwrap();
+        assert_eq!(listener.get_contracts().len(), 1);
+        let saved = database.load_watched_contracts().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].protocol_type, protocol_types::AMM_V3);
+
+        assert!(listener.unwatch_address(address).unwrap());
+        assert!(!listener.unwatch_address(address).unwrap());
+        assert!(database.load_watched_contracts().unwrap().is_empty());
+    }
+
+    fn test_pair(id: &str) -> PairData {
+        PairData {
+            id: id.to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::UNISWAP_V3.to_string(),
+            protocol_type: protocol_types::AMM_V3.to_string(),
+            token0: crate::thegraph::TokenInfo {
+                id: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                symbol: "USDC".to_string(),
+                name: "USD Coin".to_string(),
+                decimals: "6".to_string(),
+            },
+            token1: crate::thegraph::TokenInfo {
+                id: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+                symbol: "WETH".to_string(),
+                name: "Wrapped Ether".to_string(),
+                decimals: "18".to_string(),
+            },
+            volume_usd: "1000".to_string(),
+            reserve_usd: "1000".to_string(),
+            tx_count: "10".to_string(),
+            reserve0: "1000".to_string(),
+            reserve1: "1000".to_string(),
+            fee_tier: "500".to_string(),
+            sqrt_price: Some("1000000000000000000000000000000000".to_string()),
+            tick: Some("200000".to_string()),
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_v3_swap_event_persists_pair() {
+        let pair_address = H160::from_low_u64_be(0x88e6);
+        let pair_id = format!("{:?}", pair_address);
+        let database = Database::new(Some(":memory:")).unwrap();
+        database.save_pairs(&[test_pair(&pair_id)]).unwrap();
+
+        let pairs = RwLock::new(database.load_pairs().unwrap());
+        let (tx, mut rx) = mpsc::channel(8);
+        let new_sqrt_price = U256::from_dec_str("1500000000000000000000000000000000").unwrap();
+        let swap_event = EventType::V3SwapEvent {
+            pair_address,
+            sender: H160::zero(),
+            recipient: H160::zero(),
+            amount0: I256::from(-1000),
+            amount1: I256::from(500),
+            sqrt_price_x96: new_sqrt_price,
+            liquidity: 1_000_000,
+            tick: 201000,
+        };
+
+        EventListener::handle_swap_event_update(swap_event, &tx, &pairs, &database, Utc::now()).await.unwrap();
+
+        let stored = database.load_pairs().unwrap();
+        assert_eq!(stored[0].sqrt_price.as_deref(), Some("1500000000000000000000000000000000"));
+        assert_eq!(stored[0].tick.as_deref(), Some("201000"));
+        assert_eq!(pairs.read().await[0].sqrt_price, stored[0].sqrt_price);
+        assert!(matches!(rx.try_recv(), Ok(DisplayMessage::PartialUpdate { index: 0, .. })));
+    }
+
+    #[test]
+    fn test_apply_v2_swap_keeps_sync_reserves() {
+        let mut pair = test_pair("0x0000000000000000000000000000000000000001");
+        pair.reserve0 = "1000.5".to_string();
+        let swap_event = EventType::V2SwapEvent {
+            pair_address: H160::zero(),
+            sender: H160::zero(),
+            amount0_in: U256::from(100),
+            amount1_in: U256::zero(),
+            amount0_out: U256::zero(),
+            amount1_out: U256::from(90),
+            to: H160::zero(),
+        };
+
+        // 储备量已由之前的 Sync 事件设置，Swap 不再重复累加成交数量
+        EventListener::apply_swap_to_pair(&mut pair, &swap_event, Utc::now());
+        assert_eq!(pair.reserve0, "1000.5");
+        assert_eq!(pair.reserve1, "1000");
+        assert!(pair.updated_at.is_some());
+    }
+
+    fn sync_log(address: H160, block: u64, log_index: u64, reserve0: u64, reserve1: u64) -> Log {
+        let mut data = vec![0u8; 64];
+        U256::from(reserve0).to_big_endian(&mut data[0..32]);
+        U256::from(reserve1).to_big_endian(&mut data[32..64]);
+        Log {
+            address,
+            data: data.into(),
+            block_number: Some(U64::from(block)),
+            log_index: Some(U256::from(log_index)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_replay_keeps_last_sync() {
+        let pair_address = H160::from_low_u64_be(0xabcd);
+        let database = Database::new(None).unwrap();
+        let mut pair = test_pair(&format!("{:?}", pair_address));
+        pair.protocol_type = protocol_types::AMM_V2.to_string();
+        database.save_pairs(&[pair.clone()]).unwrap();
+
+        let pool = Arc::new(Mutex::new(ProviderPool::new(Vec::new())));
+        let listener = EventListener::with_provider(database.clone(), mpsc::channel(16).0, 10, vec![pair], None, pool);
+
+        // 日志顺序被打乱；区块 12 的第 3 条 Sync 为区间内的最终状态
+        let logs = vec![
+            sync_log(pair_address, 12, 1, 5_000, 6_000),
+            sync_log(pair_address, 10, 0, 1_000, 2_000),
+            sync_log(pair_address, 12, 3, 7_000, 8_000),
+            sync_log(pair_address, 11, 5, 3_000, 4_000),
+        ];
+        let block_time = |block: u64| async move {
+            Ok(DateTime::from_timestamp(1_700_000_000 + block as i64, 0).unwrap())
+        };
+        listener.replay_latest_logs(protocol_types::AMM_V2, logs, block_time).await;
+
+        let pairs = listener.pairs.read().await;
+        assert_eq!((pairs[0].reserve0.as_str(), pairs[0].reserve1.as_str()), ("7000", "8000"));
+        assert_eq!(pairs[0].updated_at.unwrap().timestamp(), 1_700_000_012);
+        let stored = database.load_pairs().unwrap();
+        assert_eq!((stored[0].reserve0.as_str(), stored[0].reserve1.as_str()), ("7000", "8000"));
+    }
+
+    fn encode_int256(value: i64) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        I256::from(value).into_raw().to_big_endian(&mut word);
+        word
+    }
+
+    #[test]
+    fn test_decode_tick() {
+        let word = encode_int256(-100);
+        assert_eq!(word[0], 0xff);
+        assert_eq!(EventListener::decode_tick(&word).unwrap(), -100);
+        assert_eq!(EventListener::decode_tick(&encode_int256(887272)).unwrap(), 887272);
+        assert_eq!(EventListener::decode_tick(&encode_int256(-887272)).unwrap(), -887272);
+        assert_eq!(EventListener::decode_tick(&encode_int256(0)).unwrap(), 0);
+
+        // 超出 int24 范围或长度错误
+        assert!(EventListener::decode_tick(&encode_int256(1 << 23)).is_err());
+        assert!(EventListener::decode_tick(&encode_int256(-(1 << 23) - 1)).is_err());
+        assert!(EventListener::decode_tick(&word[..4]).is_err());
+    }
+
+    #[test]
+    fn test_decode_sync_data() {
+        let mut data = vec![0u8; 64];
+        U256::from(1_234_567_890_123u64).to_big_endian(&mut data[0..32]);
+        U256::from(987_654_321u64).to_big_endian(&mut data[32..64]);
+
+        let (reserve0, reserve1) = EventListener::decode_sync_data(&data).unwrap();
+        assert_eq!(reserve0, U256::from(1_234_567_890_123u64));
+        assert_eq!(reserve1, U256::from(987_654_321u64));
+
+        assert!(EventListener::decode_sync_data(&data[..63]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_logs_bounded_caps_concurrency() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let handled = Arc::new(AtomicUsize::new(0));
+        let stream = futures::stream::iter(0..50);
+
+        let (active_ref, peak_ref, handled_ref) = (active.clone(), peak.clone(), handled.clone());
+        EventListener::process_logs_bounded(stream, 4, |item: &i32| *item, move |_| {
+            let active = active_ref.clone();
+            let peak = peak_ref.clone();
+            let handled = handled_ref.clone();
+            async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                handled.fetch_add(1, Ordering::SeqCst);
+            }
+        }).await;
+
+        assert_eq!(handled.load(Ordering::SeqCst), 50);
+        assert!(peak.load(Ordering::SeqCst) <= 4);
+        assert_eq!(active.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_logs_bounded_keeps_per_pair_order() {
+        // 三个交易对交替到达，先到的日志处理更慢
+        let logs: Vec<(u8, usize)> = (0..30).map(|seq| ((seq % 3) as u8, seq)).collect();
+        let handled = Arc::new(Mutex::new(Vec::new()));
+
+        let handled_ref = handled.clone();
+        EventListener::process_logs_bounded(futures::stream::iter(logs), 8, |(pair, _): &(u8, usize)| *pair, move |(pair, seq)| {
+            let handled = handled_ref.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(((30 - seq) / 5) as u64)).await;
+                handled.lock().unwrap().push((pair, seq));
+            }
+        }).await;
+
+        let handled = handled.lock().unwrap();
+        assert_eq!(handled.len(), 30);
+        for pair in 0..3u8 {
+            let order: Vec<usize> = handled.iter().filter(|(p, _)| *p == pair).map(|(_, seq)| *seq).collect();
+            let mut sorted = order.clone();
+            sorted.sort();
+            assert_eq!(order, sorted, "交易对 {} 的日志乱序", pair);
+        }
+    }
+}
@@ -2,16 +2,21 @@
 //! 
 //! 这个库提供了监控多个DEX平台套利机会的功能，支持从区块链直接获取价格数据。
 
+pub mod api;
 pub mod cli;
 pub mod config;
 pub mod database;
 pub mod dex;
 pub mod event_listener;
 pub mod log_adapter;
+pub mod metrics;
 pub mod monitor;
+pub mod notify;
 pub mod pairs;
 pub mod price_calculator;
+pub mod provider_pool;
 pub mod realtime_monitor;
+pub mod snapshot;
 pub mod table_display;
 pub mod thegraph;
 pub mod token;
@@ -1,16 +1,21 @@
 use anyhow::Result;
 use log::info;
 
+mod api;
 mod cli;
 mod config;
 mod database;
 mod dex;
 mod event_listener;
 mod log_adapter;
+mod metrics;
 mod monitor;
+mod notify;
 mod pairs;
 mod price_calculator;
+mod provider_pool;
 mod realtime_monitor;
+mod snapshot;
 mod table_display;
 mod thegraph;
 mod token;
@@ -34,7 +39,7 @@ async fn main() -> Result<()> {
     let matches = CliApp::build_cli().get_matches();
 
     // 创建CLI应用程序实例
-    let app = CliApp::new().await?;
+    let app = CliApp::new(&CliApp::db_path(&matches)).await?;
 
     // 运行应用程序
     app.run(matches).await?;
@@ -0,0 +1,231 @@
+use anyhow::Result;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 扫描耗时直方图的桶上限，单位秒
+const SCAN_DURATION_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// 读取 HTTP 请求头的最大字节数
+const MAX_REQUEST_BYTES: usize = 8192;
+
+static GLOBAL_METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// 进程内共享的指标实例
+pub fn global() -> &'static Metrics {
+    GLOBAL_METRICS.get_or_init(Metrics::new)
+}
+
+/// 直方图 - 记录每个桶的计数、总和与样本数
+#[derive(Debug, Default)]
+struct Histogram {
+    bucket_counts: [u64; SCAN_DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// 监控指标，按 Prometheus 文本格式输出
+#[derive(Debug, Default)]
+pub struct Metrics {
+    chains_found_total: AtomicU64,
+    scan_duration: Mutex<Histogram>,
+    dex_price_fetch_errors: Mutex<BTreeMap<String, u64>>,
+    /// 按 (链 ID, DEX 名称) 记录，同名 DEX 在多条链上各有一个熔断器
+    dex_circuit_states: Mutex<BTreeMap<(u64, String), u64>>,
+    graph_edges: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累加发现的套利机会数量
+    pub fn inc_chains_found(&self, count: u64) {
+        self.chains_found_total.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录一次扫描耗时
+    pub fn observe_scan_duration(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut histogram = self.scan_duration.lock().unwrap();
+        if let Some(index) = SCAN_DURATION_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            histogram.bucket_counts[index] += 1;
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    /// 记录某个 DEX 获取价格失败
+    pub fn inc_dex_price_fetch_error(&self, dex: &str) {
+        *self
+            .dex_price_fetch_errors
+            .lock()
+            .unwrap()
+            .entry(dex.to_string())
+            .or_default() += 1;
+    }
+
+    /// 设置某条链上某个 DEX 的熔断状态（0 关闭，1 半开，2 断开）
+    pub fn set_dex_circuit_state(&self, chain_id: u64, dex: &str, state: u64) {
+        self.dex_circuit_states.lock().unwrap().insert((chain_id, dex.to_string()), state);
+    }
+
+    /// 设置当前价格图的边数（本次扫描获取到的 DEX 报价数）
+    pub fn set_graph_edges(&self, edges: u64) {
+        self.graph_edges.store(edges, Ordering::Relaxed);
+    }
+
+    /// 以 Prometheus 文本格式输出所有指标
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP arb_chains_found_total Total number of arbitrage opportunities found.\n");
+        out.push_str("# TYPE arb_chains_found_total counter\n");
+        let _ = writeln!(out, "arb_chains_found_total {}", self.chains_found_total.load(Ordering::Relaxed));
+
+        out.push_str("# HELP arb_scan_duration_seconds Duration of arbitrage scans.\n");
+        out.push_str("# TYPE arb_scan_duration_seconds histogram\n");
+        {
+            let histogram = self.scan_duration.lock().unwrap();
+            let mut cumulative = 0;
+            for (bound, count) in SCAN_DURATION_BUCKETS.iter().zip(histogram.bucket_counts.iter()) {
+                cumulative += count;
+                let _ = writeln!(out, "arb_scan_duration_seconds_bucket{{le=\"{}\"}} {}", bound, cumulative);
+            }
+            let _ = writeln!(out, "arb_scan_duration_seconds_bucket{{le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "arb_scan_duration_seconds_sum {}", histogram.sum);
+            let _ = writeln!(out, "arb_scan_duration_seconds_count {}", histogram.count);
+        }
+
+        out.push_str("# HELP dex_price_fetch_errors_total Total number of failed DEX price fetches.\n");
+        out.push_str("# TYPE dex_price_fetch_errors_total counter\n");
+        for (dex, count) in self.dex_price_fetch_errors.lock().unwrap().iter() {
+            let _ = writeln!(out, "dex_price_fetch_errors_total{{dex=\"{}\"}} {}", escape_label_value(dex), count);
+        }
+
+        out.push_str("# HELP dex_circuit_breaker_state DEX circuit breaker state (0 closed, 1 half-open, 2 open).\n");
+        out.push_str("# TYPE dex_circuit_breaker_state gauge\n");
+        for ((chain_id, dex), state) in self.dex_circuit_states.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "dex_circuit_breaker_state{{chain=\"{}\",dex=\"{}\"}} {}",
+                chain_id,
+                escape_label_value(dex),
+                state
+            );
+        }
+
+        out.push_str("# HELP graph_edges Number of price edges in the latest scan.\n");
+        out.push_str("# TYPE graph_edges gauge\n");
+        let _ = writeln!(out, "graph_edges {}", self.graph_edges.load(Ordering::Relaxed));
+
+        out
+    }
+}
+
+/// 转义标签值中的反斜杠、双引号和换行
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// 在指定端口启动 `/metrics` 导出服务，返回实际监听地址
+pub async fn start_exporter(port: u16, metrics: &'static Metrics) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    let addr = listener.local_addr()?;
+    info!("📈 指标服务已启动: http://{}/metrics", addr);
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, metrics).await {
+                            warn!("处理指标请求失败: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("接受指标连接失败: {}", e),
+            }
+        }
+    });
+
+    Ok(addr)
+}
+
+/// 处理单个 HTTP 请求：`GET /metrics` 返回指标，其余路径返回 404
+async fn handle_connection(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_BYTES {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = metrics.render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exporter_serves_metrics() {
+        let metrics: &'static Metrics = Box::leak(Box::new(Metrics::new()));
+        metrics.inc_chains_found(3);
+        metrics.inc_dex_price_fetch_error("uniswap_v2");
+        metrics.observe_scan_duration(Duration::from_millis(200));
+        metrics.set_graph_edges(12);
+        metrics.set_dex_circuit_state(1, "sushiswap", 2);
+        metrics.set_dex_circuit_state(56, "sushiswap", 0);
+
+        let addr = start_exporter(0, metrics).await.unwrap();
+        let body = reqwest::get(format!("http://127.0.0.1:{}/metrics", addr.port()))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(body.contains("# TYPE arb_chains_found_total counter"));
+        assert!(body.contains("arb_chains_found_total 3\n"));
+        assert!(body.contains("dex_price_fetch_errors_total{dex=\"uniswap_v2\"} 1\n"));
+        assert!(body.contains("arb_scan_duration_seconds_bucket{le=\"0.1\"} 0\n"));
+        assert!(body.contains("arb_scan_duration_seconds_bucket{le=\"0.25\"} 1\n"));
+        assert!(body.contains("arb_scan_duration_seconds_count 1\n"));
+        assert!(body.contains("graph_edges 12\n"));
+        assert!(body.contains("dex_circuit_breaker_state{chain=\"1\",dex=\"sushiswap\"} 2\n"));
+        assert!(body.contains("dex_circuit_breaker_state{chain=\"56\",dex=\"sushiswap\"} 0\n"));
+
+        let status = reqwest::get(format!("http://127.0.0.1:{}/other", addr.port()))
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+    }
+}
@@ -1,31 +1,82 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bigdecimal::BigDecimal;
-use chrono::Utc;
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Utc};
+use ethers::providers::{Http, Provider};
 use log::{error, info, warn};
 use std::collections::HashMap;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tabled::{settings::Style, Table};
+use tokio::sync::watch;
 use tokio::time;
 
-use crate::config::{Config, dex_types};
+use crate::api::SharedApiState;
+use crate::config::{default_gas_units, Config, dex_types};
+use crate::database::Database;
 use crate::dex::balancer::BalancerProvider;
 use crate::dex::curve::CurveProvider;
 use crate::dex::pancakeswap::PancakeSwapProvider;
 use crate::dex::sushiswap::SushiSwapProvider;
 use crate::dex::uniswap::UniswapProvider;
-use crate::dex::{DexManager, DexProvider};
+use crate::dex::uniswap_v3::UniswapV3Provider;
+use crate::dex::{check_chain_id, DexKey, DexManager, DexProvider};
+use crate::metrics;
+use crate::notify::NotificationDispatcher;
+use crate::snapshot::SnapshotWriter;
 use crate::types::{ArbitrageOpportunity, GasPrice, Price, Token, TokenPair};
-use crate::utils::{calculate_percentage_difference, generate_id};
+use crate::utils::{calculate_percentage_difference, generate_id, pow10};
+
+/// 置信度和夹子风险评分时假设的交易规模（与流动性同单位）
+const CONFIDENCE_TRADE_SIZE: f64 = 10_000.0;
+
+/// 价格数据的新鲜度半衰期，单位秒
+const PRICE_FRESHNESS_HALF_LIFE_SECS: f64 = 30.0;
+
+/// 扫描间隔的随机抖动比例（±10%），避免多个实例同时请求 RPC 节点
+const SCAN_INTERVAL_JITTER: f64 = 0.1;
+
+/// 一次套利中经过公开内存池的兑换次数（买入和卖出各一次）
+const PUBLIC_MEMPOOL_HOPS: u32 = 2;
+
+/// 扫描结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 在终端中以表格显示
+    Table,
+    /// 每个套利机会输出一行 JSON（NDJSON）到 stdout
+    Json,
+}
 
 pub struct ArbitrageMonitor {
     config: Config,
     dex_manager: DexManager,
+    /// 各 DEX 提供者（按名称）单次兑换的 gas 用量
+    gas_units: HashMap<String, u64>,
+    snapshot_writer: Option<SnapshotWriter>,
+    notifications: Option<NotificationDispatcher>,
+    display_limit: Option<usize>,
+    output_format: OutputFormat,
+    start_token: Option<String>,
+    /// 没有真实机会时是否在表格中显示演示数据（--demo）
+    demo: bool,
+    /// 两次扫描之间的间隔，为 0 时只扫描一次
+    scan_interval: Duration,
+    /// 保存每次扫描发现的套利机会，未设置时不持久化
+    database: Option<Database>,
+    /// HTTP 接口读取的共享结果，未启用 --serve 时为空
+    api_state: Option<SharedApiState>,
+    /// 配置文件热更新，每次扫描前检查并应用最新配置
+    config_updates: Option<watch::Receiver<Config>>,
 }
 
 impl ArbitrageMonitor {
     pub async fn new(config: Config) -> Result<Self> {
-        let mut dex_manager = DexManager::new();
+        let mut dex_manager = DexManager::new().with_circuit_breaker(
+            config.monitoring.circuit_breaker_failure_threshold,
+            Duration::from_secs(config.monitoring.circuit_breaker_cooldown_seconds),
+        );
+        let mut gas_units = HashMap::new();
 
         // 初始化所有启用的 DEX 提供者
         for (dex_name, dex_config) in &config.dex_configs {
@@ -39,8 +90,9 @@ impl ArbitrageMonitor {
                     info!("创建 Uniswap V2 提供者");
                     Box::new(UniswapProvider::new(dex_config.clone()))
                 }
+                "uniswap_v3" => Box::new(UniswapV3Provider::new(dex_config.clone())),
                 "sushiswap" => Box::new(SushiSwapProvider::new(dex_config.clone())),
-                "pancakeswap" => Box::new(PancakeSwapProvider::new(dex_config.clone())),
+                dex_types::PANCAKESWAP => Box::new(PancakeSwapProvider::new(dex_config.clone())),
                 "curve" => Box::new(CurveProvider::new(dex_config.clone())),
                 "balancer" => Box::new(BalancerProvider::new(dex_config.clone())),
                 _ => {
@@ -49,11 +101,44 @@ impl ArbitrageMonitor {
                 }
             };
 
-            // 健康检查
-            match provider.health_check().await {
+            // 检查 RPC 节点所在链，避免测试网与主网数据混用；api_url 不一定是 RPC 节点的提供者不检查
+            let rpc = if Self::probes_chain_id(dex_name) {
+                Provider::<Http>::try_from(dex_config.api_url.as_str()).ok()
+            } else {
+                None
+            };
+            if let Some(rpc) = rpc {
+                match check_chain_id(&rpc, dex_config.chain_id).await {
+                    Ok(None) => {}
+                    Ok(Some(actual)) if config.monitoring.strict_chain_id => {
+                        return Err(anyhow!(
+                            "DEX provider {} chain id mismatch: configured {}, RPC reports {}",
+                            dex_name, dex_config.chain_id, actual
+                        ));
+                    }
+                    Ok(Some(actual)) => {
+                        warn!("DEX provider {} chain id mismatch: configured {}, RPC reports {}, skipping",
+                              dex_name, dex_config.chain_id, actual);
+                        continue;
+                    }
+                    // 响应不是合法的 RPC 结果时链 ID 未知，保留提供者而不是当作不一致
+                    Err(e) => {
+                        warn!("Unable to verify chain id of DEX provider {}: {}", dex_name, e);
+                    }
+                }
+            }
+
+            gas_units.insert(provider.name().to_string(), dex_config.gas_units_or_default(dex_name));
+            dex_manager.add_provider(provider);
+        }
+
+        // 并发健康检查，移除不健康的提供者
+        for (key, result) in dex_manager.health_check_all().await {
+            let (_, dex_name) = &key;
+            match result {
                 Ok(true) => {
                     info!("DEX provider {} is healthy", dex_name);
-                    dex_manager.add_provider(provider);
+                    continue;
                 }
                 Ok(false) => {
                     warn!("DEX provider {} failed health check", dex_name);
@@ -62,24 +147,134 @@ impl ArbitrageMonitor {
                     error!("Error checking health of DEX provider {}: {}", dex_name, e);
                 }
             }
+            gas_units.remove(dex_name);
+            dex_manager.remove_provider(&key);
+        }
+
+        let snapshot_writer = match &config.monitoring.snapshot {
+            Some(snapshot_config) => Some(SnapshotWriter::new(snapshot_config.clone())?),
+            None => None,
+        };
+
+        let notifications = NotificationDispatcher::from_config(&config.monitoring);
+
+        if let Some(port) = config.monitoring.metrics_port {
+            if let Err(e) = metrics::start_exporter(port, metrics::global()).await {
+                warn!("启动指标服务失败（端口 {}）: {}", port, e);
+            }
         }
 
         Ok(Self {
-            config,
             dex_manager,
+            gas_units,
+            snapshot_writer,
+            notifications,
+            api_state: None,
+            database: None,
+            config_updates: None,
+            display_limit: None,
+            output_format: OutputFormat::Table,
+            start_token: None,
+            demo: false,
+            scan_interval: Duration::from_secs(config.monitoring.scan_interval_seconds),
+            config,
         })
     }
 
+    /// 设置扫描结果的输出格式
+    pub fn set_output_format(&mut self, output_format: OutputFormat) {
+        self.output_format = output_format;
+    }
+
+    /// 设置表格中最多显示的套利机会数量
+    pub fn set_display_limit(&mut self, limit: usize) {
+        self.display_limit = Some(limit);
+    }
+
+    /// 只保留包含指定代币（符号，不区分大小写）的套利机会
+    pub fn set_start_token(&mut self, symbol: String) {
+        self.start_token = Some(symbol);
+    }
+
+    /// 没有发现真实机会时显示演示数据，默认关闭
+    pub fn set_demo(&mut self, demo: bool) {
+        self.demo = demo;
+    }
+
+    /// 设置扫描间隔（默认取配置中的 scan_interval_seconds），为 0 时只扫描一次
+    pub fn set_scan_interval(&mut self, scan_interval: Duration) {
+        self.scan_interval = scan_interval;
+    }
+
+    /// 在基础间隔上叠加抖动，`factor` 取值 [-1, 1]，对应 ±SCAN_INTERVAL_JITTER
+    fn jittered_interval(base: Duration, factor: f64) -> Duration {
+        base.mul_f64(1.0 + SCAN_INTERVAL_JITTER * factor.clamp(-1.0, 1.0))
+    }
+
+    /// 每次扫描后将发现的套利机会写入数据库
+    pub fn set_database(&mut self, database: Database) {
+        self.database = Some(database);
+    }
+
+    /// 每次扫描后将结果发布到 HTTP 接口
+    pub fn set_api_state(&mut self, api_state: SharedApiState) {
+        self.api_state = Some(api_state);
+    }
+
+    /// 订阅配置热更新，新配置在下一次扫描前生效
+    pub fn set_config_updates(&mut self, config_updates: watch::Receiver<Config>) {
+        self.config_updates = Some(config_updates);
+    }
+
+    /// 应用热更新的配置：替换扫描间隔、通知阈值和套利参数；
+    /// DEX 提供者在启动时创建，修改 DEX 配置需要重启
+    fn apply_config(&mut self, config: Config) {
+        self.scan_interval = Duration::from_secs(config.monitoring.scan_interval_seconds);
+        self.notifications = NotificationDispatcher::from_config(&config.monitoring);
+        self.config = config;
+        info!("已应用新配置: 扫描间隔 {}s", self.scan_interval.as_secs());
+    }
+
+    /// 取出尚未应用的配置更新
+    fn take_config_update(&mut self) -> Option<Config> {
+        let updates = self.config_updates.as_mut()?;
+        if !updates.has_changed().unwrap_or(false) {
+            return None;
+        }
+        Some(updates.borrow_and_update().clone())
+    }
+
+    /// 只有 V2/V3 AMM 提供者的 api_url 是 JSON-RPC 节点，可以用 eth_chainId 检查所在链
+    fn probes_chain_id(dex_name: &str) -> bool {
+        matches!(dex_name, dex_types::UNISWAP_V2 | "uniswap_v3" | "sushiswap" | dex_types::PANCAKESWAP)
+    }
+
+    fn matches_start_token(opportunity: &ArbitrageOpportunity, symbol: &str) -> bool {
+        opportunity.token_pair.token_a.symbol.eq_ignore_ascii_case(symbol)
+            || opportunity.token_pair.token_b.symbol.eq_ignore_ascii_case(symbol)
+    }
+
+    /// 按扫描间隔（带随机抖动）循环扫描；间隔为 0 时只扫描一次
     pub async fn start_scan(&mut self) {
+        let one_shot = self.scan_interval.is_zero();
+        let jittered = |base| Self::jittered_interval(base, rand::random::<f64>() * 2.0 - 1.0);
+        let mut period = if one_shot { Duration::from_secs(1) } else { jittered(self.scan_interval) };
         info!("🚀 开始扫描套利机会...");
-        info!("📊 监控配置: 扫描间隔 10s");
+        info!("📊 监控配置: 扫描间隔 {:.1}s", period.as_secs_f64());
         
         // 启动监控循环
-        let mut interval = time::interval(Duration::from_secs(10));
+        let mut interval = time::interval(period);
         let mut scan_count = 0;
 
         loop {
             interval.tick().await;
+            if let Some(config) = self.take_config_update() {
+                self.apply_config(config);
+                if !one_shot && !self.scan_interval.is_zero() {
+                    period = jittered(self.scan_interval);
+                    interval = time::interval_at(time::Instant::now() + period, period);
+                }
+            }
             scan_count += 1;
             info!("🔍 第 {} 次扫描开始", scan_count);
 
@@ -100,29 +295,74 @@ impl ArbitrageMonitor {
                 }
             }
             
-            info!("⏰ 等待 10s 后进行下次扫描...");
+            if one_shot {
+                break;
+            }
+            info!("⏰ 等待 {:.1}s 后进行下次扫描...", period.as_secs_f64());
         }
     }
 
     pub async fn scan_opportunities(&mut self) -> Result<Vec<ArbitrageOpportunity>> {
         info!("开始扫描套利机会...");
+        let scan_started = Instant::now();
 
         // 创建要监控的代币对
         let token_pairs = self.create_token_pairs();
         info!("监控 {} 个代币对", token_pairs.len());
 
         // 从所有 DEX 获取价格
-        let all_prices: HashMap<String, HashMap<TokenPair, Price>> = self
+        let report = self
             .dex_manager
-            .get_prices_from_all_dexes(&token_pairs)
+            .get_prices_from_all_dexes_detailed(&token_pairs)
             .await?;
+        if report.errors.is_empty() {
+            info!("{}/{} 个 DEX 返回了价格", report.responded(), report.total());
+        } else {
+            let failed: Vec<&str> = report.errors.keys().map(|(_, name)| name.as_str()).collect();
+            warn!("{}/{} 个 DEX 返回了价格，失败: {}", report.responded(), report.total(), failed.join(", "));
+        }
+        let all_prices: HashMap<DexKey, HashMap<TokenPair, Price>> = report.prices;
+
+        let edges: usize = all_prices.values().map(|prices| prices.len()).sum();
+        metrics::global().set_graph_edges(edges as u64);
 
         // 分析套利机会
-        let opportunities = self.analyze_arbitrage_opportunities(all_prices).await?;
+        let mut opportunities = self.analyze_arbitrage_opportunities(all_prices).await?;
+        if let Some(symbol) = &self.start_token {
+            opportunities.retain(|opportunity| Self::matches_start_token(opportunity, symbol));
+        }
 
         info!("发现 {} 个潜在套利机会", opportunities.len());
+        metrics::global().inc_chains_found(opportunities.len() as u64);
+        metrics::global().observe_scan_duration(scan_started.elapsed());
 
-        let mut display_opportunities = if opportunities.is_empty() {
+        // 写入本次扫描的快照
+        if let Some(writer) = &self.snapshot_writer {
+            if let Err(e) = writer.write(&opportunities) {
+                warn!("写入套利机会快照失败: {}", e);
+            }
+        }
+
+        // 本次扫描的机会在一个事务中写入数据库
+        if let Some(database) = &self.database {
+            if let Err(e) = database.save_opportunities(&opportunities) {
+                warn!("保存套利机会失败: {}", e);
+            }
+        }
+
+        // 推送达到阈值的机会（后台发送，不阻塞扫描）
+        if let Some(notifications) = &mut self.notifications {
+            notifications.dispatch(&opportunities);
+        }
+
+        if let Some(api_state) = &self.api_state {
+            let mut published = opportunities.clone();
+            published.sort_by(Self::compare_opportunities);
+            api_state.publish_opportunities(published).await;
+            api_state.publish_circuit_states(self.dex_manager.circuit_states()).await;
+        }
+
+        let mut display_opportunities = if self.shows_demo(&opportunities) {
             // 演示模式：如果没有找到真实机会，显示模拟数据
             info!("演示模式：显示模拟套利机会");
             self.create_demo_opportunities()
@@ -131,18 +371,56 @@ impl ArbitrageMonitor {
         };
 
         // 按利润率降序排序
-        display_opportunities.sort_by(|a, b| {
-            b.profit_percentage
-                .partial_cmp(&a.profit_percentage)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        display_opportunities.sort_by(Self::compare_opportunities);
+
+        if let Some(limit) = self.display_limit {
+            display_opportunities.truncate(limit);
+        }
 
-        // 始终显示表格
-        self.display_opportunities_table(&display_opportunities);
+        match self.output_format {
+            OutputFormat::Table => self.display_opportunities_table(&display_opportunities),
+            OutputFormat::Json => print!("{}", Self::opportunities_to_ndjson(&display_opportunities)?),
+        }
 
         Ok(opportunities)
     }
 
+    /// 是否用演示数据代替空的扫描结果：只在显式开启 --demo 的表格输出中显示，
+    /// JSON 输出和按代币过滤的结果只包含真实机会
+    fn shows_demo(&self, opportunities: &[ArbitrageOpportunity]) -> bool {
+        self.demo && opportunities.is_empty() && self.output_format == OutputFormat::Table && self.start_token.is_none()
+    }
+
+    /// 套利机会的排序规则，保证结果在多次运行间稳定：
+    /// 利润率降序，相同时置信度降序，再按代币对和买卖 DEX 的字典序
+    fn compare_opportunities(a: &ArbitrageOpportunity, b: &ArbitrageOpportunity) -> std::cmp::Ordering {
+        b.profit_percentage
+            .total_cmp(&a.profit_percentage)
+            .then_with(|| b.confidence_score.total_cmp(&a.confidence_score))
+            .then_with(|| Self::opportunity_path(a).cmp(&Self::opportunity_path(b)))
+    }
+
+    /// 套利路径的文本表示，例如 `WETH/USDT:uniswap_v2->sushiswap`
+    fn opportunity_path(opportunity: &ArbitrageOpportunity) -> String {
+        format!(
+            "{}/{}:{}->{}",
+            opportunity.token_pair.token_a.symbol,
+            opportunity.token_pair.token_b.symbol,
+            opportunity.buy_dex,
+            opportunity.sell_dex
+        )
+    }
+
+    /// 将套利机会序列化为 NDJSON，每行一个机会；BigDecimal 字段序列化为十进制字符串以保留精度
+    fn opportunities_to_ndjson(opportunities: &[ArbitrageOpportunity]) -> Result<String> {
+        let mut output = String::new();
+        for opportunity in opportunities {
+            output.push_str(&serde_json::to_string(opportunity)?);
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
     /// 以表格形式显示套利机会
     fn display_opportunities_table(&self, opportunities: &[ArbitrageOpportunity]) {
         println!("\n🔍 发现的套利机会:");
@@ -193,6 +471,7 @@ impl ArbitrageMonitor {
                 liquidity: BigDecimal::from_str("1000000").unwrap(),
                 gas_cost_estimate: BigDecimal::from_str("0.005").unwrap(),
                 confidence_score: 0.85,
+                sandwich_risk: 0.02,
                 timestamp: Utc::now(),
             },
             ArbitrageOpportunity {
@@ -207,6 +486,7 @@ impl ArbitrageMonitor {
                 liquidity: BigDecimal::from_str("2500000").unwrap(),
                 gas_cost_estimate: BigDecimal::from_str("0.008").unwrap(),
                 confidence_score: 0.92,
+                sandwich_risk: 0.01,
                 timestamp: Utc::now(),
             },
         ]
@@ -260,25 +540,25 @@ impl ArbitrageMonitor {
 
     async fn analyze_arbitrage_opportunities(
         &self,
-        all_prices: HashMap<String, HashMap<TokenPair, Price>>,
+        all_prices: HashMap<DexKey, HashMap<TokenPair, Price>>,
     ) -> Result<Vec<ArbitrageOpportunity>> {
         let mut opportunities = Vec::new();
 
-        // 为每个代币对分析不同 DEX 之间的价格差异
-        let mut token_pair_prices: HashMap<TokenPair, Vec<(String, Price)>> = HashMap::new();
+        // 为每个代币对分析同一条链上不同 DEX 之间的价格差异，不同链的价格不能直接套利
+        let mut token_pair_prices: HashMap<(u64, TokenPair), Vec<(String, Price)>> = HashMap::new();
 
         // 整理价格数据
-        for (dex_name, prices) in all_prices {
+        for ((chain_id, dex_name), prices) in all_prices {
             for (token_pair, price) in prices {
                 token_pair_prices
-                    .entry(token_pair)
+                    .entry((chain_id, token_pair))
                     .or_insert_with(Vec::new)
                     .push((dex_name.clone(), price));
             }
         }
 
         // 分析每个代币对的套利机会
-        for (token_pair, dex_prices) in token_pair_prices {
+        for ((_, token_pair), dex_prices) in token_pair_prices {
             if dex_prices.len() < 2 {
                 continue; // 需要至少两个 DEX 的价格才能进行套利
             }
@@ -314,11 +594,7 @@ impl ArbitrageMonitor {
         }
 
         // 按利润百分比排序
-        opportunities.sort_by(|a, b| {
-            b.profit_percentage
-                .partial_cmp(&a.profit_percentage)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        opportunities.sort_by(Self::compare_opportunities);
 
         Ok(opportunities)
     }
@@ -341,12 +617,15 @@ impl ArbitrageMonitor {
         let price_diff = &sell_dex.1.price - &buy_dex.1.price;
         let estimated_profit = &price_diff * &available_liquidity * BigDecimal::from_str("0.1")?; // 假设使用 10% 的流动性
 
-        // 估算 Gas 成本（简化）
-        let gas_cost_estimate = BigDecimal::from_str("0.01")?; // 假设 0.01 ETH 的 Gas 成本
+        // 估算 Gas 成本：买入和卖出两笔兑换的 gas 用量之和
+        let gas_price = self.get_gas_price().await?;
+        let gas_units = self.gas_units_for(&buy_dex.0) + self.gas_units_for(&sell_dex.0);
+        let gas_cost_estimate = Self::estimate_gas_cost(gas_units, &gas_price.standard);
 
         // 计算置信度分数
         let confidence_score =
-            self.calculate_confidence_score(&buy_dex.1, &sell_dex.1, profit_percentage);
+            Self::calculate_confidence_score(&buy_dex.1, &sell_dex.1, profit_percentage, Utc::now());
+        let sandwich_risk = Self::calculate_sandwich_risk(&available_liquidity, PUBLIC_MEMPOOL_HOPS);
 
         Ok(ArbitrageOpportunity {
             id: generate_id(),
@@ -361,45 +640,64 @@ impl ArbitrageMonitor {
             gas_cost_estimate,
             timestamp: Utc::now(),
             confidence_score,
+            sandwich_risk,
         })
     }
 
+    /// 计算 0-1 的夹子风险：每次公开兑换被夹的概率按 `交易规模 / (交易规模 + 流动性)` 估计，
+    /// 多次兑换中任意一次被夹即视为有风险，即 `1 - (流动性 / (交易规模 + 流动性))^hops`
+    fn calculate_sandwich_risk(min_liquidity: &BigDecimal, public_hops: u32) -> f64 {
+        let liquidity = min_liquidity.to_f64().unwrap_or(0.0).max(0.0);
+        let safe_per_hop = liquidity / (CONFIDENCE_TRADE_SIZE + liquidity);
+        1.0 - safe_per_hop.powi(public_hops as i32)
+    }
+
+    /// 计算 0-100 的置信度分数，由三部分组成：
+    /// - 利润率（0-40 分）：每 1% 利润 10 分，封顶 40 分
+    /// - 流动性深度（0-30 分）：`30 / (1 + 交易规模 / 较浅一侧流动性)`，池子越浅分数越低
+    /// - 数据新鲜度（0-30 分）：按较旧一侧价格的时间戳指数衰减，每 `PRICE_FRESHNESS_HALF_LIFE_SECS` 秒减半
     fn calculate_confidence_score(
-        &self,
         buy_price: &Price,
         sell_price: &Price,
         profit_percentage: f64,
+        now: DateTime<Utc>,
     ) -> f64 {
         let mut score = 0.0;
 
         // 基于利润百分比的分数（0-40分）
-        score += (profit_percentage * 10.0).min(40.0);
+        score += (profit_percentage * 10.0).clamp(0.0, 40.0);
 
-        // 基于流动性的分数（0-30分）
+        // 基于流动性深度的分数（0-30分）
         let min_liquidity = if buy_price.liquidity < sell_price.liquidity {
             &buy_price.liquidity
         } else {
             &sell_price.liquidity
         };
+        let min_liquidity = min_liquidity.to_f64().unwrap_or(0.0);
+        if min_liquidity > 0.0 {
+            score += 30.0 / (1.0 + CONFIDENCE_TRADE_SIZE / min_liquidity);
+        }
 
-        let liquidity_score = if *min_liquidity > BigDecimal::from(100000) {
-            30.0
-        } else if *min_liquidity > BigDecimal::from(50000) {
-            20.0
-        } else if *min_liquidity > BigDecimal::from(10000) {
-            10.0
-        } else {
-            5.0
-        };
+        // 基于数据新鲜度的分数（0-30分）
+        let oldest = buy_price.timestamp.min(sell_price.timestamp);
+        let age_secs = (now - oldest).num_milliseconds().max(0) as f64 / 1000.0;
+        score += 30.0 * 0.5f64.powf(age_secs / PRICE_FRESHNESS_HALF_LIFE_SECS);
 
-        score += liquidity_score;
+        // 确保分数在 0-100 范围内
+        score.clamp(0.0, 100.0)
+    }
 
-        // 基于价格稳定性的分数（0-30分）
-        // 这里简化为固定分数，实际应该基于历史价格波动
-        score += 20.0;
+    /// 指定 DEX 单次兑换的 gas 用量，未知的 DEX 按名称取默认值
+    fn gas_units_for(&self, dex_name: &str) -> u64 {
+        self.gas_units
+            .get(dex_name)
+            .copied()
+            .unwrap_or_else(|| default_gas_units(dex_name))
+    }
 
-        // 确保分数在 0-100 范围内
-        score.min(100.0).max(0.0)
+    /// 按 gas 用量和 gas 价格（Gwei）估算成本，单位 ETH
+    fn estimate_gas_cost(gas_units: u64, gas_price_gwei: &BigDecimal) -> BigDecimal {
+        BigDecimal::from(gas_units) * gas_price_gwei / pow10(9)
     }
 
     pub async fn get_gas_price(&self) -> Result<GasPrice> {
@@ -413,3 +711,212 @@ impl ArbitrageMonitor {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_opportunity() -> ArbitrageOpportunity {
+        let token_pair = TokenPair {
+            token_a: Token::new("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string(), "WETH".to_string(), "Wrapped Ether".to_string(), 18, 1),
+            token_b: Token::new("0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(), "USDT".to_string(), "Tether USD".to_string(), 6, 1),
+        };
+        ArbitrageOpportunity {
+            id: "arb-1".to_string(),
+            token_pair,
+            buy_dex: "uniswap_v2".to_string(),
+            sell_dex: "sushiswap".to_string(),
+            buy_price: BigDecimal::from_str("2000.123456789012345678").unwrap(),
+            sell_price: BigDecimal::from_str("2010.5").unwrap(),
+            profit_percentage: 0.52,
+            estimated_profit: BigDecimal::from_str("10.37").unwrap(),
+            liquidity: BigDecimal::from(1_000_000),
+            gas_cost_estimate: BigDecimal::from_str("0.01").unwrap(),
+            timestamp: Utc::now(),
+            confidence_score: 0.8,
+            sandwich_risk: 0.02,
+        }
+    }
+
+    #[test]
+    fn test_opportunities_to_ndjson() {
+        let opportunity = sample_opportunity();
+
+        let output = ArbitrageMonitor::opportunities_to_ndjson(&[opportunity.clone(), opportunity]).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        // 价格以字符串形式输出，精度不丢失
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value["buy_price"], serde_json::Value::String("2000.123456789012345678".to_string()));
+        assert!(lines[0].contains("\"sell_price\":\"2010.5\""));
+    }
+
+    fn test_price(liquidity: i64, age_secs: i64, now: DateTime<Utc>) -> Price {
+        Price {
+            token_pair: TokenPair {
+                token_a: Token::new("0xa".to_string(), "WETH".to_string(), "Wrapped Ether".to_string(), 18, 1),
+                token_b: Token::new("0xb".to_string(), "USDT".to_string(), "Tether USD".to_string(), 6, 1),
+            },
+            price: BigDecimal::from(2000),
+            liquidity: BigDecimal::from(liquidity),
+            dex: "uniswap_v2".to_string(),
+            timestamp: now - chrono::Duration::seconds(age_secs),
+            block_number: None,
+        }
+    }
+
+    #[test]
+    fn test_confidence_score_decays_with_age_and_depth() {
+        let now = Utc::now();
+        let deep = test_price(1_000_000, 0, now);
+
+        // 价格越旧分数越低
+        let mut last = f64::MAX;
+        for age in [0, 10, 30, 120, 600] {
+            let score = ArbitrageMonitor::calculate_confidence_score(&deep, &test_price(1_000_000, age, now), 1.0, now);
+            assert!(score < last, "age {} score {} not below {}", age, score, last);
+            assert!((0.0..=100.0).contains(&score));
+            last = score;
+        }
+
+        // 流动性越浅分数越低
+        let mut last = f64::MAX;
+        for liquidity in [1_000_000, 100_000, 10_000, 1_000, 0] {
+            let score = ArbitrageMonitor::calculate_confidence_score(&deep, &test_price(liquidity, 0, now), 1.0, now);
+            assert!(score < last, "liquidity {} score {} not below {}", liquidity, score, last);
+            last = score;
+        }
+
+        // 新鲜、深度充足且利润高的机会接近满分
+        let score = ArbitrageMonitor::calculate_confidence_score(&deep, &deep, 10.0, now);
+        assert!(score > 99.0 && score <= 100.0);
+    }
+
+    #[test]
+    fn test_gas_cost_uses_configured_units() {
+        let config = Config::load().unwrap();
+        let mut curve = config.dex_configs["curve"].clone();
+        assert_eq!(curve.gas_units_or_default("curve"), 250_000);
+
+        curve.gas_units = Some(400_000);
+        let units = curve.gas_units_or_default("curve") + default_gas_units("sushiswap");
+        assert_eq!(units, 550_000);
+
+        // 550k gas × 20 Gwei = 0.011 ETH
+        let cost = ArbitrageMonitor::estimate_gas_cost(units, &BigDecimal::from(20));
+        assert_eq!(cost, BigDecimal::from_str("0.011").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scan_interval_from_config() {
+        let mut config = Config::load().unwrap();
+        config.monitoring.scan_interval_seconds = 2;
+        for dex_config in config.dex_configs.values_mut() {
+            dex_config.enabled = false;
+        }
+
+        let mut monitor = ArbitrageMonitor::new(config.clone()).await.unwrap();
+        assert_eq!(monitor.scan_interval, Duration::from_secs(2));
+
+        // 热更新的配置只应用一次
+        let (sender, receiver) = watch::channel(config.clone());
+        monitor.set_config_updates(receiver);
+        assert!(monitor.take_config_update().is_none());
+        config.monitoring.scan_interval_seconds = 5;
+        sender.send(config).unwrap();
+        let update = monitor.take_config_update().unwrap();
+        monitor.apply_config(update);
+        assert_eq!(monitor.scan_interval, Duration::from_secs(5));
+        assert!(monitor.take_config_update().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_demo_opportunities_require_flag() {
+        let mut config = Config::load().unwrap();
+        for dex_config in config.dex_configs.values_mut() {
+            dex_config.enabled = false;
+        }
+        let mut monitor = ArbitrageMonitor::new(config).await.unwrap();
+
+        // 默认不显示演示数据
+        assert!(!monitor.shows_demo(&[]));
+        monitor.set_demo(true);
+        assert!(monitor.shows_demo(&[]));
+        assert!(!monitor.shows_demo(&[sample_opportunity()]));
+
+        // 按代币过滤或 JSON 输出时只显示真实机会
+        monitor.set_start_token("WETH".to_string());
+        assert!(!monitor.shows_demo(&[]));
+        monitor.start_token = None;
+        monitor.set_output_format(OutputFormat::Json);
+        assert!(!monitor.shows_demo(&[]));
+    }
+
+    #[test]
+    fn test_jittered_interval_bounds() {
+        let base = Duration::from_secs(10);
+        assert_eq!(ArbitrageMonitor::jittered_interval(base, -1.0), Duration::from_secs(9));
+        assert_eq!(ArbitrageMonitor::jittered_interval(base, 1.0), Duration::from_secs(11));
+        assert_eq!(ArbitrageMonitor::jittered_interval(base, 5.0), Duration::from_secs(11));
+        for _ in 0..100 {
+            let interval = ArbitrageMonitor::jittered_interval(base, rand::random::<f64>() * 2.0 - 1.0);
+            assert!(interval >= Duration::from_secs(9) && interval <= Duration::from_secs(11));
+        }
+    }
+
+    #[test]
+    fn test_sandwich_risk_higher_for_shallow_pools() {
+        let shallow = ArbitrageMonitor::calculate_sandwich_risk(&BigDecimal::from(1_000), PUBLIC_MEMPOOL_HOPS);
+        let deep = ArbitrageMonitor::calculate_sandwich_risk(&BigDecimal::from(10_000_000), PUBLIC_MEMPOOL_HOPS);
+        assert!(shallow > 0.9 && shallow <= 1.0);
+        assert!(deep < 0.01);
+
+        // 经过的公开兑换越多风险越高
+        let one_hop = ArbitrageMonitor::calculate_sandwich_risk(&BigDecimal::from(100_000), 1);
+        let two_hops = ArbitrageMonitor::calculate_sandwich_risk(&BigDecimal::from(100_000), 2);
+        assert!(two_hops > one_hop);
+    }
+
+    #[test]
+    fn test_probes_chain_id_only_for_rpc_providers() {
+        assert!(ArbitrageMonitor::probes_chain_id(dex_types::UNISWAP_V2));
+        assert!(ArbitrageMonitor::probes_chain_id("uniswap_v3"));
+        assert!(ArbitrageMonitor::probes_chain_id(dex_types::PANCAKESWAP));
+        assert!(!ArbitrageMonitor::probes_chain_id(dex_types::CURVE));
+        assert!(!ArbitrageMonitor::probes_chain_id(dex_types::BALANCER));
+    }
+
+    #[test]
+    fn test_matches_start_token() {
+        let opportunity = sample_opportunity();
+        assert!(ArbitrageMonitor::matches_start_token(&opportunity, "weth"));
+        assert!(ArbitrageMonitor::matches_start_token(&opportunity, "USDT"));
+        assert!(!ArbitrageMonitor::matches_start_token(&opportunity, "DAI"));
+    }
+
+    #[test]
+    fn test_opportunity_ordering_is_deterministic() {
+        let with = |buy_dex: &str, sell_dex: &str, confidence_score: f64| ArbitrageOpportunity {
+            buy_dex: buy_dex.to_string(),
+            sell_dex: sell_dex.to_string(),
+            confidence_score,
+            ..sample_opportunity()
+        };
+        let expected = vec![
+            "WETH/USDT:curve->uniswap_v2",
+            "WETH/USDT:sushiswap->uniswap_v2",
+            "WETH/USDT:uniswap_v2->sushiswap",
+        ];
+
+        // 利润率相同时，先比较置信度，再比较路径，输入顺序不影响结果
+        for mut opportunities in [
+            vec![with("uniswap_v2", "sushiswap", 50.0), with("sushiswap", "uniswap_v2", 50.0), with("curve", "uniswap_v2", 90.0)],
+            vec![with("curve", "uniswap_v2", 90.0), with("uniswap_v2", "sushiswap", 50.0), with("sushiswap", "uniswap_v2", 50.0)],
+        ] {
+            opportunities.sort_by(ArbitrageMonitor::compare_opportunities);
+            let paths: Vec<String> = opportunities.iter().map(ArbitrageMonitor::opportunity_path).collect();
+            assert_eq!(paths, expected);
+        }
+    }
+}
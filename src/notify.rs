@@ -0,0 +1,431 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::config::MonitoringConfig;
+use crate::types::ArbitrageOpportunity;
+
+/// Telegram Bot API 地址
+const TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+
+/// Telegram 待发送消息队列长度
+const TELEGRAM_QUEUE_SIZE: usize = 32;
+
+/// 同一聊天两条消息之间的最短间隔（Telegram 限制约每秒一条）
+const TELEGRAM_SEND_INTERVAL: Duration = Duration::from_millis(1100);
+
+/// 收到 429 后的最大重试次数
+const TELEGRAM_MAX_RETRIES: u32 = 3;
+
+/// 套利机会通知渠道
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// 通知渠道名称，用于日志
+    fn name(&self) -> &str;
+
+    /// 触发通知的最小利润率（百分比）
+    fn min_profit_percentage(&self) -> f64;
+
+    /// 发送一条套利机会通知
+    async fn notify(&self, opportunity: &ArbitrageOpportunity) -> Result<()>;
+}
+
+/// Webhook 请求体
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    token_pair: String,
+    buy_dex: &'a str,
+    sell_dex: &'a str,
+    buy_price: String,
+    sell_price: String,
+    estimated_profit: String,
+    profit_percentage: f64,
+    summary: String,
+    timestamp: String,
+}
+
+impl<'a> WebhookPayload<'a> {
+    fn from_opportunity(opportunity: &'a ArbitrageOpportunity) -> Self {
+        let token_pair = format!(
+            "{}/{}",
+            opportunity.token_pair.token_a.symbol, opportunity.token_pair.token_b.symbol
+        );
+        Self {
+            summary: format!("{}: {} -> {}", token_pair, opportunity.buy_dex, opportunity.sell_dex),
+            token_pair,
+            buy_dex: &opportunity.buy_dex,
+            sell_dex: &opportunity.sell_dex,
+            buy_price: opportunity.buy_price.to_string(),
+            sell_price: opportunity.sell_price.to_string(),
+            estimated_profit: opportunity.estimated_profit.to_string(),
+            profit_percentage: opportunity.profit_percentage,
+            timestamp: opportunity.timestamp.to_rfc3339(),
+        }
+    }
+}
+
+/// 通过 HTTP POST JSON 的方式推送套利机会
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    min_profit_percentage: f64,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String, min_profit_percentage: f64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            min_profit_percentage,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn min_profit_percentage(&self) -> f64 {
+        self.min_profit_percentage
+    }
+
+    async fn notify(&self, opportunity: &ArbitrageOpportunity) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&WebhookPayload::from_opportunity(opportunity))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Webhook returned status {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// 通过 Telegram Bot `sendMessage` 推送套利机会。消息先进入内部队列，
+/// 由后台任务按固定间隔发送，遇到 429 时按 `retry_after` 退避重试
+pub struct TelegramNotifier {
+    queue: mpsc::Sender<String>,
+    min_profit_percentage: f64,
+}
+
+impl TelegramNotifier {
+    /// 创建通知器并启动后台发送任务（需要在 tokio 运行时中调用）
+    pub fn new(bot_token: String, chat_id: String, min_profit_percentage: f64) -> Self {
+        Self::with_api_base(TELEGRAM_API_BASE, bot_token, chat_id, min_profit_percentage)
+    }
+
+    pub fn with_api_base(api_base: &str, bot_token: String, chat_id: String, min_profit_percentage: f64) -> Self {
+        let (queue, receiver) = mpsc::channel(TELEGRAM_QUEUE_SIZE);
+        let url = format!("{}/bot{}/sendMessage", api_base.trim_end_matches('/'), bot_token);
+        tokio::spawn(Self::run_queue(reqwest::Client::new(), url, chat_id, receiver));
+        Self {
+            queue,
+            min_profit_percentage,
+        }
+    }
+
+    /// 格式化通知消息：路径用箭头连接（买入 DEX 换入、卖出 DEX 换回），附带利润与置信度
+    pub fn format_message(opportunity: &ArbitrageOpportunity) -> String {
+        let token_a = &opportunity.token_pair.token_a.symbol;
+        let token_b = &opportunity.token_pair.token_b.symbol;
+        format!(
+            "💰 套利机会 {}\n{} → {} → {}\n买入: {} @ {}\n卖出: {} @ {}\n预估利润: {} ({:.4}%)\n置信度: {:.2}",
+            opportunity.id,
+            token_a,
+            token_b,
+            token_a,
+            opportunity.buy_dex,
+            opportunity.buy_price,
+            opportunity.sell_dex,
+            opportunity.sell_price,
+            opportunity.estimated_profit,
+            opportunity.profit_percentage,
+            opportunity.confidence_score,
+        )
+    }
+
+    /// 后台发送循环：逐条发送，两条消息之间保持最短间隔
+    async fn run_queue(client: reqwest::Client, url: String, chat_id: String, mut receiver: mpsc::Receiver<String>) {
+        while let Some(text) = receiver.recv().await {
+            let mut attempt = 0;
+            loop {
+                match Self::send_message(&client, &url, &chat_id, &text).await {
+                    Ok(None) => break,
+                    Ok(Some(retry_after)) if attempt < TELEGRAM_MAX_RETRIES => {
+                        attempt += 1;
+                        warn!("Telegram 触发限流，{}s 后进行第 {} 次重试", retry_after.as_secs(), attempt);
+                        tokio::time::sleep(retry_after).await;
+                    }
+                    Ok(Some(_)) => {
+                        warn!("Telegram 限流重试次数已用完，丢弃消息");
+                        break;
+                    }
+                    Err(e) => {
+                        warn!("Telegram 消息发送失败: {}", e);
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(TELEGRAM_SEND_INTERVAL).await;
+        }
+    }
+
+    /// 发送一条消息，被限流时返回需要等待的时间
+    async fn send_message(client: &reqwest::Client, url: &str, chat_id: &str, text: &str) -> Result<Option<Duration>> {
+        let response = client
+            .post(url)
+            .json(&serde_json::json!({ "chat_id": chat_id, "text": text }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            let retry_after = body["parameters"]["retry_after"].as_u64().unwrap_or(1);
+            return Ok(Some(Duration::from_secs(retry_after)));
+        }
+        if !status.is_success() {
+            return Err(anyhow!("Telegram returned status {}", status));
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn min_profit_percentage(&self) -> f64 {
+        self.min_profit_percentage
+    }
+
+    async fn notify(&self, opportunity: &ArbitrageOpportunity) -> Result<()> {
+        self.queue
+            .try_send(Self::format_message(opportunity))
+            .map_err(|e| anyhow!("Telegram 消息队列不可用: {}", e))
+    }
+}
+
+/// 通知分发器 - 筛选达到阈值的机会，同一机会在去重窗口内只通知一次，发送在后台任务中进行
+pub struct NotificationDispatcher {
+    notifiers: Vec<Arc<dyn Notifier>>,
+    dedup_window: Duration,
+    last_sent: HashMap<String, Instant>,
+}
+
+impl NotificationDispatcher {
+    pub fn new(dedup_window: Duration) -> Self {
+        Self {
+            notifiers: Vec::new(),
+            dedup_window,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// 根据配置创建分发器，未配置任何通知渠道时返回 None
+    pub fn from_config(config: &MonitoringConfig) -> Option<Self> {
+        let mut dispatcher = Self::new(Duration::from_secs(config.notification_dedup_seconds));
+        if let Some(webhook) = &config.webhook {
+            dispatcher.add_notifier(Arc::new(WebhookNotifier::new(
+                webhook.url.clone(),
+                webhook.min_profit_percentage,
+            )));
+        }
+        if let Some(telegram) = &config.telegram {
+            dispatcher.add_notifier(Arc::new(TelegramNotifier::new(
+                telegram.bot_token.clone(),
+                telegram.chat_id.clone(),
+                telegram.min_profit_percentage,
+            )));
+        }
+        if dispatcher.notifiers.is_empty() {
+            None
+        } else {
+            Some(dispatcher)
+        }
+    }
+
+    pub fn add_notifier(&mut self, notifier: Arc<dyn Notifier>) {
+        self.notifiers.push(notifier);
+    }
+
+    /// 去重键：代币对地址 + 买入/卖出 DEX
+    fn opportunity_key(opportunity: &ArbitrageOpportunity) -> String {
+        format!(
+            "{}/{}:{}->{}",
+            opportunity.token_pair.token_a.address.to_lowercase(),
+            opportunity.token_pair.token_b.address.to_lowercase(),
+            opportunity.buy_dex,
+            opportunity.sell_dex
+        )
+    }
+
+    /// 分发一批套利机会，返回实际触发通知的次数
+    pub fn dispatch(&mut self, opportunities: &[ArbitrageOpportunity]) -> usize {
+        let now = Instant::now();
+        let dedup_window = self.dedup_window;
+        self.last_sent.retain(|_, sent_at| now.duration_since(*sent_at) < dedup_window);
+
+        let mut fired = 0;
+        for opportunity in opportunities {
+            let qualifying: Vec<Arc<dyn Notifier>> = self
+                .notifiers
+                .iter()
+                .filter(|notifier| opportunity.profit_percentage >= notifier.min_profit_percentage())
+                .cloned()
+                .collect();
+            if qualifying.is_empty() {
+                continue;
+            }
+
+            let key = Self::opportunity_key(opportunity);
+            if self.last_sent.contains_key(&key) {
+                continue;
+            }
+            self.last_sent.insert(key, now);
+
+            for notifier in qualifying {
+                let opportunity = opportunity.clone();
+                fired += 1;
+                tokio::spawn(async move {
+                    match notifier.notify(&opportunity).await {
+                        Ok(()) => info!("已通过 {} 推送套利机会 {}", notifier.name(), opportunity.id),
+                        Err(e) => warn!("通过 {} 推送套利机会失败: {}", notifier.name(), e),
+                    }
+                });
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Token, TokenPair};
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+    use std::str::FromStr;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn sample_opportunity(profit_percentage: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            id: "arb-1".to_string(),
+            token_pair: TokenPair {
+                token_a: Token::new("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string(), "WETH".to_string(), "Wrapped Ether".to_string(), 18, 1),
+                token_b: Token::new("0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(), "USDT".to_string(), "Tether USD".to_string(), 6, 1),
+            },
+            buy_dex: "uniswap_v2".to_string(),
+            sell_dex: "sushiswap".to_string(),
+            buy_price: BigDecimal::from_str("2000.5").unwrap(),
+            sell_price: BigDecimal::from_str("2030.25").unwrap(),
+            profit_percentage,
+            estimated_profit: BigDecimal::from_str("29.75").unwrap(),
+            liquidity: BigDecimal::from(1_000_000),
+            gas_cost_estimate: BigDecimal::from_str("0.01").unwrap(),
+            timestamp: Utc::now(),
+            confidence_score: 0.8,
+            sandwich_risk: 0.02,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_payload_shape() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(body_partial_json(serde_json::json!({
+                "token_pair": "WETH/USDT",
+                "buy_dex": "uniswap_v2",
+                "sell_dex": "sushiswap",
+                "estimated_profit": "29.75",
+                "profit_percentage": 1.5,
+                "summary": "WETH/USDT: uniswap_v2 -> sushiswap",
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let notifier = WebhookNotifier::new(format!("{}/hook", server.uri()), 1.0);
+        notifier.notify(&sample_opportunity(1.5)).await.unwrap();
+    }
+
+    #[test]
+    fn test_telegram_message_format() {
+        let message = TelegramNotifier::format_message(&sample_opportunity(1.5));
+        assert!(message.contains("WETH → USDT → WETH"));
+        assert!(message.contains("买入: uniswap_v2 @ 2000.5"));
+        assert!(message.contains("卖出: sushiswap @ 2030.25"));
+        assert!(message.contains("预估利润: 29.75 (1.5000%)"));
+        assert!(message.contains("置信度: 0.80"));
+    }
+
+    #[tokio::test]
+    async fn test_telegram_retries_after_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/bottoken/sendMessage"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(serde_json::json!({
+                "ok": false,
+                "error_code": 429,
+                "parameters": { "retry_after": 0 }
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/bottoken/sendMessage"))
+            .and(body_partial_json(serde_json::json!({ "chat_id": "42" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let notifier = TelegramNotifier::with_api_base(&server.uri(), "token".to_string(), "42".to_string(), 1.0);
+        notifier.notify(&sample_opportunity(1.5)).await.unwrap();
+
+        // 等待后台任务完成重试
+        for _ in 0..50 {
+            if server.received_requests().await.map(|r| r.len()).unwrap_or(0) >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_threshold_and_dedup() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let mut dispatcher = NotificationDispatcher::new(Duration::from_secs(60));
+        dispatcher.add_notifier(Arc::new(WebhookNotifier::new(server.uri(), 1.0)));
+
+        // 低于阈值不通知；同一机会在去重窗口内只通知一次
+        assert_eq!(dispatcher.dispatch(&[sample_opportunity(0.5)]), 0);
+        assert_eq!(dispatcher.dispatch(&[sample_opportunity(1.5)]), 1);
+        assert_eq!(dispatcher.dispatch(&[sample_opportunity(2.0)]), 0);
+
+        let mut other = sample_opportunity(1.5);
+        other.sell_dex = "curve".to_string();
+        assert_eq!(dispatcher.dispatch(&[other]), 1);
+    }
+}
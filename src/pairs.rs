@@ -6,12 +6,18 @@ use crate::config::{protocol_types, dex_types};
 /// 交易对管理器 - 负责业务逻辑
 pub struct PairManager {
     database: Database,
+    canonicalize: bool,
 }
 
 impl PairManager {
     /// 创建新的交易对管理器
     pub fn new(database: &Database) -> Self {
-        Self { database: database.clone() }
+        Self { database: database.clone(), canonicalize: true }
+    }
+
+    /// 设置是否将交易对统一为规范的 token0/token1 顺序（token0 为地址较小的代币）
+    pub fn set_canonicalize(&mut self, canonicalize: bool) {
+        self.canonicalize = canonicalize;
     }
 
     /// 保存交易对列表到数据库 - 业务逻辑
@@ -19,8 +25,11 @@ impl PairManager {
         // 业务逻辑：数据验证
         self.validate_pairs(pairs)?;
         
-        // 业务逻辑：数据预处理（这里直接使用原始数据）
-        // 如果需要预处理，可以在这里添加逻辑
+        // 业务逻辑：数据预处理，统一 token0/token1 顺序
+        if self.canonicalize && pairs.iter().any(|pair| !pair.is_canonical()) {
+            let pairs: Vec<PairData> = pairs.iter().cloned().map(|pair| self.postprocess_pair(pair)).collect();
+            return self.database.save_pairs(&pairs);
+        }
         
         // 调用数据库层的方法
         self.database.save_pairs(pairs)
@@ -83,6 +92,20 @@ impl PairManager {
         Ok(pair.map(|p| self.postprocess_pair(p)))
     }
 
+    /// 根据token地址查找相关交易对 - 业务逻辑
+    pub fn find_pairs_by_token(&self, token_id: &str) -> Result<Vec<PairData>> {
+        // 业务逻辑：参数验证
+        if token_id.is_empty() {
+            return Err(anyhow::anyhow!("Token ID cannot be empty"));
+        }
+
+        // 调用数据库层的方法
+        let pairs = self.database.find_pairs_by_token(token_id)?;
+
+        // 业务逻辑：结果处理
+        Ok(self.postprocess_pairs(pairs))
+    }
+
     /// 获取交易对统计信息 - 业务逻辑
     pub fn get_pairs_stats(&self) -> Result<(usize, f64, f64)> {
         // 调用数据库层的方法
@@ -139,12 +162,15 @@ impl PairManager {
     /// 后处理交易对数据
     fn postprocess_pairs(&self, pairs: Vec<PairData>) -> Vec<PairData> {
         // 这里可以添加数据转换、排序等逻辑
-        pairs
+        pairs.into_iter().map(|pair| self.postprocess_pair(pair)).collect()
     }
 
     /// 后处理单个交易对数据
-    fn postprocess_pair(&self, pair: PairData) -> PairData {
-        // 这里可以添加单个交易对的数据处理逻辑
+    fn postprocess_pair(&self, mut pair: PairData) -> PairData {
+        // 统一为规范顺序，保证 reserve1/reserve0 始终表示 token1/token0 的价格
+        if self.canonicalize && pair.canonicalize() {
+            log::debug!("交易对 {} 的 token 顺序已调整为规范顺序", pair.id);
+        }
         pair
     }
 
@@ -180,17 +206,18 @@ impl PairManager {
 mod tests {
     use super::*;
     use crate::thegraph::TokenInfo;
+    use std::str::FromStr;
 
     #[test]
     fn test_pair_manager_creation() {
-        let database = Database::new(Some("test_pairs.db")).unwrap();
+        let database = Database::new(None).unwrap();
         let _manager = PairManager::new(&database);
         // PairManager现在包含Database实例
     }
 
     #[test]
     fn test_validate_pairs() {
-        let database = Database::new(Some("test_pairs.db")).unwrap();
+        let database = Database::new(None).unwrap();
         let manager = PairManager::new(&database);
         
         // 测试有效数据
@@ -206,7 +233,7 @@ mod tests {
 
     #[test]
     fn test_validate_filter_params() {
-        let database = Database::new(Some("test_pairs.db")).unwrap();
+        let database = Database::new(None).unwrap();
         let manager = PairManager::new(&database);
         
         // 测试有效参数
@@ -221,7 +248,7 @@ mod tests {
 
     #[test]
     fn test_supported_networks_and_dex_types() {
-        let database = Database::new(Some("test_pairs.db")).unwrap();
+        let database = Database::new(None).unwrap();
         let manager = PairManager::new(&database);
         
         assert!(manager.is_network_supported("ethereum"));
@@ -233,7 +260,7 @@ mod tests {
 
     #[test]
     fn test_process_stats() {
-        let database = Database::new(Some("test_pairs.db")).unwrap();
+        let database = Database::new(None).unwrap();
         let manager = PairManager::new(&database);
         let stats = (100, 123.456789, 987.654321);
         let processed = manager.process_stats(stats);
@@ -243,6 +270,75 @@ mod tests {
         assert_eq!(processed.2, 987.65);
     }
 
+    #[test]
+    fn test_canonicalize_pair_order() {
+        let database = Database::new(None).unwrap();
+        let manager = PairManager::new(&database);
+
+        // 反向给出的交易对：token0 为 WETH（地址较大），reserve0 为 1 WETH
+        let mut flipped = get_demo_pair();
+        std::mem::swap(&mut flipped.token0, &mut flipped.token1);
+        flipped.reserve0 = "1".to_string();
+        flipped.reserve1 = "2000".to_string();
+        assert!(!flipped.is_canonical());
+
+        manager.save_pairs(&[flipped]).unwrap();
+        let pair = manager.find_pair_by_id("0x123").unwrap().unwrap();
+
+        assert_eq!(pair.token0.symbol, "USDC");
+        assert_eq!(pair.reserve0, "2000");
+        assert_eq!(pair.reserve1, "1");
+
+        // reserve1/reserve0 对应 token1/token0：1 USDC = 0.0005 WETH
+        let price = crate::price_calculator::PriceCalculator::calculate_price_with_decimals(
+            &pair.reserve0, &pair.reserve1, 0, 0,
+        ).unwrap();
+        assert_eq!(price, bigdecimal::BigDecimal::from_str("0.0005").unwrap());
+    }
+
+    #[test]
+    fn test_canonicalize_v3_pair() {
+        let mut pair = get_demo_pair();
+        std::mem::swap(&mut pair.token0, &mut pair.token1);
+        // sqrtPriceX96 = 2^96 * 2，即 token1/token0 = 4
+        pair.sqrt_price = Some("158456325028528675187087900672".to_string());
+        pair.tick = Some("13863".to_string());
+
+        assert!(pair.canonicalize());
+        // 交换后 sqrtPrice 为 2^96 / 2
+        assert_eq!(pair.sqrt_price.as_deref(), Some("39614081257132168796771975168"));
+        assert_eq!(pair.tick.as_deref(), Some("-13863"));
+        assert!(!pair.canonicalize());
+    }
+
+    #[test]
+    fn test_canonicalize_weighted_pair() {
+        let database = Database::new(None).unwrap();
+        let manager = PairManager::new(&database);
+
+        // 反向给出的 80/20 加权池：token0 为 WETH（权重 0.8），token1 为 USDC（权重 0.2）
+        let mut flipped = get_demo_pair();
+        flipped.dex_type = dex_types::BALANCER.to_string();
+        std::mem::swap(&mut flipped.token0, &mut flipped.token1);
+        flipped.weight0 = Some("0.8".to_string());
+        flipped.weight1 = Some("0.2".to_string());
+        flipped.balances = vec!["1000".to_string(), "1000000".to_string()];
+
+        manager.save_pairs(&[flipped]).unwrap();
+        let pair = manager.find_pair_by_id("0x123").unwrap().unwrap();
+
+        // 权重随代币一起交换，并在数据库中保留
+        assert_eq!(pair.token0.symbol, "USDC");
+        assert_eq!(pair.weight0.as_deref(), Some("0.2"));
+        assert_eq!(pair.weight1.as_deref(), Some("0.8"));
+
+        let mut flipped = get_demo_pair();
+        std::mem::swap(&mut flipped.token0, &mut flipped.token1);
+        flipped.balances = vec!["1000".to_string(), "1000000".to_string(), "5".to_string()];
+        assert!(flipped.canonicalize());
+        assert_eq!(flipped.balances, vec!["1000000", "1000", "5"]);
+    }
+
     fn get_demo_pair() -> PairData {
         PairData {
             id: "0x123".to_string(),
@@ -269,6 +365,12 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: None,
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         }
     }
 }
\ No newline at end of file
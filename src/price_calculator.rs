@@ -1,9 +1,18 @@
 use anyhow::Result;
-use bigdecimal::{BigDecimal, FromPrimitive, Zero};
+use bigdecimal::{BigDecimal, FromPrimitive, RoundingMode, Zero};
+use log::warn;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering as AtomicOrdering};
 use crate::thegraph::{PairData};
 use crate::types::{Price, TokenPair};
 use crate::config::{protocol_types, dex_types};
+use crate::utils::pow10;
+
+/// 价格计算中除法结果默认保留的小数位数
+pub const DEFAULT_PRICE_SCALE: i64 = 36;
+
+/// 当前生效的除法小数位数，由 `PriceCalculator::set_scale` 修改，对整个进程生效
+static PRICE_SCALE: AtomicI64 = AtomicI64::new(DEFAULT_PRICE_SCALE);
 
 /// 价格计算工具
 pub struct PriceCalculator;
@@ -14,6 +23,51 @@ impl PriceCalculator {
     
     /// Uniswap V3 tick 基数 (1.0001)
     const TICK_BASE: f64 = 1.0001;
+    
+    /// 估算 V3 活跃流动性时使用的价格区间（当前价格上下 2%）
+    const ACTIVE_LIQUIDITY_PRICE_BAND: f64 = 0.02;
+    
+    /// StableSwap 计算中保留的有效数字位数
+    const STABLESWAP_PRECISION: u64 = 50;
+    
+    /// 活跃流动性估算保留的有效数字位数，估算本身以 f64 计算，更多位数没有意义
+    const ACTIVE_LIQUIDITY_PRECISION: u64 = 15;
+    
+    /// 支持的最大代币小数位数（U256 最大值约为 1.16e77）
+    const MAX_DECIMALS: u32 = 77;
+    
+    /// Uniswap V3 TickMath 允许的 sqrtPriceX96 范围 [MIN_SQRT_RATIO, MAX_SQRT_RATIO)
+    const MIN_SQRT_RATIO: &'static str = "4295128739";
+    const MAX_SQRT_RATIO: &'static str = "1461446703485210103287273052203988822378723970342";
+    
+    /// 由 sqrtPriceX96 计算出的价格数量级上限，超出时截断到 [10^-30, 10^30]
+    const MAX_PRICE_EXPONENT: u32 = 30;
+
+    /// 设置价格除法保留的小数位数（向零截断），避免循环小数展开成上百位
+    pub fn set_scale(scale: u64) {
+        PRICE_SCALE.store(scale.min(i64::MAX as u64) as i64, AtomicOrdering::Relaxed);
+    }
+
+    /// 当前价格除法保留的小数位数
+    pub fn scale() -> i64 {
+        PRICE_SCALE.load(AtomicOrdering::Relaxed)
+    }
+
+    /// 按当前小数位数做除法
+    fn divide(numerator: &BigDecimal, denominator: &BigDecimal) -> BigDecimal {
+        Self::divide_with_scale(numerator, denominator, Self::scale())
+    }
+
+    /// 除法结果超过 `scale` 位小数时向零截断，位数更少的精确结果原样返回
+    pub fn divide_with_scale(numerator: &BigDecimal, denominator: &BigDecimal, scale: i64) -> BigDecimal {
+        let quotient = numerator / denominator;
+        if quotient.fractional_digit_count() > scale {
+            quotient.with_scale_round(scale, RoundingMode::Down)
+        } else {
+            quotient
+        }
+    }
+
     /// 从储备量计算token0/token1的价格
     /// 
     /// # 参数
@@ -37,11 +91,11 @@ impl PriceCalculator {
         }
         
         // 调整小数位数
-        let adjusted_reserve0 = Self::adjust_for_decimals(&reserve0_bd, token0_decimals);
-        let adjusted_reserve1 = Self::adjust_for_decimals(&reserve1_bd, token1_decimals);
+        let adjusted_reserve0 = Self::adjust_for_decimals(&reserve0_bd, token0_decimals)?;
+        let adjusted_reserve1 = Self::adjust_for_decimals(&reserve1_bd, token1_decimals)?;
         
         // 计算价格 (token1/token0)
-        let price = &adjusted_reserve1 / &adjusted_reserve0;
+        let price = Self::divide(&adjusted_reserve1, &adjusted_reserve0);
         
         Ok(price)
     }
@@ -51,23 +105,53 @@ impl PriceCalculator {
         format!("${:.6}", price)
     }
     
-    /// 调整BigDecimal的小数位数
-    fn adjust_for_decimals(value: &BigDecimal, decimals: u32) -> BigDecimal {
-        // 安全地计算 10^decimals，避免整数溢出
-        let divisor = if decimals <= 18 {
-            // 对于常见的小数位数，使用预计算的值
-            BigDecimal::from_u64(10_u64.pow(decimals))
-                .unwrap_or_else(|| BigDecimal::from(1))
-        } else {
-            // 对于极大的小数位数，使用BigDecimal的乘法
-            let ten = BigDecimal::from(10);
-            let mut result = BigDecimal::from(1);
-            for _ in 0..decimals {
-                result = result * &ten;
+    /// 调整BigDecimal的小数位数（除以 10^decimals），小数位数为 0 时原样返回
+    fn adjust_for_decimals(value: &BigDecimal, decimals: u32) -> Result<BigDecimal> {
+        Self::check_decimals(decimals)?;
+        if decimals == 0 {
+            return Ok(value.clone());
+        }
+        Ok(value / pow10(decimals))
+    }
+    
+    /// 拒绝不合理的小数位数，避免构造巨大的 10 的幂
+    fn check_decimals(decimals: u32) -> Result<()> {
+        if decimals > Self::MAX_DECIMALS {
+            return Err(anyhow::anyhow!(
+                "Token decimals {} exceed the supported maximum of {}",
+                decimals,
+                Self::MAX_DECIMALS
+            ));
+        }
+        Ok(())
+    }
+    
+    /// 按小数位差调整 token1/token0 的原始价格：乘以 10^(token0_decimals - token1_decimals)
+    fn apply_decimals_diff(price: BigDecimal, token0_decimals: u32, token1_decimals: u32) -> Result<BigDecimal> {
+        Self::check_decimals(token0_decimals)?;
+        Self::check_decimals(token1_decimals)?;
+        Ok(match token0_decimals.cmp(&token1_decimals) {
+            std::cmp::Ordering::Greater => price * pow10(token0_decimals - token1_decimals),
+            std::cmp::Ordering::Less => price / pow10(token1_decimals - token0_decimals),
+            std::cmp::Ordering::Equal => price,
+        })
+    }
+    
+    /// 交易对的手续费率（小数形式，0.003 表示 0.3%）
+    /// 
+    /// V3 池由 `fee_tier`（百万分之一为单位，如 500 = 0.05%）决定，其他协议按 DEX 类型取默认费率
+    pub fn pair_fee_percentage(pair: &PairData) -> f64 {
+        if pair.protocol_type == protocol_types::AMM_V3 {
+            if let Ok(fee_tier) = pair.fee_tier.parse::<u32>() {
+                return fee_tier as f64 / 1_000_000.0;
             }
-            result
-        };
-        value / divisor
+        }
+        
+        match pair.dex_type.as_str() {
+            dex_types::PANCAKESWAP => 0.0025,
+            dex_types::CURVE => 0.0004,
+            _ => 0.003,
+        }
     }
     
     /// 检查是否为有效的储备量数据
@@ -103,12 +187,21 @@ impl PriceCalculator {
             return Err(anyhow::anyhow!("sqrt_price is zero, cannot calculate price"));
         }
         
+        let min_sqrt_ratio = BigDecimal::from_str(Self::MIN_SQRT_RATIO)?;
+        let max_sqrt_ratio = BigDecimal::from_str(Self::MAX_SQRT_RATIO)?;
+        if sqrt_price_bd < min_sqrt_ratio || sqrt_price_bd >= max_sqrt_ratio {
+            return Err(anyhow::anyhow!(
+                "sqrt_price {} out of range [{}, {})",
+                sqrt_price_x96, Self::MIN_SQRT_RATIO, Self::MAX_SQRT_RATIO
+            ));
+        }
+        
         // sqrt_price 是 Q64.96 格式，需要除以 2^96
         let q96 = BigDecimal::from_str(Self::Q96)
             .map_err(|e| anyhow::anyhow!("Invalid Q96 constant: {}", e))?;
         
         // 计算实际的 sqrt_price
-        let sqrt_price_real = &sqrt_price_bd / &q96;
+        let sqrt_price_real = Self::divide(&sqrt_price_bd, &q96);
         
         // 价格 = sqrt_price^2
         let price_raw = &sqrt_price_real * &sqrt_price_real;
@@ -116,35 +209,23 @@ impl PriceCalculator {
         // 调整小数位数差异
         // 对于 WETH(18)/USDT(6) 对，价格应该是 USDT/WETH
         // 需要将价格乘以 10^(token0_decimals - token1_decimals) = 10^(18-6) = 10^12
-        let decimals_diff = token0_decimals as i32 - token1_decimals as i32;
-        let price = if decimals_diff != 0 {
-            let abs_diff = decimals_diff.abs() as u32;
-            
-            // 安全地计算 10^abs_diff，避免整数溢出
-            let adjustment = if abs_diff <= 18 {
-                // 对于常见的小数位数差异，使用预计算的值
-                BigDecimal::from_u64(10_u64.pow(abs_diff))
-                    .unwrap_or_else(|| BigDecimal::from(1))
-            } else {
-                // 对于极大的差异，使用BigDecimal的字符串构造
-                let ten = BigDecimal::from(10);
-                let mut result = BigDecimal::from(1);
-                for _ in 0..abs_diff {
-                    result = result * &ten;
-                }
-                result
-            };
-            
-            if decimals_diff > 0 {
-                price_raw * adjustment
-            } else {
-                price_raw / adjustment
-            }
+        let price = Self::apply_decimals_diff(price_raw, token0_decimals, token1_decimals)?;
+        Ok(Self::clamp_price_magnitude(price))
+    }
+    
+    /// 将价格截断到 [10^-MAX_PRICE_EXPONENT, 10^MAX_PRICE_EXPONENT]，截断时记录警告
+    fn clamp_price_magnitude(price: BigDecimal) -> BigDecimal {
+        let max_price = pow10(Self::MAX_PRICE_EXPONENT);
+        let min_price = BigDecimal::from(1) / &max_price;
+        if price > max_price {
+            warn!("价格 {} 超出上限，截断为 10^{}", price, Self::MAX_PRICE_EXPONENT);
+            max_price
+        } else if price < min_price {
+            warn!("价格 {} 低于下限，截断为 10^-{}", price, Self::MAX_PRICE_EXPONENT);
+            min_price
         } else {
-            price_raw
-        };
-        
-        Ok(price)
+            price
+        }
     }
     
     /// 从 Uniswap V3 的 tick 计算实际价格
@@ -172,35 +253,7 @@ impl PriceCalculator {
             .ok_or_else(|| anyhow::anyhow!("Failed to convert price to BigDecimal"))?;
         
         // 调整小数位数差异
-        let decimals_diff = token0_decimals as i32 - token1_decimals as i32;
-        let price = if decimals_diff != 0 {
-            let abs_diff = decimals_diff.abs() as u32;
-            
-            // 安全地计算 10^abs_diff，避免整数溢出
-            let adjustment = if abs_diff <= 18 {
-                // 对于常见的小数位数差异，使用预计算的值
-                BigDecimal::from_u64(10_u64.pow(abs_diff))
-                    .unwrap_or_else(|| BigDecimal::from(1))
-            } else {
-                // 对于极大的差异，使用BigDecimal的字符串构造
-                let ten = BigDecimal::from(10);
-                let mut result = BigDecimal::from(1);
-                for _ in 0..abs_diff {
-                    result = result * &ten;
-                }
-                result
-            };
-            
-            if decimals_diff > 0 {
-                price_bd * adjustment
-            } else {
-                price_bd / adjustment
-            }
-        } else {
-            price_bd
-        };
-        
-        Ok(price)
+        Self::apply_decimals_diff(price_bd, token0_decimals, token1_decimals)
     }
     
     /// 从 PairData 计算 V3 价格（优先使用 sqrt_price，fallback 到 tick）
@@ -233,6 +286,193 @@ impl PriceCalculator {
         Err(anyhow::anyhow!("No valid V3 price data (sqrt_price or tick) found"))
     }
     
+    /// 按 Curve StableSwap 不变量计算币种 i 以币种 j 计价的边际价格（即 get_dy 在小额交易下的极限）
+    /// 
+    /// # 参数
+    /// * `reserves` - 池中所有币种按精度换算后的余额
+    /// * `amp` - 放大系数，与合约 `A()` 的返回值一致（Ann = amp * n）
+    /// * `i` - 卖出币种的索引
+    /// * `j` - 买入币种的索引
+    /// 
+    /// # 返回
+    /// 每单位币种 i 可换得的币种 j 数量
+    pub fn calculate_stableswap_price(reserves: &[BigDecimal], amp: u64, i: usize, j: usize) -> Result<BigDecimal> {
+        let n = reserves.len();
+        if n < 2 {
+            return Err(anyhow::anyhow!("StableSwap pool needs at least 2 coins, got {}", n));
+        }
+        if i >= n || j >= n || i == j {
+            return Err(anyhow::anyhow!("Invalid coin indices i={}, j={} for {} coins", i, j, n));
+        }
+        if amp == 0 {
+            return Err(anyhow::anyhow!("Amplification coefficient must be positive"));
+        }
+        if reserves.iter().any(|x| x <= &BigDecimal::zero()) {
+            return Err(anyhow::anyhow!("StableSwap reserves must be positive"));
+        }
+        
+        let n_bd = BigDecimal::from(n as u64);
+        let ann = BigDecimal::from(amp) * &n_bd;
+        let d = Self::stableswap_invariant(reserves, &ann)?;
+        let d_p = Self::stableswap_d_p(reserves, &d);
+        
+        // 对不变量隐式求导: dy_j/dx_i = (Ann + D_P/x_i) / (Ann + D_P/x_j)，其中 D_P = D^(n+1) / (n^n * Πx)
+        let numerator = &ann + &d_p / &reserves[i];
+        let denominator = &ann + &d_p / &reserves[j];
+        Ok((numerator / denominator).with_prec(Self::STABLESWAP_PRECISION))
+    }
+    
+    /// D_P = D^(n+1) / (n^n * Πx)
+    fn stableswap_d_p(reserves: &[BigDecimal], d: &BigDecimal) -> BigDecimal {
+        let n_bd = BigDecimal::from(reserves.len() as u64);
+        reserves.iter().fold(d.clone(), |d_p, x| {
+            (d_p * d / (x * &n_bd)).with_prec(Self::STABLESWAP_PRECISION)
+        })
+    }
+    
+    /// 使用牛顿迭代求解 StableSwap 不变量 D（与 Curve 合约的 get_D 一致）
+    fn stableswap_invariant(reserves: &[BigDecimal], ann: &BigDecimal) -> Result<BigDecimal> {
+        let n_bd = BigDecimal::from(reserves.len() as u64);
+        let sum: BigDecimal = reserves.iter().sum();
+        let tolerance = BigDecimal::from_str("1e-30")?;
+        let one = BigDecimal::from(1);
+        
+        let mut d = sum.clone();
+        for _ in 0..255 {
+            let d_p = Self::stableswap_d_p(reserves, &d);
+            let prev = d.clone();
+            let numerator = (ann * &sum + &d_p * &n_bd) * &d;
+            let denominator = (ann - &one) * &d + (&n_bd + &one) * &d_p;
+            d = (numerator / denominator).with_prec(Self::STABLESWAP_PRECISION);
+            if (&d - &prev).abs() <= &tolerance * &d {
+                return Ok(d);
+            }
+        }
+        
+        Err(anyhow::anyhow!("StableSwap invariant did not converge"))
+    }
+    
+    /// 从 StableSwap 池的 PairData 计算 token1/token0 的价格
+    /// 
+    /// 优先使用 `balances`（已按精度换算），否则使用 reserve0/reserve1 视为两币种池
+    fn calculate_stableswap_pair_price(pair: &PairData) -> Result<BigDecimal> {
+        let amp = pair.amp_coefficient
+            .ok_or_else(|| anyhow::anyhow!("Missing amp coefficient for stable pool"))?;
+        
+        let reserves = if pair.balances.len() >= 2 {
+            pair.balances.iter()
+                .map(|balance| BigDecimal::from_str(balance).map_err(|e| anyhow::anyhow!("Invalid balance: {}", e)))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            let token0_decimals = pair.token0.decimals.parse::<u32>()
+                .map_err(|e| anyhow::anyhow!("Invalid token0 decimals: {}", e))?;
+            let token1_decimals = pair.token1.decimals.parse::<u32>()
+                .map_err(|e| anyhow::anyhow!("Invalid token1 decimals: {}", e))?;
+            let reserve0 = BigDecimal::from_str(&pair.reserve0)
+                .map_err(|e| anyhow::anyhow!("Invalid reserve0: {}", e))?;
+            let reserve1 = BigDecimal::from_str(&pair.reserve1)
+                .map_err(|e| anyhow::anyhow!("Invalid reserve1: {}", e))?;
+            vec![
+                Self::adjust_for_decimals(&reserve0, token0_decimals)?,
+                Self::adjust_for_decimals(&reserve1, token1_decimals)?,
+            ]
+        };
+        
+        Self::calculate_stableswap_price(&reserves, amp, 0, 1)
+    }
+    
+    /// 计算 Balancer 加权池的现货价格：(balance_in / weight_in) / (balance_out / weight_out)
+    /// 
+    /// # 返回
+    /// 每单位 out 代币对应的 in 代币数量（不含手续费）
+    pub fn calculate_weighted_price(
+        balance_in: &BigDecimal,
+        weight_in: &BigDecimal,
+        balance_out: &BigDecimal,
+        weight_out: &BigDecimal,
+    ) -> Result<BigDecimal> {
+        if weight_in.is_zero() || weight_out.is_zero() {
+            return Err(anyhow::anyhow!("Pool weights must be non-zero"));
+        }
+        if balance_out.is_zero() {
+            return Err(anyhow::anyhow!("Balance out is zero, cannot calculate price"));
+        }
+        
+        Ok(Self::divide(&Self::divide(balance_in, weight_in), &Self::divide(balance_out, weight_out)))
+    }
+    
+    /// 从 Balancer 加权池的 PairData 计算 token1/token0 的价格，缺少权重时按 50/50 处理
+    fn calculate_weighted_pair_price(pair: &PairData) -> Result<BigDecimal> {
+        let token0_decimals = pair.token0.decimals.parse::<u32>()
+            .map_err(|e| anyhow::anyhow!("Invalid token0 decimals: {}", e))?;
+        let token1_decimals = pair.token1.decimals.parse::<u32>()
+            .map_err(|e| anyhow::anyhow!("Invalid token1 decimals: {}", e))?;
+        let reserve0 = BigDecimal::from_str(&pair.reserve0)
+            .map_err(|e| anyhow::anyhow!("Invalid reserve0: {}", e))?;
+        let reserve1 = BigDecimal::from_str(&pair.reserve1)
+            .map_err(|e| anyhow::anyhow!("Invalid reserve1: {}", e))?;
+        
+        let parse_weight = |weight: &Option<String>| -> Result<BigDecimal> {
+            match weight {
+                Some(weight) => BigDecimal::from_str(weight).map_err(|e| anyhow::anyhow!("Invalid weight: {}", e)),
+                None => Ok(BigDecimal::from_str("0.5")?),
+            }
+        };
+        let weight0 = parse_weight(&pair.weight0)?;
+        let weight1 = parse_weight(&pair.weight1)?;
+        
+        // token1/token0 的价格即以 token1 买入 token0 时的现货价格
+        Self::calculate_weighted_price(
+            &Self::adjust_for_decimals(&reserve1, token1_decimals)?,
+            &weight1,
+            &Self::adjust_for_decimals(&reserve0, token0_decimals)?,
+            &weight0,
+        )
+    }
+    
+    /// 估算 V3 池在当前价格附近的活跃流动性价值（USD）
+    /// 
+    /// 近似方法：假设当前 tick 处的流动性 L 在价格上下 `ACTIVE_LIQUIDITY_PRICE_BAND` 范围内保持不变，
+    /// 按集中流动性公式计算该区间内的 token0/token1 数量，再用 TVL 的 USD/代币比例折算为 USD。
+    /// 区间内若有头寸边界，实际深度会偏离该估算；结果不会超过 TVL。
+    /// 该值只用于表格中的流动性显示，滑点估算和套利阈值判断不使用它。
+    /// 
+    /// # 返回
+    /// 缺少 tick、流动性或 TVL 数据时返回 None
+    pub fn v3_active_liquidity_usd(pair: &PairData) -> Option<BigDecimal> {
+        let tick = pair.tick.as_ref()?.parse::<i32>().ok()?;
+        let liquidity = pair.liquidity.as_ref()?.parse::<f64>().ok()?;
+        let token0_decimals = pair.token0.decimals.parse::<i32>().ok()?;
+        let token1_decimals = pair.token1.decimals.parse::<i32>().ok()?;
+        let tvl_usd = BigDecimal::from_str(&pair.reserve_usd).ok()?;
+        let tvl_token0 = pair.reserve0.parse::<f64>().ok()?;
+        let tvl_token1 = pair.reserve1.parse::<f64>().ok()?;
+        if liquidity <= 0.0 || tvl_usd <= BigDecimal::zero() {
+            return None;
+        }
+        
+        // 原始单位下的 sqrt(P)，P 为 token1/token0
+        let sqrt_price = Self::TICK_BASE.powi(tick).sqrt();
+        let band = Self::ACTIVE_LIQUIDITY_PRICE_BAND;
+        
+        // 价格上移到 P(1+band) 需要的 token0，价格下移到 P(1-band) 需要的 token1
+        let amount0 = liquidity / sqrt_price * (1.0 - 1.0 / (1.0 + band).sqrt());
+        let amount1 = liquidity * sqrt_price * (1.0 - (1.0 - band).sqrt());
+        let amount0 = amount0 / 10f64.powi(token0_decimals);
+        let amount1 = amount1 / 10f64.powi(token1_decimals);
+        
+        // 以 token1 计价，再按 TVL 折算 USD
+        let price = sqrt_price * sqrt_price * 10f64.powi(token0_decimals - token1_decimals);
+        let tvl_in_token1 = tvl_token0 * price + tvl_token1;
+        if !tvl_in_token1.is_finite() || tvl_in_token1 <= 0.0 {
+            return None;
+        }
+        let active_share = ((amount0 * price + amount1) / tvl_in_token1).min(1.0);
+        
+        let active_usd = BigDecimal::from_f64(active_share)? * tvl_usd;
+        Some(active_usd.with_prec(Self::ACTIVE_LIQUIDITY_PRECISION))
+    }
+    
     /// 从 PairData 自动计算价格（根据 protocol_type 选择 V2 或 V3 计算方式）
     /// 
     /// # 参数
@@ -245,6 +485,10 @@ impl PriceCalculator {
         if pair.protocol_type == protocol_types::AMM_V3 {
             // 使用 V3 计算方式
             Self::calculate_v3_price(pair)
+        } else if pair.protocol_type == protocol_types::STABLE_SWAP {
+            Self::calculate_stableswap_pair_price(pair)
+        } else if pair.protocol_type == protocol_types::WEIGHTED {
+            Self::calculate_weighted_pair_price(pair)
         } else {
             // 使用 V2 计算方式（默认）
             if Self::has_valid_reserves(pair) {
@@ -271,6 +515,22 @@ mod tests {
     use super::*;
     use crate::thegraph::TokenInfo;
     
+    #[test]
+    fn test_repeating_reciprocal_truncated_to_scale() {
+        let one = BigDecimal::from(1);
+        let third = PriceCalculator::divide_with_scale(&one, &BigDecimal::from(3), 8);
+        assert_eq!(third.to_string(), "0.33333333");
+        // 向零截断而不是四舍五入
+        let two_thirds = PriceCalculator::divide_with_scale(&BigDecimal::from(2), &BigDecimal::from(3), 8);
+        assert_eq!(two_thirds.to_string(), "0.66666666");
+
+        // 储备量价格使用默认小数位数，整除结果不受影响
+        let price = PriceCalculator::calculate_price_with_decimals("3", "1", 0, 0).unwrap();
+        assert_eq!(price.fractional_digit_count(), DEFAULT_PRICE_SCALE);
+        let price = PriceCalculator::calculate_price_with_decimals("1000", "2000000", 0, 0).unwrap();
+        assert_eq!(price, BigDecimal::from(2000));
+    }
+
     #[test]
     fn test_calculate_price_with_decimals() {
         // 测试独立的 calculate_price_with_decimals 方法
@@ -316,6 +576,12 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: None,
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         };
         
         let price = PriceCalculator::calculate_price_with_decimals(
@@ -362,6 +628,12 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: None,
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         };
         
         assert!(PriceCalculator::has_valid_reserves(&valid_pair));
@@ -389,6 +661,31 @@ mod tests {
         assert!(price > BigDecimal::from_str("0.9").unwrap() && price < BigDecimal::from_str("1.1").unwrap());
     }
 
+    #[test]
+    fn test_sqrt_price_range_validation() {
+        // 低于 MIN_SQRT_RATIO
+        let err = PriceCalculator::calculate_price_from_sqrt_price("4295128738", 18, 18).unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+        
+        // 等于或高于 MAX_SQRT_RATIO
+        assert!(PriceCalculator::calculate_price_from_sqrt_price(
+            "1461446703485210103287273052203988822378723970342", 18, 18
+        ).is_err());
+        assert!(PriceCalculator::calculate_price_from_sqrt_price(
+            "99999999999999999999999999999999999999999999999999999", 18, 18
+        ).is_err());
+        
+        // 区间内的值：2^96 × 2 对应价格 4
+        let price = PriceCalculator::calculate_price_from_sqrt_price("158456325028528675187087900672", 18, 18).unwrap();
+        assert_eq!(price, BigDecimal::from(4));
+        
+        // 边界附近的合法值配合极端小数位差时截断到 10^30
+        let price = PriceCalculator::calculate_price_from_sqrt_price(
+            "1461446703485210103287273052203988822378723970341", 77, 0
+        ).unwrap();
+        assert_eq!(price, pow10(30));
+    }
+
     #[test]
     fn test_calculate_price_from_tick() {
         // 测试从 tick 计算价格
@@ -430,6 +727,12 @@ mod tests {
             fee_tier: "3000".to_string(),
              sqrt_price: Some("79228162514264337593543950336".to_string()), // Q96, 对应价格1
              tick: Some("0".to_string()),
+             amp_coefficient: None,
+             balances: Vec::new(),
+             weight0: None,
+             weight1: None,
+             liquidity: None,
+             updated_at: None,
         };
 
         let result = PriceCalculator::calculate_v3_price(&pair);
@@ -467,6 +770,12 @@ mod tests {
             fee_tier: "3000".to_string(),
              sqrt_price: None,
              tick: Some("0".to_string()),
+             amp_coefficient: None,
+             balances: Vec::new(),
+             weight0: None,
+             weight1: None,
+             liquidity: None,
+             updated_at: None,
         };
 
         let result = PriceCalculator::calculate_v3_price(&pair);
@@ -504,6 +813,12 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: None,
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         };
 
         let result = PriceCalculator::calculate_v3_price(&pair);
@@ -537,6 +852,12 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: None,
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         };
         
         let result = PriceCalculator::calculate_price_from_pair(&pair);
@@ -572,10 +893,247 @@ mod tests {
             fee_tier: "3000".to_string(),
             sqrt_price: Some("79228162514264337593543950336".to_string()), // Q96
             tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
         };
         
         let result = PriceCalculator::calculate_price_from_pair(&pair);
         assert!(result.is_ok());
         // 只验证V3计算方式被正确调用，不验证具体数值
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_calculate_stableswap_price_3pool() {
+        let bd = |v: &str| BigDecimal::from_str(v).unwrap();
+        let tolerance = bd("0.000000000001");
+        
+        // 3pool (A = 2000) 余额完全平衡时价格为 1
+        let balanced = vec![bd("300000000"), bd("300000000"), bd("300000000")];
+        let price = PriceCalculator::calculate_stableswap_price(&balanced, 2000, 0, 1).unwrap();
+        assert!((price - bd("1")).abs() < tolerance);
+        
+        // 不平衡时偏离锚定很小，余额较多的币种价格略低于 1
+        let imbalanced = vec![bd("400000000"), bd("300000000"), bd("200000000")];
+        let price_01 = PriceCalculator::calculate_stableswap_price(&imbalanced, 2000, 0, 1).unwrap();
+        let price_02 = PriceCalculator::calculate_stableswap_price(&imbalanced, 2000, 0, 2).unwrap();
+        let price_20 = PriceCalculator::calculate_stableswap_price(&imbalanced, 2000, 2, 0).unwrap();
+        assert!((&price_01 - bd("0.999859465755481")).abs() < tolerance);
+        assert!((&price_02 - bd("0.999578515732391")).abs() < tolerance);
+        assert!((&price_02 * &price_20 - bd("1")).abs() < tolerance);
+        
+        assert!(PriceCalculator::calculate_stableswap_price(&imbalanced, 2000, 1, 1).is_err());
+        assert!(PriceCalculator::calculate_stableswap_price(&imbalanced, 0, 0, 1).is_err());
+    }
+    
+    #[test]
+    fn test_calculate_price_from_pair_stableswap() {
+        let pair = PairData {
+            id: "test".to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::CURVE.to_string(),
+            protocol_type: protocol_types::STABLE_SWAP.to_string(),
+            token0: TokenInfo {
+                id: "token0".to_string(),
+                symbol: "DAI".to_string(),
+                name: "Dai Stablecoin".to_string(),
+                decimals: "18".to_string(),
+            },
+            token1: TokenInfo {
+                id: "token1".to_string(),
+                symbol: "USDC".to_string(),
+                name: "USD Coin".to_string(),
+                decimals: "6".to_string(),
+            },
+            volume_usd: "500000".to_string(),
+            reserve_usd: "900000000".to_string(),
+            tx_count: "100".to_string(),
+            reserve0: "0".to_string(),
+            reserve1: "0".to_string(),
+            fee_tier: "100".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: Some(2000),
+            balances: vec!["400000000".to_string(), "300000000".to_string(), "200000000".to_string()],
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        };
+        
+        let price = PriceCalculator::calculate_price_from_pair(&pair).unwrap();
+        let naive = BigDecimal::from_str("0.75").unwrap();
+        assert!((price.clone() - BigDecimal::from_str("0.999859465755481").unwrap()).abs() < BigDecimal::from_str("0.000000000001").unwrap());
+        assert!(price > naive);
+        
+        // 缺少放大系数时无法计算
+        let pair = PairData { amp_coefficient: None, ..pair };
+        assert!(PriceCalculator::calculate_price_from_pair(&pair).is_err());
+    }
+    
+    #[test]
+    fn test_calculate_weighted_price_80_20() {
+        let bd = |v: &str| BigDecimal::from_str(v).unwrap();
+        let mut pair = PairData {
+            id: "test".to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::BALANCER.to_string(),
+            protocol_type: protocol_types::WEIGHTED.to_string(),
+            token0: TokenInfo {
+                id: "token0".to_string(),
+                symbol: "BAL".to_string(),
+                name: "Balancer".to_string(),
+                decimals: "18".to_string(),
+            },
+            token1: TokenInfo {
+                id: "token1".to_string(),
+                symbol: "WETH".to_string(),
+                name: "Wrapped Ether".to_string(),
+                decimals: "18".to_string(),
+            },
+            volume_usd: "500000".to_string(),
+            reserve_usd: "4000000".to_string(),
+            tx_count: "100".to_string(),
+            reserve0: "1000000000000000000000000".to_string(), // 1,000,000 BAL
+            reserve1: "1000000000000000000000".to_string(), // 1000 WETH
+            fee_tier: "3000".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: Some("0.8".to_string()),
+            weight1: Some("0.2".to_string()),
+            liquidity: None,
+            updated_at: None,
+        };
+        
+        // 80/20 池: (1000 / 0.2) / (1,000,000 / 0.8) = 0.004，而储备量比值只有 0.001
+        let price = PriceCalculator::calculate_price_from_pair(&pair).unwrap();
+        assert_eq!(price, bd("0.004"));
+        assert_ne!(price, bd("0.001"));
+        
+        // 缺少权重时按 50/50 处理，与储备量比值一致
+        pair.weight0 = None;
+        pair.weight1 = None;
+        assert_eq!(PriceCalculator::calculate_price_from_pair(&pair).unwrap(), bd("0.001"));
+        
+        assert!(PriceCalculator::calculate_weighted_price(&bd("1"), &bd("0"), &bd("1"), &bd("0.5")).is_err());
+    }
+    
+    #[test]
+    fn test_v3_active_liquidity_usd_below_tvl() {
+        // USDC/WETH 0.05% 池，tick 200000 约对应 2060 USDC/WETH
+        let mut pair = PairData {
+            id: "test".to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::UNISWAP_V3.to_string(),
+            protocol_type: protocol_types::AMM_V3.to_string(),
+            token0: TokenInfo {
+                id: "token0".to_string(),
+                symbol: "USDC".to_string(),
+                name: "USD Coin".to_string(),
+                decimals: "6".to_string(),
+            },
+            token1: TokenInfo {
+                id: "token1".to_string(),
+                symbol: "WETH".to_string(),
+                name: "Wrapped Ether".to_string(),
+                decimals: "18".to_string(),
+            },
+            volume_usd: "500000".to_string(),
+            reserve_usd: "200000000".to_string(),
+            tx_count: "100".to_string(),
+            reserve0: "100000000".to_string(),
+            reserve1: "48543.7".to_string(),
+            fee_tier: "500".to_string(),
+            sqrt_price: None,
+            tick: Some("200000".to_string()),
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: Some("20000000000000000000".to_string()),
+            updated_at: None,
+        };
+        
+        let tvl = BigDecimal::from_str(&pair.reserve_usd).unwrap();
+        let active = PriceCalculator::v3_active_liquidity_usd(&pair).unwrap();
+        assert!(active > BigDecimal::zero());
+        assert!(active < tvl);
+        
+        // 缺少流动性数据时无法估算
+        pair.liquidity = None;
+        assert!(PriceCalculator::v3_active_liquidity_usd(&pair).is_none());
+    }
+
+    #[test]
+    fn test_pair_fee_percentage_from_fee_tier() {
+        let mut pair = PairData {
+            id: "test".to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::UNISWAP_V3.to_string(),
+            protocol_type: protocol_types::AMM_V3.to_string(),
+            token0: TokenInfo {
+                id: "token0".to_string(),
+                symbol: "USDC".to_string(),
+                name: "USD Coin".to_string(),
+                decimals: "6".to_string(),
+            },
+            token1: TokenInfo {
+                id: "token1".to_string(),
+                symbol: "WETH".to_string(),
+                name: "Wrapped Ether".to_string(),
+                decimals: "18".to_string(),
+            },
+            volume_usd: "0".to_string(),
+            reserve_usd: "0".to_string(),
+            tx_count: "0".to_string(),
+            reserve0: "0".to_string(),
+            reserve1: "0".to_string(),
+            fee_tier: "500".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        };
+        
+        for (fee_tier, expected) in [("500", 0.0005), ("3000", 0.003), ("10000", 0.01)] {
+            pair.fee_tier = fee_tier.to_string();
+            assert_eq!(PriceCalculator::pair_fee_percentage(&pair), expected);
+        }
+        
+        // 非 V3 协议忽略 fee_tier，按 DEX 类型取默认费率
+        pair.protocol_type = protocol_types::AMM_V2.to_string();
+        pair.dex_type = dex_types::PANCAKESWAP.to_string();
+        assert_eq!(PriceCalculator::pair_fee_percentage(&pair), 0.0025);
+        pair.dex_type = dex_types::UNISWAP_V2.to_string();
+        assert_eq!(PriceCalculator::pair_fee_percentage(&pair), 0.003);
+    }
+
+    #[test]
+    fn test_adjust_for_extreme_decimals() {
+        let value = BigDecimal::from_str("123456789").unwrap();
+        
+        // 0 位小数原样返回
+        assert_eq!(PriceCalculator::adjust_for_decimals(&value, 0).unwrap(), value);
+        
+        // 24 位小数超出 u64 范围
+        let adjusted = PriceCalculator::adjust_for_decimals(&BigDecimal::from_str("5000000000000000000000000").unwrap(), 24).unwrap();
+        assert_eq!(adjusted, BigDecimal::from(5));
+        
+        // 不合理的小数位数直接报错
+        assert!(PriceCalculator::adjust_for_decimals(&value, 100).is_err());
+        
+        // tick 路径同样支持 0/24 位小数，并拒绝过大的小数位数
+        let price = PriceCalculator::calculate_price_from_tick("0", 24, 0).unwrap();
+        assert_eq!(price, BigDecimal::from_str("1000000000000000000000000").unwrap());
+        assert!(PriceCalculator::calculate_price_from_tick("0", 100, 18).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use ethers::providers::{Middleware, Provider, Ws};
+use log::{info, warn};
+use std::cmp::Ordering;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 未设置 WSS_URLS 时使用的默认端点
+const DEFAULT_WSS_URL: &str = "wss://mainnet.infura.io/ws/v3/";
+
+/// 当前端点连续出错达到该次数后切换到其他端点
+const FAILOVER_THRESHOLD: u32 = 3;
+
+/// 延迟滑动平均中新样本的权重
+const LATENCY_EWMA_WEIGHT: f64 = 0.2;
+
+/// 单个 WebSocket 端点的健康统计
+#[derive(Debug, Clone)]
+pub struct EndpointStats {
+    pub url: String,
+    pub successes: u64,
+    pub failures: u64,
+    /// 最近连续出错次数，成功一次即清零
+    pub consecutive_failures: u32,
+    /// 连接与请求延迟的滑动平均，尚无成功记录时为空
+    pub latency: Option<Duration>,
+}
+
+impl EndpointStats {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            successes: 0,
+            failures: 0,
+            consecutive_failures: 0,
+            latency: None,
+        }
+    }
+
+    /// 出错比例，没有记录时为 0
+    fn error_rate(&self) -> f64 {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            0.0
+        } else {
+            self.failures as f64 / total as f64
+        }
+    }
+}
+
+/// WebSocket 端点池：记录每个端点的延迟和出错比例，当前订阅连续出错时切换到最健康的其他端点
+#[derive(Debug)]
+pub struct ProviderPool {
+    endpoints: Vec<EndpointStats>,
+    active: usize,
+}
+
+impl ProviderPool {
+    /// 按给定顺序创建端点池，第一个端点为初始端点
+    pub fn new(urls: Vec<String>) -> Self {
+        let mut urls: Vec<String> = urls.into_iter().filter(|url| !url.is_empty()).collect();
+        if urls.is_empty() {
+            urls.push(DEFAULT_WSS_URL.to_string());
+        }
+        Self {
+            endpoints: urls.into_iter().map(EndpointStats::new).collect(),
+            active: 0,
+        }
+    }
+
+    /// 从逗号分隔的 WSS_URLS 环境变量创建端点池
+    pub fn from_env() -> Self {
+        match env::var("WSS_URLS") {
+            Ok(urls) => Self::new(urls.split(',').map(|url| url.trim().to_string()).collect()),
+            Err(_) => {
+                warn!("未找到环境变量 WSS_URLS，使用默认WebSocket端点");
+                Self::new(Vec::new())
+            }
+        }
+    }
+
+    /// 当前使用的端点
+    pub fn active_endpoint(&self) -> &str {
+        &self.endpoints[self.active].url
+    }
+
+    /// 记录一次成功请求及其延迟
+    pub fn record_success(&mut self, index: usize, latency: Duration) {
+        let endpoint = &mut self.endpoints[index];
+        endpoint.successes += 1;
+        endpoint.consecutive_failures = 0;
+        endpoint.latency = Some(match endpoint.latency {
+            Some(average) => average.mul_f64(1.0 - LATENCY_EWMA_WEIGHT) + latency.mul_f64(LATENCY_EWMA_WEIGHT),
+            None => latency,
+        });
+    }
+
+    /// 记录当前端点的一次成功请求
+    pub fn record_active_success(&mut self, latency: Duration) {
+        self.record_success(self.active, latency);
+    }
+
+    /// 记录一次失败请求
+    pub fn record_failure(&mut self, index: usize) {
+        let endpoint = &mut self.endpoints[index];
+        endpoint.failures += 1;
+        endpoint.consecutive_failures += 1;
+    }
+
+    /// 记录当前订阅出错；连续出错达到阈值且存在其他端点时切换到最健康的端点，返回是否发生切换
+    pub fn report_active_error(&mut self) -> bool {
+        self.record_failure(self.active);
+        if self.endpoints[self.active].consecutive_failures < FAILOVER_THRESHOLD {
+            return false;
+        }
+        match self.next_healthiest() {
+            Some(next) => {
+                warn!("WebSocket 端点 {} 连续出错，切换到 {}", self.active_endpoint(), self.endpoints[next].url);
+                // 切换后重新累计，避免回切时立即再次切走
+                self.endpoints[self.active].consecutive_failures = 0;
+                self.active = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 除当前端点外最健康的端点，健康程度相同时按当前端点之后的顺序轮换
+    fn next_healthiest(&self) -> Option<usize> {
+        let count = self.endpoints.len();
+        (1..count)
+            .map(|offset| (self.active + offset) % count)
+            .min_by(|&a, &b| self.compare_health(a, b))
+    }
+
+    /// 出错比例低者更健康，其次延迟低者，没有延迟记录的排在后面
+    fn compare_health(&self, a: usize, b: usize) -> Ordering {
+        let (a, b) = (&self.endpoints[a], &self.endpoints[b]);
+        a.error_rate()
+            .total_cmp(&b.error_rate())
+            .then_with(|| a.latency.unwrap_or(Duration::MAX).cmp(&b.latency.unwrap_or(Duration::MAX)))
+    }
+
+    /// 连接时尝试的端点顺序：当前端点优先，其余按健康程度排序
+    fn candidates(&self) -> Vec<(usize, String)> {
+        let mut order = vec![self.active];
+        let mut others: Vec<usize> = (0..self.endpoints.len()).filter(|&index| index != self.active).collect();
+        others.sort_by(|&a, &b| self.compare_health(a, b));
+        order.extend(others);
+        order.into_iter().map(|index| (index, self.endpoints[index].url.clone())).collect()
+    }
+
+    /// 按健康程度依次连接端点，第一个能返回区块高度的端点成为当前端点
+    pub async fn connect(pool: &Mutex<ProviderPool>) -> Option<Arc<Provider<Ws>>> {
+        let candidates = pool.lock().unwrap().candidates();
+        for (index, url) in candidates {
+            let started = Instant::now();
+            match Self::try_connect(&url).await {
+                Ok(provider) => {
+                    let mut pool = pool.lock().unwrap();
+                    pool.record_success(index, started.elapsed());
+                    pool.active = index;
+                    info!("成功连接到以太坊WebSocket节点: {}", url);
+                    return Some(Arc::new(provider));
+                }
+                Err(e) => {
+                    pool.lock().unwrap().record_failure(index);
+                    info!("WebSocket连接失败 {}: {}", url, e);
+                }
+            }
+        }
+
+        warn!("无法连接到任何以太坊WebSocket节点");
+        None
+    }
+
+    async fn try_connect(url: &str) -> Result<Provider<Ws>> {
+        let provider = Provider::<Ws>::connect(url).await?;
+        provider.get_block_number().await?;
+        Ok(provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_on_primary_failures() {
+        let urls = ["wss://primary", "wss://slow", "wss://fast"];
+        let mut pool = ProviderPool::new(urls.iter().map(|url| url.to_string()).collect());
+        pool.record_success(0, Duration::from_millis(50));
+        pool.record_success(1, Duration::from_millis(400));
+        pool.record_success(2, Duration::from_millis(80));
+
+        // 未达到阈值前保持主端点，中间一次成功会清零连续出错计数
+        assert!(!pool.report_active_error());
+        pool.record_success(0, Duration::from_millis(50));
+        assert!(!pool.report_active_error());
+        assert!(!pool.report_active_error());
+        assert_eq!(pool.active_endpoint(), "wss://primary");
+
+        // 连续第三次出错后切换到延迟最低的健康端点
+        assert!(pool.report_active_error());
+        assert_eq!(pool.active_endpoint(), "wss://fast");
+
+        // 新端点也持续出错时，优先选择出错比例更低的慢端点而不是刚出错的主端点
+        for _ in 0..FAILOVER_THRESHOLD - 1 {
+            assert!(!pool.report_active_error());
+        }
+        assert!(pool.report_active_error());
+        assert_eq!(pool.active_endpoint(), "wss://slow");
+        assert_eq!(pool.candidates()[0].1, "wss://slow");
+    }
+
+    #[test]
+    fn test_single_endpoint_never_rotates() {
+        let mut pool = ProviderPool::new(vec!["wss://only".to_string(), String::new()]);
+        assert_eq!(pool.endpoints.len(), 1);
+        for _ in 0..FAILOVER_THRESHOLD * 2 {
+            assert!(!pool.report_active_error());
+        }
+        assert_eq!(pool.active_endpoint(), "wss://only");
+        assert_eq!(ProviderPool::new(Vec::new()).active_endpoint(), DEFAULT_WSS_URL);
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
+use std::future::Future;
 use tokio::sync::mpsc;
 
 use crate::config::{dex_types, Config};
@@ -7,11 +8,15 @@ use crate::database::Database;
 use crate::event_listener::EventListener;
 use crate::log_adapter::LogAdapter;
 use crate::table_display::{DisplayMessage, TableDisplay, PairDisplay, PairDisplayConverter};
-use crate::thegraph::PairData;
+use crate::thegraph::{PairData, TheGraphClient};
 
 pub struct RealTimeMonitor {
     config: Config,
     database: Database,
+    /// 启动时加入监听并保存的交易对地址（--watch）
+    watch: Vec<String>,
+    /// 启动时从监听列表中移除的合约地址（--unwatch）
+    unwatch: Vec<String>,
 }
 
 impl RealTimeMonitor {
@@ -19,8 +24,16 @@ impl RealTimeMonitor {
         Ok(Self {
             config,
             database,
+            watch: Vec::new(),
+            unwatch: Vec::new(),
         })
     }
+
+    /// 设置启动时要加入和移除的监听合约，变更会保存到数据库，下次启动时继续生效
+    pub fn set_watch_changes(&mut self, watch: Vec<String>, unwatch: Vec<String>) {
+        self.watch = watch;
+        self.unwatch = unwatch;
+    }
     
     pub async fn start_monitoring(self, count: usize) -> Result<()> {
         println!("启动模块化实时监控系统...");
@@ -29,21 +42,27 @@ impl RealTimeMonitor {
         LogAdapter::switch_to_table();
         info!("已切换到表格日志模式");
         
+        // 冷启动：初始数据准备完成之前不创建消息通道，也不开始监听事件
+        println!("正在获取初始交易对数据...");
+        let graph_client = TheGraphClient::new();
+        let pairs_per_token = self.config.monitoring.cold_start.pairs_per_token;
+        let initial_pairs = self.cold_start(count, |token| {
+            let graph_client = &graph_client;
+            async move { graph_client.get_v3_pools_by_token(&token, pairs_per_token).await }
+        }).await?;
+        println!("获取到 {} 个初始交易对", initial_pairs.len());
+        
         // 创建消息通道
         let (sender, receiver) = mpsc::channel::<DisplayMessage>(100);
         println!("消息通道创建完成");
         
-        // 准备初始数据
-        println!("正在获取初始交易对数据...");
-        let pair_manager = crate::pairs::PairManager::new(&self.database);
-        let initial_pairs = pair_manager.load_pairs_by_value(None, Some(dex_types::UNISWAP_V3), Some(count.min(100)))?;
-        println!("获取到 {} 个初始交易对", initial_pairs.len());
         let initial_data = self.convert_pairs_to_display(&initial_pairs)?;
         println!("初始数据转换完成");
         
         // 创建表格显示模块
         println!("正在创建表格显示模块...");
         let mut table_display = TableDisplay::new(receiver, initial_data)?;
+        table_display.set_dedup_updates(self.config.monitoring.dedup_display_updates);
         println!("表格显示模块创建完成");
         
         // 创建事件监听模块，传递初始交易对数据
@@ -54,7 +73,21 @@ impl RealTimeMonitor {
             count,
             initial_pairs,
         ).await;
-        println!("事件监听模块创建完成");
+        event_listener.set_max_concurrent_logs(self.config.monitoring.max_concurrent_logs);
+        event_listener.set_max_reconnect_attempts(self.config.monitoring.max_ws_reconnect_attempts);
+        for address in &self.watch {
+            if let Err(e) = event_listener.watch_pair(address) {
+                warn!("添加监听合约 {} 失败: {}", address, e);
+            }
+        }
+        for address in &self.unwatch {
+            match event_listener.unwatch_address(address) {
+                Ok(true) => {}
+                Ok(false) => warn!("合约 {} 不在监听列表中", address),
+                Err(e) => warn!("移除监听合约 {} 失败: {}", address, e),
+            }
+        }
+        println!("事件监听模块创建完成，WebSocket 节点: {}", event_listener.active_endpoint());
         
         // 启动两个模块
         println!("正在启动表格显示模块...");
@@ -65,7 +98,13 @@ impl RealTimeMonitor {
         });
         
         println!("正在启动事件监听模块...");
+        let backfill_from_block = self.config.monitoring.backfill_from_block;
         let listener_handle = tokio::spawn(async move {
+            if let Some(from_block) = backfill_from_block {
+                if let Err(e) = event_listener.backfill_since(from_block).await {
+                    println!("历史事件回填失败: {}", e);
+                }
+            }
             if let Err(e) = event_listener.start_listening().await {
                 println!("事件监听模块错误: {}", e);
             }
@@ -90,10 +129,139 @@ impl RealTimeMonitor {
         Ok(())
     }
 
+    /// 冷启动：数据库中的交易对不足时先通过 `fetch` 按监控 token 刷新，再返回初始交易对
+    async fn cold_start<F, Fut>(&self, count: usize, fetch: F) -> Result<Vec<PairData>>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Vec<PairData>>>,
+    {
+        let pair_manager = crate::pairs::PairManager::new(&self.database);
+        let limit = Some(count.min(100));
+        let initial_pairs = pair_manager.load_pairs_by_value(None, Some(dex_types::UNISWAP_V3), limit)?;
+
+        let cold_start = &self.config.monitoring.cold_start;
+        if !cold_start.enabled || initial_pairs.len() >= cold_start.min_pairs {
+            return Ok(initial_pairs);
+        }
+
+        let tokens = &self.config.arbitrage.tokens_to_monitor;
+        info!("冷启动: 数据库中仅有 {} 个交易对 (最少 {})，开始从 TheGraph 刷新 {} 个 token",
+              initial_pairs.len(), cold_start.min_pairs, tokens.len());
+
+        let mut total_saved = 0;
+        for (index, token) in tokens.iter().enumerate() {
+            match fetch(token.clone()).await {
+                Ok(pairs) if !pairs.is_empty() => {
+                    pair_manager.save_pairs(&pairs)?;
+                    total_saved += pairs.len();
+                    info!("冷启动: [{}/{}] token {} 刷新 {} 个交易对", index + 1, tokens.len(), token, pairs.len());
+                }
+                Ok(_) => {
+                    info!("冷启动: [{}/{}] token {} 没有可用的交易对", index + 1, tokens.len(), token);
+                }
+                Err(e) => {
+                    warn!("冷启动: [{}/{}] token {} 刷新失败: {}", index + 1, tokens.len(), token, e);
+                }
+            }
+        }
+
+        info!("冷启动完成: 共刷新 {} 个交易对", total_saved);
+        pair_manager.load_pairs_by_value(None, Some(dex_types::UNISWAP_V3), limit)
+    }
 
     /// 将 PairData 转换为 PairDisplay
     fn convert_pairs_to_display(&self, pairs: &[PairData]) -> Result<Vec<PairDisplay>> {
         // 使用统一的转换工具
         PairDisplayConverter::convert_list(pairs)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::protocol_types;
+    use crate::thegraph::TokenInfo;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn make_pool(id: &str) -> PairData {
+        let token = |id: &str, symbol: &str| TokenInfo {
+            id: id.to_string(),
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals: "18".to_string(),
+        };
+        PairData {
+            id: id.to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::UNISWAP_V3.to_string(),
+            protocol_type: protocol_types::AMM_V3.to_string(),
+            token0: token("0xaaa", "AAA"),
+            token1: token("0xbbb", "BBB"),
+            volume_usd: "1000".to_string(),
+            reserve_usd: "5000".to_string(),
+            tx_count: "10".to_string(),
+            reserve0: "100".to_string(),
+            reserve1: "200".to_string(),
+            fee_tier: "3000".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        }
+    }
+
+    async fn test_monitor(enabled: bool) -> RealTimeMonitor {
+        let mut config = Config::load().unwrap();
+        config.monitoring.cold_start.enabled = enabled;
+        config.monitoring.cold_start.min_pairs = 2;
+        config.arbitrage.tokens_to_monitor = vec!["0xaaa".to_string(), "0xbbb".to_string()];
+        RealTimeMonitor::new(config, Database::new(None).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cold_start_refreshes_before_monitoring() {
+        let monitor = test_monitor(true).await;
+        let calls = AtomicUsize::new(0);
+
+        let pairs = monitor.cold_start(100, |token| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if token == "0xaaa" {
+                    Ok(vec![make_pool("0x1"), make_pool("0x2")])
+                } else {
+                    Err(anyhow::anyhow!("subgraph unavailable"))
+                }
+            }
+        }).await.unwrap();
+
+        // 刷新失败的 token 不影响其它 token，返回的初始数据已包含刷新结果
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(pairs.len(), 2);
+
+        // 数据充足时不再刷新
+        let pairs = monitor.cold_start(100, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok(vec![]) }
+        }).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(pairs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cold_start_disabled() {
+        let monitor = test_monitor(false).await;
+        let calls = AtomicUsize::new(0);
+
+        let pairs = monitor.cold_start(100, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok(vec![make_pool("0x1")]) }
+        }).await.unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(pairs.is_empty());
+    }
+}
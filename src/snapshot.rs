@@ -0,0 +1,128 @@
+use anyhow::Result;
+use chrono::Utc;
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::SnapshotConfig;
+use crate::types::ArbitrageOpportunity;
+
+/// 快照文件名前缀
+const SNAPSHOT_PREFIX: &str = "opportunities_";
+
+/// 套利机会快照写入器 - 每次扫描写入一个带时间戳的 JSON 文件，并按数量和总大小轮转
+pub struct SnapshotWriter {
+    config: SnapshotConfig,
+}
+
+impl SnapshotWriter {
+    /// 创建快照写入器，确保输出目录存在
+    pub fn new(config: SnapshotConfig) -> Result<Self> {
+        fs::create_dir_all(&config.directory)?;
+        Ok(Self { config })
+    }
+
+    /// 写入一次扫描的套利机会快照，返回文件路径
+    pub fn write(&self, opportunities: &[ArbitrageOpportunity]) -> Result<PathBuf> {
+        let dir = Path::new(&self.config.directory);
+        let stamp = Utc::now().format("%Y%m%d_%H%M%S_%6f");
+
+        // 同一微秒内多次写入时追加序号，保证文件名唯一且按名称有序
+        let mut path = dir.join(format!("{}{}.json", SNAPSHOT_PREFIX, stamp));
+        let mut seq = 1;
+        while path.exists() {
+            path = dir.join(format!("{}{}_{:03}.json", SNAPSHOT_PREFIX, stamp, seq));
+            seq += 1;
+        }
+
+        fs::write(&path, serde_json::to_vec_pretty(opportunities)?)?;
+        info!("已写入 {} 个套利机会快照: {}", opportunities.len(), path.display());
+
+        self.prune()?;
+        Ok(path)
+    }
+
+    /// 按时间顺序列出所有快照文件
+    pub fn list_snapshots(&self) -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.config.directory)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(".json"))
+                    .unwrap_or(false)
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// 删除最旧的快照，直到文件数和总大小都不超过限制（至少保留最新的一个）
+    fn prune(&self) -> Result<()> {
+        let files = self.list_snapshots()?;
+        let sizes: Vec<u64> = files
+            .iter()
+            .map(|path| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+            .collect();
+
+        let mut count = files.len();
+        let mut total: u64 = sizes.iter().sum();
+
+        for (path, size) in files.iter().zip(sizes) {
+            let over_count = self.config.max_files > 0 && count > self.config.max_files;
+            let over_size = self.config.max_total_bytes > 0 && total > self.config.max_total_bytes;
+            if count <= 1 || !(over_count || over_size) {
+                break;
+            }
+
+            if let Err(e) = fs::remove_file(path) {
+                warn!("删除旧快照 {} 失败: {}", path.display(), e);
+                continue;
+            }
+            count -= 1;
+            total -= size;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer(dir: &Path, max_files: usize, max_total_bytes: u64) -> SnapshotWriter {
+        SnapshotWriter::new(SnapshotConfig {
+            directory: dir.to_string_lossy().to_string(),
+            max_files,
+            max_total_bytes,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_rotation_by_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = writer(dir.path(), 2, 0);
+
+        let first = writer.write(&[]).unwrap();
+        let second = writer.write(&[]).unwrap();
+        assert_eq!(writer.list_snapshots().unwrap(), vec![first.clone(), second.clone()]);
+
+        // 超出保留数量时删除最旧的文件
+        let third = writer.write(&[]).unwrap();
+        assert_eq!(writer.list_snapshots().unwrap(), vec![second, third]);
+        assert!(!first.exists());
+    }
+
+    #[test]
+    fn test_snapshot_rotation_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        // 空列表序列化为 "[]"，两个文件即超过 3 字节
+        let writer = writer(dir.path(), 0, 3);
+
+        writer.write(&[]).unwrap();
+        let latest = writer.write(&[]).unwrap();
+        assert_eq!(writer.list_snapshots().unwrap(), vec![latest]);
+    }
+}
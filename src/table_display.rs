@@ -12,15 +12,20 @@ use ratatui::{
     Frame, Terminal,
 };
 use tui_logger::{TuiLoggerWidget, TuiLoggerLevelOutput};
+use std::fs;
 use std::io::{self, Stdout};
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use log::{info};
 use chrono;
+use bigdecimal::ToPrimitive;
+use serde::Serialize;
+use crate::config::protocol_types;
 use crate::price_calculator::PriceCalculator;
 use crate::thegraph::PairData;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct PairDisplay {
     pub rank: usize,
     pub pair: String,
@@ -28,6 +33,123 @@ pub struct PairDisplay {
     pub price: String,
     pub liquidity: String,
     pub last_update: String,
+    /// 数据超过 `STALE_AFTER_SECS` 未更新，表格中以暗色显示
+    pub stale: bool,
+}
+
+/// 交易对数据超过该秒数未更新即视为过期
+const STALE_AFTER_SECS: i64 = 300;
+
+/// CSV 导出文件所在目录
+const CSV_EXPORT_DIR: &str = "data/exports";
+
+/// 导出结果等状态提示的显示时长
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+impl PairDisplay {
+    /// CSV 表头，列顺序与 `to_csv_row` 一致
+    pub const CSV_HEADER: &'static str = "rank,pair,dex,price,liquidity,last_update";
+
+    /// 转换为一行 CSV，包含逗号、引号或换行的字段按 RFC 4180 加引号转义
+    pub fn to_csv_row(&self) -> String {
+        [
+            self.rank.to_string(),
+            self.pair.clone(),
+            self.dex.clone(),
+            self.price.clone(),
+            self.liquidity.clone(),
+            self.last_update.clone(),
+        ]
+        .iter()
+        .map(|field| escape_csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 表格排序列，`Rank` 表示按原始顺序显示
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Rank,
+    Pair,
+    Dex,
+    Price,
+    Liquidity,
+}
+
+impl SortColumn {
+    /// 循环切换到下一个排序列
+    fn next(self) -> Self {
+        match self {
+            SortColumn::Rank => SortColumn::Pair,
+            SortColumn::Pair => SortColumn::Dex,
+            SortColumn::Dex => SortColumn::Price,
+            SortColumn::Price => SortColumn::Liquidity,
+            SortColumn::Liquidity => SortColumn::Rank,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortColumn::Rank => "排名",
+            SortColumn::Pair => "交易对",
+            SortColumn::Dex => "DEX",
+            SortColumn::Price => "价格",
+            SortColumn::Liquidity => "流动性",
+        }
+    }
+}
+
+/// 解析 "$1,234" / "$0.000012" 这类显示用金额字符串
+fn parse_display_number(value: &str) -> Option<f64> {
+    value.trim().trim_start_matches('$').replace(',', "").parse::<f64>().ok()
+}
+
+/// 按数值比较金额字符串，无法解析的值无论升降序都排在最后
+fn compare_display_numbers(a: &str, b: &str, ascending: bool) -> std::cmp::Ordering {
+    match (parse_display_number(a), parse_display_number(b)) {
+        (Some(a), Some(b)) => {
+            let ordering = a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal);
+            if ascending { ordering } else { ordering.reverse() }
+        }
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+/// 按指定列排序，价格与流动性按数值而非字典序比较
+fn sort_pairs(pairs: &mut [PairDisplay], column: SortColumn, ascending: bool) {
+    pairs.sort_by(|a, b| {
+        let ordering = match column {
+            SortColumn::Rank => a.rank.cmp(&b.rank),
+            SortColumn::Pair => a.pair.cmp(&b.pair),
+            SortColumn::Dex => a.dex.cmp(&b.dex),
+            SortColumn::Price => return compare_display_numbers(&a.price, &b.price, ascending),
+            SortColumn::Liquidity => return compare_display_numbers(&a.liquidity, &b.liquidity, ascending),
+        };
+        if ascending { ordering } else { ordering.reverse() }
+    });
+}
+
+/// 比较显示内容时忽略 `last_update`，时间戳变化本身不需要重绘
+impl PartialEq for PairDisplay {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
+            && self.pair == other.pair
+            && self.dex == other.dex
+            && self.price == other.price
+            && self.liquidity == other.liquidity
+            && self.stale == other.stale
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -53,16 +175,49 @@ impl PairDisplayConverter {
             Err(_) => "$0.000000".to_string(),
         };
         
+        // 没有观测时间（如刚从 TheGraph 获取）时视为刚更新
+        let observed_at = pair.updated_at.unwrap_or_else(chrono::Utc::now);
+        let (last_update, stale) = Self::format_last_update(observed_at, chrono::Utc::now());
+        
         PairDisplay {
             rank,
             pair: format!("{}/{}", pair.token0.symbol, pair.token1.symbol),
-            dex: pair.dex_type.clone(),
+            dex: Self::format_dex(pair),
             price,
-            liquidity: format!("${:.0}", pair.reserve_usd.parse::<f64>().unwrap_or(0.0)),
-            last_update: chrono::Utc::now().format("%H:%M:%S").to_string(),
+            liquidity: Self::format_liquidity(pair),
+            last_update,
+            stale,
+        }
+    }
+    
+    /// 格式化最后更新时间，过期数据附带距今时长（如 `09:50:00 (10m 前)`），返回 (文本, 是否过期)
+    fn format_last_update(observed_at: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>) -> (String, bool) {
+        let time = observed_at.format("%H:%M:%S");
+        let age = now - observed_at;
+        if age.num_seconds() > STALE_AFTER_SECS {
+            (format!("{} ({}m 前)", time, age.num_minutes()), true)
+        } else {
+            (time.to_string(), false)
         }
     }
     
+    /// 格式化 DEX 名称：V3 池附带费率档位（如 `UNI_V3 0.05%`），以区分同一代币对的不同池子
+    fn format_dex(pair: &PairData) -> String {
+        if pair.protocol_type == protocol_types::AMM_V3 {
+            format!("{} {:.2}%", pair.dex_type, PriceCalculator::pair_fee_percentage(pair) * 100.0)
+        } else {
+            pair.dex_type.clone()
+        }
+    }
+    
+    /// 格式化流动性：V3 池优先显示当前价格附近的活跃流动性，否则显示 TVL
+    fn format_liquidity(pair: &PairData) -> String {
+        let liquidity_usd = PriceCalculator::v3_active_liquidity_usd(pair)
+            .and_then(|active| active.to_f64())
+            .unwrap_or_else(|| pair.reserve_usd.parse::<f64>().unwrap_or(0.0));
+        format!("${:.0}", liquidity_usd)
+    }
+    
     /// 将PairData列表转换为PairDisplay列表
     pub fn convert_list(pairs: &[PairData]) -> Result<Vec<PairDisplay>> {
         let display_pairs: Vec<PairDisplay> = pairs
@@ -95,10 +250,11 @@ impl PairDisplayConverter {
         PairDisplay {
             rank,
             pair: format!("{}/{}", pair.token0.symbol, pair.token1.symbol),
-            dex: pair.dex_type.clone(),
+            dex: Self::format_dex(pair),
             price,
-            liquidity: format!("${:.0}", pair.reserve_usd.parse::<f64>().unwrap_or(0.0)),
+            liquidity: Self::format_liquidity(pair),
             last_update: chrono::Utc::now().format("%H:%M:%S").to_string(),
+            stale: false,
         }
     }
 }
@@ -112,6 +268,12 @@ pub struct TableDisplay {
     scroll_offset: usize,
     visible_rows: usize,
     all_pairs: Vec<PairDisplay>,
+    dedup_updates: bool,
+    status_message: Option<(String, Instant)>,
+    sort_column: SortColumn,
+    sort_ascending: bool,
+    export_tx: mpsc::UnboundedSender<Result<PathBuf>>,
+    export_rx: mpsc::UnboundedReceiver<Result<PathBuf>>,
 }
 
 impl TableDisplay {
@@ -122,6 +284,7 @@ impl TableDisplay {
         info!("📊 接收到 {} 个初始交易对数据", initial_data.len());
         
         let all_pairs = initial_data.clone();
+        let (export_tx, export_rx) = mpsc::unbounded_channel();
         
         Ok(Self {
             terminal,
@@ -132,9 +295,32 @@ impl TableDisplay {
             scroll_offset: 0,
             visible_rows: 10,
             all_pairs,
+            dedup_updates: true,
+            status_message: None,
+            sort_column: SortColumn::Rank,
+            sort_ascending: true,
+            export_tx,
+            export_rx,
         })
     }
     
+    /// 设置是否跳过与当前显示内容相同的局部更新
+    pub fn set_dedup_updates(&mut self, dedup_updates: bool) {
+        self.dedup_updates = dedup_updates;
+    }
+    
+    /// 应用局部更新，返回是否需要重绘
+    fn apply_partial_update(pairs: &mut [PairDisplay], index: usize, data: PairDisplay, dedup: bool) -> bool {
+        match pairs.get_mut(index) {
+            Some(current) if dedup && *current == data => false,
+            Some(current) => {
+                *current = data;
+                true
+            }
+            None => false,
+        }
+    }
+    
 
     
     pub async fn start_display(&mut self) -> Result<()> {
@@ -146,14 +332,7 @@ impl TableDisplay {
         let mut current_pairs = self.initial_data.clone();
         
         // 初始渲染
-        let visible_pairs = self.get_visible_pairs(&current_pairs);
-        self.terminal.draw(|f| {
-            if self.show_logs {
-                Self::render_ui_with_logs(f, &current_pairs, &mut self.tui_logger_state);
-            } else {
-                Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows);
-            }
-        })?;
+        self.redraw(&current_pairs)?;
         
         info!("🚀 TableDisplay 已启动，显示 {} 个初始交易对", current_pairs.len());
         println!("🚀 TableDisplay 已启动，显示 {} 个初始交易对", current_pairs.len());
@@ -164,48 +343,44 @@ impl TableDisplay {
                     match message {
                         Some(DisplayMessage::FullUpdate(pairs)) => {
                             current_pairs = pairs;
-                            let visible_pairs = self.get_visible_pairs(&current_pairs);
-                            let _ = self.terminal.draw(|f| {
-                                if self.show_logs {
-                                     Self::render_ui_with_logs(f, &current_pairs, &mut self.tui_logger_state);
-                                 } else {
-                                     Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows);
-                                 }
-                            });
+                            let _ = self.redraw(&current_pairs);
                         }
                         Some(DisplayMessage::PartialUpdate { index, data }) => {
-                            if index < current_pairs.len() {
-                                current_pairs[index] = data;
-                                let visible_pairs = self.get_visible_pairs(&current_pairs);
-                                let _ = self.terminal.draw(|f| {
-                                    if self.show_logs {
-                                         Self::render_ui_with_logs(f, &current_pairs, &mut self.tui_logger_state);
-                                     } else {
-                                         Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows);
-                                     }
-                                });
+                            if Self::apply_partial_update(&mut current_pairs, index, data, self.dedup_updates) {
+                                let _ = self.redraw(&current_pairs);
                             }
                         }
                         Some(DisplayMessage::BatchPartialUpdate(updates)) => {
+                            let mut changed = false;
                             for (index, data) in updates {
-                                if index < current_pairs.len() {
-                                    current_pairs[index] = data;
-                                }
+                                changed |= Self::apply_partial_update(&mut current_pairs, index, data, self.dedup_updates);
+                            }
+                            if changed {
+                                let _ = self.redraw(&current_pairs);
                             }
-                            let visible_pairs = self.get_visible_pairs(&current_pairs);
-                            let _ = self.terminal.draw(|f| {
-                                if self.show_logs {
-                                     Self::render_ui_with_logs(f, &current_pairs, &mut self.tui_logger_state);
-                                 } else {
-                                     Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows);
-                                 }
-                            });
                         }
                         Some(DisplayMessage::Shutdown) => break,
                         None => break,
                     }
                 }
+                Some(result) = self.export_rx.recv() => {
+                    let message = match result {
+                        Ok(path) => {
+                            info!("交易对表格已导出到 {}", path.display());
+                            format!("已导出 CSV: {}", path.display())
+                        }
+                        Err(e) => format!("导出 CSV 失败: {}", e),
+                    };
+                    self.status_message = Some((message, Instant::now()));
+                    let _ = self.redraw(&current_pairs);
+                }
                 _ = tokio::time::sleep(Duration::from_millis(100)) => {
+                    // 状态提示显示一段时间后自动清除
+                    if matches!(&self.status_message, Some((_, shown_at)) if shown_at.elapsed() >= STATUS_MESSAGE_DURATION) {
+                        self.status_message = None;
+                        let _ = self.redraw(&current_pairs);
+                    }
+                    
                     if event::poll(Duration::from_millis(0))? {
                         if let Event::Key(key) = event::read()? {
                             match key.code {
@@ -214,26 +389,27 @@ impl TableDisplay {
                                 }
                                 KeyCode::Char('l') => {
                                     self.show_logs = !self.show_logs;
-                                    let visible_pairs = self.get_visible_pairs(&current_pairs);
-                                    let scroll_offset = self.scroll_offset;
-                                    let visible_rows = self.visible_rows;
-                                    let _ = self.terminal.draw(|f| {
-                                        if self.show_logs {
-                                             Self::render_ui_with_logs(f, &current_pairs, &mut self.tui_logger_state);
-                                         } else {
-                                             Self::render_ui_static(f, &visible_pairs, scroll_offset, current_pairs.len(), visible_rows);
-                                         }
-                                    });
+                                    let _ = self.redraw(&current_pairs);
+                                }
+                                KeyCode::Char('s') => {
+                                    self.export_csv(&current_pairs);
+                                }
+                                KeyCode::Char('o') => {
+                                    self.sort_column = self.sort_column.next();
+                                    self.show_sort_status();
+                                    let _ = self.redraw(&current_pairs);
+                                }
+                                KeyCode::Char('r') => {
+                                    self.sort_ascending = !self.sort_ascending;
+                                    self.show_sort_status();
+                                    let _ = self.redraw(&current_pairs);
                                 }
                                 KeyCode::Up => {
                                     if !self.show_logs {
                                         // 只在表格模式下，向上滚动
                                         if self.scroll_offset > 0 {
                                             self.scroll_offset -= 1;
-                                            let visible_pairs = self.get_visible_pairs(&current_pairs);
-                                            let _ = self.terminal.draw(|f| {
-                                                Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows);
-                                            });
+                                            let _ = self.redraw(&current_pairs);
                                         }
                                     }
                                     // 在日志模式下，忽略方向键，不进行滚动
@@ -248,10 +424,7 @@ impl TableDisplay {
                                         };
                                         if self.scroll_offset < max_offset {
                                             self.scroll_offset += 1;
-                                            let visible_pairs = self.get_visible_pairs(&current_pairs);
-                                            let _ = self.terminal.draw(|f| {
-                                                Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows);
-                                            });
+                                            let _ = self.redraw(&current_pairs);
                                         }
                                     }
                                     // 在日志模式下，忽略方向键，不进行滚动
@@ -275,6 +448,57 @@ impl TableDisplay {
         Ok(())
     }
 
+    fn show_sort_status(&mut self) {
+        let direction = if self.sort_ascending { "升序" } else { "降序" };
+        let message = format!("排序: {} {}", self.sort_column.label(), direction);
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// 按当前显示模式重绘界面。排序只作用于显示副本，
+    /// `current_pairs` 保持原始顺序，局部更新的索引仍然有效
+    fn redraw(&mut self, current_pairs: &[PairDisplay]) -> Result<()> {
+        let mut sorted_pairs = current_pairs.to_vec();
+        sort_pairs(&mut sorted_pairs, self.sort_column, self.sort_ascending);
+        let current_pairs = &sorted_pairs;
+        let visible_pairs = self.get_visible_pairs(current_pairs);
+        let status = self.status_message.as_ref().map(|(message, _)| message.as_str());
+        self.terminal.draw(|f| {
+            if self.show_logs {
+                Self::render_ui_with_logs(f, current_pairs, &mut self.tui_logger_state, status);
+            } else {
+                Self::render_ui_static(f, &visible_pairs, self.scroll_offset, current_pairs.len(), self.visible_rows, status);
+            }
+        })?;
+        Ok(())
+    }
+
+    /// 在后台线程中将当前表格导出为带时间戳的 CSV 文件，完成后通过 `export_rx` 通知界面
+    fn export_csv(&self, pairs: &[PairDisplay]) {
+        let pairs = pairs.to_vec();
+        let path = Path::new(CSV_EXPORT_DIR)
+            .join(format!("pairs_{}.csv", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+        let export_tx = self.export_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = Self::write_csv(&path, &pairs).map(|_| path);
+            let _ = export_tx.send(result);
+        });
+    }
+
+    /// 写入 CSV 文件（含表头）
+    fn write_csv(path: &Path, pairs: &[PairDisplay]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut content = String::from(PairDisplay::CSV_HEADER);
+        content.push('\n');
+        for pair in pairs {
+            content.push_str(&pair.to_csv_row());
+            content.push('\n');
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
     fn get_visible_pairs(&self, pairs: &[PairDisplay]) -> Vec<PairDisplay> {
         let start = self.scroll_offset;
         let end = std::cmp::min(start + self.visible_rows, pairs.len());
@@ -293,7 +517,7 @@ impl TableDisplay {
         }
     }
 
-    fn render_ui_with_logs(f: &mut Frame, pairs: &[PairDisplay], tui_logger_state: &mut tui_logger::TuiWidgetState) {
+    fn render_ui_with_logs(f: &mut Frame, pairs: &[PairDisplay], tui_logger_state: &mut tui_logger::TuiWidgetState, status: Option<&str>) {
         // Split screen: table on top, logs on bottom with better proportions
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -301,7 +525,7 @@ impl TableDisplay {
             .margin(0)
             .split(f.area());
         
-        Self::render_table_area(f, chunks[0], pairs, true);
+        Self::render_table_area(f, chunks[0], pairs, true, status);
         Self::render_log_area(f, chunks[1], tui_logger_state);
     }
 
@@ -327,7 +551,7 @@ impl TableDisplay {
         f.render_widget(tui_logger_widget, area);
     }
 
-    fn render_table_area(f: &mut Frame, area: Rect, pairs: &[PairDisplay], show_logs: bool) {
+    fn render_table_area(f: &mut Frame, area: Rect, pairs: &[PairDisplay], show_logs: bool, status: Option<&str>) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
@@ -360,7 +584,7 @@ impl TableDisplay {
                     Cell::from(pair.liquidity.clone()),
                     Cell::from(pair.last_update.clone()),
                 ];
-                Row::new(cells).height(1).bottom_margin(1)
+                Row::new(cells).height(1).bottom_margin(1).style(Self::row_style(pair))
             });
             
             let table = Table::new(rows, [
@@ -369,7 +593,7 @@ impl TableDisplay {
                 Constraint::Length(8),  // DEX
                 Constraint::Length(12), // 价格
                 Constraint::Length(10), // 流动性
-                Constraint::Length(12), // 最后更新
+                Constraint::Length(18), // 最后更新
             ])
             .header(header)
             .block(Block::default().borders(Borders::ALL).title("交易对数据"))
@@ -386,9 +610,13 @@ impl TableDisplay {
         
         // 渲染提示信息
         let help_text = if show_logs {
-            "按 Ctrl+C 退出 | 按 'l' 隐藏日志 | 方向键/鼠标滚轮滚动日志 | 'h'隐藏/显示级别 | '+'增加级别 | '-'减少级别"
+            "按 Ctrl+C 退出 | 按 'l' 隐藏日志 | 按 's' 导出 CSV | 'o'切换排序列 | 'r'切换升降序 | 方向键/鼠标滚轮滚动日志 | 'h'隐藏/显示级别 | '+'增加级别 | '-'减少级别"
         } else {
-            "按 Ctrl+C 退出 | 按 'l' 显示日志"
+            "按 Ctrl+C 退出 | 按 'l' 显示日志 | 按 's' 导出 CSV | 'o'切换排序列 | 'r'切换升降序"
+        };
+        let help_text = match status {
+            Some(status) => format!("{} | {}", status, help_text),
+            None => help_text.to_string(),
         };
         
         let help = Paragraph::new(help_text)
@@ -397,7 +625,16 @@ impl TableDisplay {
         f.render_widget(help, chunks[2]);
     }
 
-    fn render_ui_static(f: &mut Frame, pairs: &[PairDisplay], scroll_offset: usize, total_pairs: usize, visible_rows: usize) {
+    /// 过期的交易对以暗色显示
+    fn row_style(pair: &PairDisplay) -> Style {
+        if pair.stale {
+            Style::default().add_modifier(Modifier::DIM)
+        } else {
+            Style::default()
+        }
+    }
+
+    fn render_ui_static(f: &mut Frame, pairs: &[PairDisplay], scroll_offset: usize, total_pairs: usize, visible_rows: usize, status: Option<&str>) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
@@ -430,7 +667,7 @@ impl TableDisplay {
                     Cell::from(pair.liquidity.clone()),
                     Cell::from(pair.last_update.clone()),
                 ];
-                Row::new(cells).height(1)
+                Row::new(cells).height(1).style(Self::row_style(pair))
             });
             
             let table = Table::new(rows, &[
@@ -441,7 +678,7 @@ impl TableDisplay {
                 Constraint::Length(10), // 流动性
                 Constraint::Length(15), // Reserve0
                 Constraint::Length(15), // Reserve1
-                Constraint::Length(12), // 最后更新
+                Constraint::Length(18), // 最后更新
             ])
             .header(header)
             .block(Block::default().borders(Borders::ALL).title("交易对数据"));
@@ -462,9 +699,112 @@ impl TableDisplay {
         } else {
             format!("按 Ctrl+C 退出监控 | 显示 {}/{} | 数据实时更新中...", total_pairs, total_pairs)
         };
+        let scroll_info = match status {
+            Some(status) => format!("{} | {}", status, scroll_info),
+            None => scroll_info,
+        };
         let help = Paragraph::new(scroll_info)
             .style(Style::default().fg(Color::Yellow))
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(help, chunks[2]);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_display(price: &str, last_update: &str) -> PairDisplay {
+        PairDisplay {
+            rank: 1,
+            pair: "WETH/USDC".to_string(),
+            dex: "UNI_V3".to_string(),
+            price: price.to_string(),
+            liquidity: "$5000".to_string(),
+            last_update: last_update.to_string(),
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn test_apply_partial_update_dedup() {
+        let mut pairs = vec![make_display("2000.00", "10:00:00")];
+
+        // 内容相同（仅时间戳不同）时不触发更新
+        assert!(!TableDisplay::apply_partial_update(&mut pairs, 0, make_display("2000.00", "10:00:05"), true));
+        assert_eq!(pairs[0].last_update, "10:00:00");
+
+        // 内容变化时更新
+        assert!(TableDisplay::apply_partial_update(&mut pairs, 0, make_display("2001.00", "10:00:06"), true));
+        assert_eq!(pairs[0].price, "2001.00");
+
+        // 关闭去重后相同内容也会更新
+        assert!(TableDisplay::apply_partial_update(&mut pairs, 0, make_display("2001.00", "10:00:07"), false));
+        assert_eq!(pairs[0].last_update, "10:00:07");
+
+        // 越界索引忽略
+        assert!(!TableDisplay::apply_partial_update(&mut pairs, 5, make_display("1.00", "10:00:08"), true));
+    }
+
+    #[test]
+    fn test_to_csv_row() {
+        let mut pair = make_display("$2,000.50", "10:00:00");
+        pair.liquidity = "$1,234".to_string();
+        assert_eq!(pair.to_csv_row(), "1,WETH/USDC,UNI_V3,\"$2,000.50\",\"$1,234\",10:00:00");
+
+        pair.pair = "say \"hi\"".to_string();
+        pair.price = "$1.00".to_string();
+        assert_eq!(pair.to_csv_row(), "1,\"say \"\"hi\"\"\",UNI_V3,$1.00,\"$1,234\",10:00:00");
+        assert_eq!(PairDisplay::CSV_HEADER.split(',').count(), 6);
+    }
+
+    #[test]
+    fn test_sort_pairs_numeric() {
+        let mut pairs = vec![
+            make_display("$1,234", "10:00:00"),
+            make_display("$0.000012", "10:00:00"),
+            make_display("$0.000000", "10:00:00"),
+            make_display("$99.5", "10:00:00"),
+            make_display("N/A", "10:00:00"),
+        ];
+        for (index, pair) in pairs.iter_mut().enumerate() {
+            pair.rank = index + 1;
+        }
+
+        assert_eq!(parse_display_number("$1,234"), Some(1234.0));
+        assert_eq!(parse_display_number("$0.000012"), Some(0.000012));
+
+        sort_pairs(&mut pairs, SortColumn::Price, true);
+        let prices: Vec<&str> = pairs.iter().map(|p| p.price.as_str()).collect();
+        assert_eq!(prices, vec!["$0.000000", "$0.000012", "$99.5", "$1,234", "N/A"]);
+
+        // 降序时无法解析的值仍排在最后
+        sort_pairs(&mut pairs, SortColumn::Price, false);
+        assert_eq!(pairs[0].price, "$1,234");
+        assert_eq!(pairs[4].price, "N/A");
+
+        // 按排名恢复原始顺序
+        sort_pairs(&mut pairs, SortColumn::Rank, true);
+        assert_eq!(pairs.iter().map(|p| p.rank).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_stale_pairs_marked() {
+        let mut pair: PairData = serde_json::from_value(serde_json::json!({
+            "id": "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc",
+            "token0": { "id": "0xa", "symbol": "USDC", "name": "USD Coin", "decimals": "6" },
+            "token1": { "id": "0xb", "symbol": "WETH", "name": "Wrapped Ether", "decimals": "18" },
+            "volumeUSD": "0", "reserveUSD": "1000000", "txCount": "1",
+            "reserve0": "2000000", "reserve1": "1000",
+        }))
+        .unwrap();
+
+        pair.updated_at = Some(chrono::Utc::now() - chrono::Duration::minutes(10));
+        let display = PairDisplayConverter::convert_single(&pair, 1);
+        assert!(display.stale);
+        assert!(display.last_update.ends_with("(10m 前)"));
+
+        pair.updated_at = Some(chrono::Utc::now());
+        assert!(!PairDisplayConverter::convert_single(&pair, 1).stale);
+    }
+}
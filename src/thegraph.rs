@@ -1,9 +1,12 @@
 use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashSet;
 use std::env;
+use std::time::Duration;
 use crate::types::{TokenPair, Token};
 use crate::config::{protocol_types, dex_types};
+use crate::token::DEFAULT_CHAIN;
 
 // Manual GraphQL query structure for Uniswap V2 pairs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +34,62 @@ pub struct PairData {
     pub fee_tier: String,
     pub sqrt_price: Option<String>,
     pub tick: Option<String>,
+    /// StableSwap 池的放大系数（即合约 `A()` 的返回值）
+    #[serde(default)]
+    pub amp_coefficient: Option<u64>,
+    /// StableSwap 池中所有币种按精度换算后的余额，索引 0/1 对应 token0/token1
+    #[serde(default)]
+    pub balances: Vec<String>,
+    /// Balancer 加权池中 token0/token1 的归一化权重，缺省时按 50/50 处理
+    #[serde(default)]
+    pub weight0: Option<String>,
+    #[serde(default)]
+    pub weight1: Option<String>,
+    /// V3 池当前 tick 处的活跃流动性 L
+    #[serde(default)]
+    pub liquidity: Option<String>,
+    /// 最近一次观测到该交易对数据的时间（数据库的 updated_at 或最近一次事件），未知时为空
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl PairData {
+    /// token0 是否为地址较小的代币（AMM 合约中的规范顺序）
+    pub fn is_canonical(&self) -> bool {
+        self.token0.id.to_lowercase() <= self.token1.id.to_lowercase()
+    }
+
+    /// 将交易对转换为规范顺序：交换 token0/token1 及对应的储备量、加权池权重与 StableSwap 余额，
+    /// V3 的 sqrtPrice 取倒数、tick 取相反数。返回是否发生了交换
+    pub fn canonicalize(&mut self) -> bool {
+        if self.is_canonical() {
+            return false;
+        }
+
+        std::mem::swap(&mut self.token0, &mut self.token1);
+        std::mem::swap(&mut self.reserve0, &mut self.reserve1);
+        std::mem::swap(&mut self.weight0, &mut self.weight1);
+        if self.balances.len() >= 2 {
+            self.balances.swap(0, 1);
+        }
+
+        // sqrtPriceX96 表示 sqrt(token1/token0) * 2^96，交换后为 2^192 / sqrtPriceX96
+        self.sqrt_price = self.sqrt_price.take().and_then(|sqrt_price| {
+            match ethers::types::U256::from_dec_str(&sqrt_price) {
+                Ok(value) if !value.is_zero() => {
+                    Some(((ethers::types::U256::one() << 192) / value).to_string())
+                }
+                Ok(_) => Some(sqrt_price),
+                Err(_) => None,
+            }
+        });
+        self.tick = self.tick.take().map(|tick| match tick.parse::<i64>() {
+            Ok(value) => (-value).to_string(),
+            Err(_) => tick,
+        });
+
+        true
+    }
 }
 
 fn default_network() -> String {
@@ -100,6 +159,8 @@ pub struct PoolData {
     #[serde(rename = "sqrtPrice")]
     pub sqrt_price: Option<String>,
     pub tick: Option<String>,
+    #[serde(default)]
+    pub liquidity: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -113,12 +174,68 @@ struct GraphQLRequest {
     variables: serde_json::Value,
 }
 
+/// Built-in stablecoin symbols, overridable via `STABLECOIN_SYMBOLS`
+const DEFAULT_STABLECOINS: &[&str] = &[
+    "USDT", "USDC", "DAI", "BUSD", "TUSD", "USDP", "FRAX", "LUSD", "MIM", "USDD", "GUSD",
+    "SUSD", "USDN", "USTC", "HUSD", "DUSD", "OUSD", "USDK", "USDS", "USDX", "USDR", "USDB",
+    "USDE", "USDF", "USDH", "USDJ", "USDL", "USDM", "USDO", "USDQ", "USDV", "USDW",
+    "USDY", "USDZ", "VAI", "VUSD", "YUSD", "ZUSD", "CUSD", "EURC", "EURS", "EURT", "JEUR",
+    "AGEUR", "CEUR", "EUROC",
+];
+
+/// Default Uniswap V2 subgraph on Ethereum, overridable via `UNISWAP_V2_SUBGRAPH_ID`
+const DEFAULT_UNISWAP_V2_SUBGRAPH_ID: &str = "A3Np3RQbaBA6oKJgiwDJeo5T3zrYfGHPWFYayMwtNDum";
+
+/// Default Uniswap V3 subgraph on Ethereum, overridable via `UNISWAP_V3_SUBGRAPH_ID`
+const DEFAULT_UNISWAP_V3_SUBGRAPH_ID: &str = "5zvR82QoaXYFyDEKLZ9t6v9adgnptxYpKpSbxtgVENFV";
+
+/// Uniswap V3 subgraphs on other chains, keyed by CoinGecko platform ID.
+/// Each entry is overridable via `UNISWAP_V3_SUBGRAPH_ID_<CHAIN>`, e.g. `UNISWAP_V3_SUBGRAPH_ID_POLYGON_POS`
+const UNISWAP_V3_SUBGRAPHS: &[(&str, &str)] = &[
+    ("polygon-pos", "3hCPRGf4z88VC5rsBKU5AA9FBBq5nF3jbKJG7VZCbhjm"),
+    ("arbitrum-one", "FbCGRftH4a3yZugY7TnbYgPJVEv2LvMT6oF1fxPe9aJM"),
+    ("optimistic-ethereum", "Cghf4LfVqPiFw6fp6Y5X5Ubc8UpmUhSfJL82zwiBFLaj"),
+    ("base", "43Hwfi3dJSoGpyas9VwNoDAv55yjgGrPpNSmbQZArzMG"),
+    ("binance-smart-chain", "F85MNzUGYqgSHSHRGgeVMNsdnW1KtZSVgFULumXRZTw2"),
+];
+
+/// How fetched pairs are filtered by stablecoin membership
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Drop every pair that contains a stablecoin on either side (default)
+    #[default]
+    DropBothStable,
+    /// Drop only stable/stable pairs, keeping stable-vs-volatile pairs such as WETH/USDC
+    DropIfBothStable,
+    /// Keep all pairs
+    NoFilter,
+}
+
+impl FilterMode {
+    /// Parse the `STABLECOIN_FILTER_MODE` value: `drop_both_stable`, `drop_if_both_stable` or `none`
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "drop_both_stable" => Some(Self::DropBothStable),
+            "drop_if_both_stable" => Some(Self::DropIfBothStable),
+            "none" => Some(Self::NoFilter),
+            _ => None,
+        }
+    }
+}
+
 pub struct TheGraphClient {
     client: reqwest::Client,
     api_key: Option<String>,
     base_url: String,
-    uniswap_v2_subgraph_id: String,
+    /// CoinGecko platform ID of the chain the subgraphs index, stored as `PairData::network`
+    network: String,
+    /// `None` when no Uniswap V2 subgraph is known for `network`
+    uniswap_v2_subgraph_id: Option<String>,
     uniswap_v3_subgraph_id: String,
+    max_retries: u32,
+    backoff_ms: u64,
+    stablecoins: HashSet<String>,
+    filter_mode: FilterMode,
 }
 
 // Convert V3 Pool to V2 PairData format for compatibility
@@ -139,26 +256,146 @@ impl From<PoolData> for PairData {
             fee_tier: pool.fee_tier,
             sqrt_price: pool.sqrt_price,
             tick: pool.tick,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: pool.liquidity,
+            updated_at: None,
         }
     }
 }
 
 impl TheGraphClient {
+    /// Create an Ethereum client from the environment, including `STABLECOIN_SYMBOLS` and `STABLECOIN_FILTER_MODE`
     pub fn new() -> Self {
+        let stablecoins = match env::var("STABLECOIN_SYMBOLS") {
+            Ok(symbols) => Self::parse_stablecoin_symbols(&symbols),
+            Err(_) => DEFAULT_STABLECOINS.iter().map(|s| s.to_string()).collect(),
+        };
+        let filter_mode = env::var("STABLECOIN_FILTER_MODE").ok().and_then(|v| FilterMode::parse(&v)).unwrap_or_default();
+
+        Self::with_stablecoins(stablecoins).with_filter_mode(filter_mode)
+    }
+
+    /// Create a client querying the subgraphs of `chain` (a CoinGecko platform ID such as `polygon-pos`).
+    /// Fails when no Uniswap V3 subgraph is known or configured for the chain
+    pub fn for_chain(chain: &str) -> Result<Self> {
+        if chain == DEFAULT_CHAIN {
+            return Ok(Self::new());
+        }
+
+        let env_suffix = chain.to_uppercase().replace('-', "_");
+        let uniswap_v3_subgraph_id = env::var(format!("UNISWAP_V3_SUBGRAPH_ID_{}", env_suffix))
+            .ok()
+            .or_else(|| {
+                UNISWAP_V3_SUBGRAPHS
+                    .iter()
+                    .find(|(network, _)| *network == chain)
+                    .map(|(_, id)| id.to_string())
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "No Uniswap V3 subgraph known for chain {}; set UNISWAP_V3_SUBGRAPH_ID_{}",
+                    chain,
+                    env_suffix
+                )
+            })?;
+
+        Ok(Self {
+            network: chain.to_string(),
+            uniswap_v2_subgraph_id: env::var(format!("UNISWAP_V2_SUBGRAPH_ID_{}", env_suffix)).ok(),
+            uniswap_v3_subgraph_id,
+            ..Self::new()
+        })
+    }
+
+    /// CoinGecko platform ID of the chain this client queries
+    pub fn network(&self) -> &str {
+        &self.network
+    }
+
+    /// Create a client with a custom stablecoin symbol set (matched case-insensitively)
+    pub fn with_stablecoins(set: HashSet<String>) -> Self {
         let api_key = env::var("THEGRAPH_API_KEY").ok();
         let base_url = env::var("THEGRAPH_BASE_URL").unwrap_or_else(|_| "https://gateway.thegraph.com/api".to_string());
-        let uniswap_v2_subgraph_id = env::var("UNISWAP_V2_SUBGRAPH_ID").unwrap_or_else(|_| "A3Np3RQbaBA6oKJgiwDJeo5T3zrYfGHPWFYayMwtNDum".to_string());
-        let uniswap_v3_subgraph_id = env::var("UNISWAP_V3_SUBGRAPH_ID").unwrap_or_else(|_| "5zvR82QoaXYFyDEKLZ9t6v9adgnptxYpKpSbxtgVENFV".to_string());
+        let uniswap_v2_subgraph_id = env::var("UNISWAP_V2_SUBGRAPH_ID").unwrap_or_else(|_| DEFAULT_UNISWAP_V2_SUBGRAPH_ID.to_string());
+        let uniswap_v3_subgraph_id = env::var("UNISWAP_V3_SUBGRAPH_ID").unwrap_or_else(|_| DEFAULT_UNISWAP_V3_SUBGRAPH_ID.to_string());
+        let max_retries = env::var("THEGRAPH_MAX_RETRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(3);
+        let backoff_ms = env::var("THEGRAPH_BACKOFF_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(500);
 
         Self {
             client: reqwest::Client::new(),
             api_key,
             base_url,
-            uniswap_v2_subgraph_id,
+            network: DEFAULT_CHAIN.to_string(),
+            uniswap_v2_subgraph_id: Some(uniswap_v2_subgraph_id),
             uniswap_v3_subgraph_id,
+            max_retries,
+            backoff_ms,
+            stablecoins: set.into_iter().map(|s| s.to_uppercase()).collect(),
+            filter_mode: FilterMode::default(),
         }
     }
 
+    /// Set how stablecoin pairs are filtered
+    pub fn with_filter_mode(mut self, filter_mode: FilterMode) -> Self {
+        self.filter_mode = filter_mode;
+        self
+    }
+
+    /// Parse a comma-separated symbol list such as `USDT,USDC,DAI`
+    fn parse_stablecoin_symbols(symbols: &str) -> HashSet<String> {
+        symbols
+            .split(',')
+            .map(|s| s.trim().to_uppercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Send a GraphQL request, retrying network errors and HTTP 429/502/503 with exponential backoff
+    async fn post_graphql<T: DeserializeOwned>(&self, url: &str, request: &GraphQLRequest) -> Result<T> {
+        let mut attempt: u32 = 0;
+        loop {
+            let mut request_builder = self.client.post(url).json(request);
+
+            // Add Bearer token if available
+            if let Some(ref api_key) = self.api_key {
+                request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
+            }
+
+            let (error, retry_after) = match request_builder.send().await {
+                Ok(response) if Self::is_retryable_status(response.status()) => {
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    (anyhow!("TheGraph returned HTTP {}", response.status()), retry_after)
+                }
+                Ok(response) => return Ok(response.json::<T>().await?),
+                Err(e) => (anyhow!(e), None),
+            };
+
+            if attempt >= self.max_retries {
+                return Err(error);
+            }
+
+            let delay = retry_after.unwrap_or_else(|| {
+                Duration::from_millis(self.backoff_ms.saturating_mul(1u64 << attempt.min(16)))
+            });
+            attempt += 1;
+            log::warn!("TheGraph 请求失败 ({}), {}ms 后进行第 {}/{} 次重试",
+                       error, delay.as_millis(), attempt, self.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+        matches!(status.as_u16(), 429 | 502 | 503)
+    }
+
     /// Fetch V3 pools by token from TheGraph API
     async fn fetch_v3_pools_by_token_from_graph(&self, token_address: &str, limit: i32) -> Result<Vec<PoolData>> {
         let query = r#"
@@ -200,6 +437,7 @@ impl TheGraphClient {
                     feeTier
                     sqrtPrice
                     tick
+                    liquidity
                 }
             }
         "#;
@@ -219,18 +457,83 @@ impl TheGraphClient {
             self.base_url, self.uniswap_v3_subgraph_id
         );
         
-        let mut request_builder = self.client.post(&url).json(&request);
-        
-        // Add Bearer token if available
-        if let Some(ref api_key) = self.api_key {
-            request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
+        let response: GraphQLV3Response = self.post_graphql(&url, &request).await?;
+
+        if let Some(errors) = response.errors {
+            return Err(anyhow!("GraphQL errors: {:?}", errors));
         }
-        
-        let response = request_builder
-            .send()
-            .await?
-            .json::<GraphQLV3Response>()
-            .await?;
+
+        let pools = response
+            .data
+            .ok_or_else(|| anyhow!("No data in response"))?
+            .pools;
+
+        Ok(pools)
+    }
+
+    /// Fetch V3 pools touching any of `token_addresses` in a single query
+    async fn fetch_v3_pools_by_tokens_from_graph(&self, token_addresses: &[String], first: i32) -> Result<Vec<PoolData>> {
+        let query = r#"
+            query GetPoolsByTokens($tokens: [String!]!, $first: Int!) {
+                pools(
+                    first: $first,
+                    orderBy: totalValueLockedUSD,
+                    orderDirection: desc,
+                    where: {
+                        and: [
+                            {
+                                or: [
+                                    { token0_in: $tokens },
+                                    { token1_in: $tokens }
+                                ]
+                            },
+                            { totalValueLockedUSD_gt: "1000" }
+                        ]
+                    }
+                ) {
+                    id
+                    token0 {
+                        id
+                        symbol
+                        name
+                        decimals
+                    }
+                    token1 {
+                        id
+                        symbol
+                        name
+                        decimals
+                    }
+                    volumeUSD
+                    totalValueLockedUSD
+                    txCount
+                    totalValueLockedToken0
+                    totalValueLockedToken1
+                    feeTier
+                    sqrtPrice
+                    tick
+                    liquidity
+                }
+            }
+        "#;
+
+        let tokens: Vec<String> = token_addresses.iter().map(|address| address.to_lowercase()).collect();
+        let variables = serde_json::json!({
+            "tokens": tokens,
+            "first": first
+        });
+
+        let request = GraphQLRequest {
+            query: query.to_string(),
+            variables,
+        };
+
+        let url = format!(
+            "{}/subgraphs/id/{}",
+            self.base_url, self.uniswap_v3_subgraph_id
+        );
+
+        let response: GraphQLV3Response = self.post_graphql(&url, &request).await?;
 
         if let Some(errors) = response.errors {
             return Err(anyhow!("GraphQL errors: {:?}", errors));
@@ -244,24 +547,43 @@ impl TheGraphClient {
         Ok(pools)
     }
 
-    /// Filter out stablecoin pairs
+    /// Whether a Uniswap V2 subgraph is known for this chain
+    pub fn supports_uniswap_v2(&self) -> bool {
+        self.uniswap_v2_subgraph_id.is_some()
+    }
+
+    /// Uniswap V2 subgraph endpoint for this chain
+    fn uniswap_v2_url(&self) -> Result<String> {
+        let subgraph_id = self.uniswap_v2_subgraph_id.as_ref().ok_or_else(|| {
+            anyhow!(
+                "No Uniswap V2 subgraph known for chain {}; set UNISWAP_V2_SUBGRAPH_ID_{}",
+                self.network,
+                self.network.to_uppercase().replace('-', "_")
+            )
+        })?;
+        Ok(format!("{}/subgraphs/id/{}", self.base_url, subgraph_id))
+    }
+
+    /// Record the chain this client queries on a fetched pair
+    fn tag_network(&self, mut pair: PairData) -> PairData {
+        pair.network = self.network.clone();
+        pair
+    }
+
+    /// Filter stablecoin pairs according to the configured `FilterMode`
     fn filter_stablecoins(&self, pairs: Vec<PairData>) -> Vec<PairData> {
-        let stablecoins: HashSet<&str> = [
-            "USDT", "USDC", "DAI", "BUSD", "TUSD", "USDP", "FRAX", "LUSD", "MIM", "USDD", "GUSD",
-            "SUSD", "USDN", "USTC", "HUSD", "DUSD", "OUSD", "USDK", "USDS", "USDX", "USDR", "USDB",
-            "USDE", "USDF", "USDH", "USDJ", "USDL", "USDM", "USDO", "USDQ", "USDT", "USDV", "USDW",
-            "USDY", "USDZ", "VAI", "VUSD", "YUSD", "ZUSD", "CUSD", "EURC", "EURS", "EURT", "JEUR",
-            "AGEUR", "CEUR", "EUROC",
-        ]
-        .iter()
-        .copied()
-        .collect();
+        let is_stable = |symbol: &str| self.stablecoins.contains(&symbol.to_uppercase());
 
         pairs
             .into_iter()
             .filter(|pair| {
-                !stablecoins.contains(pair.token0.symbol.as_str())
-                    && !stablecoins.contains(pair.token1.symbol.as_str())
+                let stable0 = is_stable(&pair.token0.symbol);
+                let stable1 = is_stable(&pair.token1.symbol);
+                match self.filter_mode {
+                    FilterMode::DropBothStable => !stable0 && !stable1,
+                    FilterMode::DropIfBothStable => !(stable0 && stable1),
+                    FilterMode::NoFilter => true,
+                }
             })
             .collect()
     }
@@ -272,7 +594,7 @@ impl TheGraphClient {
         match self.fetch_v3_pools_by_token_from_graph(token_address, limit).await {
             Ok(pools) if !pools.is_empty() => {
                 // Convert V3 pools to PairData format
-                let pairs: Vec<PairData> = pools.into_iter().map(|pool| pool.into()).collect();
+                let pairs: Vec<PairData> = pools.into_iter().map(|pool| self.tag_network(pool.into())).collect();
                 Ok(pairs)
             }
             _ => {
@@ -282,6 +604,113 @@ impl TheGraphClient {
         }
     }
 
+    /// Get V3 pools for a list of tokens, deduplicated by pool id.
+    /// Tokens are queried in batches small enough that every batch can return `limit_per_token`
+    /// pools per token within the subgraph maximum page size of 1000.
+    pub async fn get_v3_pools_by_tokens(&self, token_addresses: &[String], limit_per_token: i32) -> Result<Vec<PairData>> {
+        const MAX_PAGE_SIZE: i32 = 1000;
+        let limit_per_token = limit_per_token.clamp(1, MAX_PAGE_SIZE);
+        let batch_size = (MAX_PAGE_SIZE / limit_per_token) as usize;
+
+        let mut seen = HashSet::new();
+        let mut pairs = Vec::new();
+        for batch in token_addresses.chunks(batch_size) {
+            let first = limit_per_token * batch.len() as i32;
+            let pools = self.fetch_v3_pools_by_tokens_from_graph(batch, first).await?;
+            pairs.extend(
+                pools
+                    .into_iter()
+                    .filter(|pool| seen.insert(pool.id.clone()))
+                    .map(|pool| self.tag_network(pool.into())),
+            );
+        }
+        Ok(pairs)
+    }
+
+    /// Get all pairs by token address, paginating with `id_gt` cursors past the subgraph page size
+    pub async fn get_all_pairs_by_token(&self, token_address: &str) -> Result<Vec<PairData>> {
+        const PAGE_SIZE: i32 = 1000;
+        let mut all_pairs = Vec::new();
+        let mut last_id = String::new();
+
+        loop {
+            let page = self.fetch_pairs_page_by_token(token_address, PAGE_SIZE, &last_id).await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            last_id = last.id.clone();
+            all_pairs.extend(page);
+        }
+
+        // Filter out stablecoins
+        Ok(self.filter_stablecoins(all_pairs))
+    }
+
+    /// Fetch one page of V2 pairs ordered by id, starting after `last_id`
+    async fn fetch_pairs_page_by_token(&self, token_address: &str, first: i32, last_id: &str) -> Result<Vec<PairData>> {
+        let query = r#"
+            query GetPairsPageByToken($token: String!, $first: Int!, $lastId: String!) {
+                pairs(
+                    first: $first,
+                    orderBy: id,
+                    orderDirection: asc,
+                    where: {
+                        or: [
+                            { token0: $token, reserveUSD_gt: "1000", id_gt: $lastId },
+                            { token1: $token, reserveUSD_gt: "1000", id_gt: $lastId }
+                        ]
+                    }
+                ) {
+                    id
+                    token0 {
+                        id
+                        symbol
+                        name
+                        decimals
+                    }
+                    token1 {
+                        id
+                        symbol
+                        name
+                        decimals
+                    }
+                    volumeUSD
+                    reserveUSD
+                    txCount
+                    reserve0
+                    reserve1
+                }
+            }
+        "#;
+
+        let variables = serde_json::json!({
+            "token": token_address.to_lowercase(),
+            "first": first,
+            "lastId": last_id
+        });
+
+        let request = GraphQLRequest {
+            query: query.to_string(),
+            variables,
+        };
+
+        let url = self.uniswap_v2_url()?;
+
+        let response: GraphQLResponse = self.post_graphql(&url, &request).await?;
+
+        if let Some(errors) = response.errors {
+            return Err(anyhow!("GraphQL errors: {:?}", errors));
+        }
+
+        Ok(response
+            .data
+            .ok_or_else(|| anyhow!("No data in response"))?
+            .pairs
+            .into_iter()
+            .map(|pair| self.tag_network(pair))
+            .collect())
+    }
+
     pub async fn get_pairs_by_token(&self, token_address: &str, limit: i32) -> Result<Vec<PairData>> {
         let query = r#"
             query GetPairsByToken($token: String!, $first: Int!) {
@@ -328,23 +757,9 @@ impl TheGraphClient {
             variables,
         };
 
-        let url = format!(
-            "{}/subgraphs/id/{}",
-            self.base_url, self.uniswap_v2_subgraph_id
-        );
-        
-        let mut request_builder = self.client.post(&url).json(&request);
-        
-        // Add Bearer token if available
-        if let Some(ref api_key) = self.api_key {
-            request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
-        }
+        let url = self.uniswap_v2_url()?;
         
-        let response = request_builder
-            .send()
-            .await?
-            .json::<GraphQLResponse>()
-            .await?;
+        let response: GraphQLResponse = self.post_graphql(&url, &request).await?;
 
         if let Some(errors) = response.errors {
             return Err(anyhow!("GraphQL errors: {:?}", errors));
@@ -353,7 +768,10 @@ impl TheGraphClient {
         let pairs = response
             .data
             .ok_or_else(|| anyhow!("No data in response"))?
-            .pairs;
+            .pairs
+            .into_iter()
+            .map(|pair| self.tag_network(pair))
+            .collect();
 
         // Filter out stablecoins
         let filtered_pairs = self.filter_stablecoins(pairs);
@@ -396,6 +814,12 @@ mod tests {
                 fee_tier: "3000".to_string(),
                 sqrt_price: None,
                 tick: None,
+                amp_coefficient: None,
+                balances: Vec::new(),
+                weight0: None,
+                weight1: None,
+                liquidity: None,
+                updated_at: None,
             },
             PairData {
                 id: "0x2".to_string(),
@@ -422,6 +846,12 @@ mod tests {
                 fee_tier: "3000".to_string(),
                 sqrt_price: None,
                 tick: None,
+                amp_coefficient: None,
+                balances: Vec::new(),
+                weight0: None,
+                weight1: None,
+                liquidity: None,
+                updated_at: None,
             },
         ];
 
@@ -429,4 +859,294 @@ mod tests {
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered[0].token1.symbol, "UNI");
     }
+
+    fn mock_client(base_url: String) -> TheGraphClient {
+        TheGraphClient {
+            client: reqwest::Client::new(),
+            api_key: None,
+            base_url,
+            network: DEFAULT_CHAIN.to_string(),
+            uniswap_v2_subgraph_id: Some("v2".to_string()),
+            uniswap_v3_subgraph_id: "v3".to_string(),
+            max_retries: 3,
+            backoff_ms: 1,
+            stablecoins: DEFAULT_STABLECOINS.iter().map(|s| s.to_string()).collect(),
+            filter_mode: FilterMode::default(),
+        }
+    }
+
+    fn symbol_pair(id: &str, symbol0: &str, symbol1: &str) -> PairData {
+        let token = |symbol: &str| TokenInfo {
+            id: format!("0x{}", symbol.to_lowercase()),
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals: "18".to_string(),
+        };
+        PairData {
+            id: id.to_string(),
+            network: "ethereum".to_string(),
+            dex_type: dex_types::UNISWAP_V2.to_string(),
+            protocol_type: protocol_types::AMM_V2.to_string(),
+            token0: token(symbol0),
+            token1: token(symbol1),
+            volume_usd: "0".to_string(),
+            reserve_usd: "0".to_string(),
+            tx_count: "0".to_string(),
+            reserve0: "0".to_string(),
+            reserve1: "0".to_string(),
+            fee_tier: "3000".to_string(),
+            sqrt_price: None,
+            tick: None,
+            amp_coefficient: None,
+            balances: Vec::new(),
+            weight0: None,
+            weight1: None,
+            liquidity: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_stablecoin_filter_modes() {
+        let pairs = vec![
+            symbol_pair("0x1", "WETH", "USDC"),
+            symbol_pair("0x2", "WETH", "UNI"),
+            symbol_pair("0x3", "USDC", "usdt"),
+        ];
+        let ids = |client: &TheGraphClient| -> Vec<String> {
+            client.filter_stablecoins(pairs.clone()).into_iter().map(|p| p.id).collect()
+        };
+
+        let client = mock_client(String::new());
+        assert_eq!(ids(&client), vec!["0x2"]);
+        let client = client.with_filter_mode(FilterMode::DropIfBothStable);
+        assert_eq!(ids(&client), vec!["0x1", "0x2"]);
+        let client = client.with_filter_mode(FilterMode::NoFilter);
+        assert_eq!(ids(&client), vec!["0x1", "0x2", "0x3"]);
+
+        // 自定义稳定币集合：只把 UNI 当作稳定币
+        assert_eq!(FilterMode::parse("Drop_If_Both_Stable"), Some(FilterMode::DropIfBothStable));
+        assert_eq!(FilterMode::parse("unknown"), None);
+
+        let client = TheGraphClient::with_stablecoins(TheGraphClient::parse_stablecoin_symbols(" uni ,"))
+            .with_filter_mode(FilterMode::DropBothStable);
+        assert_eq!(ids(&client), vec!["0x1", "0x3"]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_rate_limit() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/subgraphs/id/v2"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/subgraphs/id/v2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "pairs": [{
+                        "id": "0x1",
+                        "token0": { "id": "0xa", "symbol": "WETH", "name": "Wrapped Ether", "decimals": "18" },
+                        "token1": { "id": "0xd", "symbol": "UNI", "name": "Uniswap", "decimals": "18" },
+                        "volumeUSD": "1000",
+                        "reserveUSD": "5000",
+                        "txCount": "10",
+                        "reserve0": "1",
+                        "reserve1": "2"
+                    }]
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(server.uri());
+        let pairs = client.get_pairs_by_token("0xa", 10).await.unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].id, "0x1");
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_retries() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(server.uri());
+        let err = client.get_pairs_by_token("0xa", 10).await.unwrap_err();
+        assert!(err.to_string().contains("503"));
+    }
+
+    #[tokio::test]
+    async fn test_get_all_pairs_by_token_paginates() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let pair_json = |id: &str, symbol: &str| serde_json::json!({
+            "id": id,
+            "token0": { "id": "0xa", "symbol": "WETH", "name": "Wrapped Ether", "decimals": "18" },
+            "token1": { "id": "0xb", "symbol": symbol, "name": symbol, "decimals": "18" },
+            "volumeUSD": "1000",
+            "reserveUSD": "5000",
+            "txCount": "10",
+            "reserve0": "1",
+            "reserve1": "2"
+        });
+        let page = |pairs: Vec<serde_json::Value>| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": { "pairs": pairs } }))
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "lastId": "" } })))
+            .respond_with(page(vec![pair_json("0x1", "UNI"), pair_json("0x2", "USDC")]))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "lastId": "0x2" } })))
+            .respond_with(page(vec![pair_json("0x3", "LINK")]))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "lastId": "0x3" } })))
+            .respond_with(page(vec![]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(server.uri());
+        let pairs = client.get_all_pairs_by_token("0xa").await.unwrap();
+
+        // 两页结果合并，且稳定币交易对被过滤
+        let ids: Vec<&str> = pairs.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["0x1", "0x3"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_v3_pools_by_tokens_dedups() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let pool_json = |id: &str, token0: &str, token1: &str| serde_json::json!({
+            "id": id,
+            "token0": { "id": token0, "symbol": token0, "name": token0, "decimals": "18" },
+            "token1": { "id": token1, "symbol": token1, "name": token1, "decimals": "18" },
+            "volumeUSD": "1000",
+            "totalValueLockedUSD": "5000",
+            "txCount": "10",
+            "totalValueLockedToken0": "1",
+            "totalValueLockedToken1": "2",
+            "feeTier": "3000",
+            "sqrtPrice": "79228162514264337593543950336",
+            "tick": "0",
+            "liquidity": "1000"
+        });
+
+        // 同时包含两个 token 的池子会被两个过滤条件各匹配一次
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/subgraphs/id/v3"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "tokens": ["0xa", "0xb"], "first": 50 } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "pools": [
+                        pool_json("0x1", "0xa", "0xb"),
+                        pool_json("0x2", "0xa", "0xc"),
+                        pool_json("0x1", "0xa", "0xb"),
+                    ]
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(server.uri());
+        let pairs = client
+            .get_v3_pools_by_tokens(&["0xA".to_string(), "0xB".to_string()], 25)
+            .await
+            .unwrap();
+
+        let ids: Vec<&str> = pairs.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["0x1", "0x2"]);
+        assert!(client.get_v3_pools_by_tokens(&[], 25).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_v3_pools_by_tokens_batches_within_page_size() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let pool_json = |id: &str, token0: &str| serde_json::json!({
+            "id": id,
+            "token0": { "id": token0, "symbol": token0, "name": token0, "decimals": "18" },
+            "token1": { "id": "0xz", "symbol": "Z", "name": "Z", "decimals": "18" },
+            "volumeUSD": "1000",
+            "totalValueLockedUSD": "5000",
+            "txCount": "10",
+            "totalValueLockedToken0": "1",
+            "totalValueLockedToken1": "2",
+            "feeTier": "3000",
+            "sqrtPrice": "79228162514264337593543950336",
+            "tick": "0",
+            "liquidity": "1000"
+        });
+        let pools = |pools: Vec<serde_json::Value>| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": { "pools": pools } }))
+        };
+
+        // 每个 token 400 个池子时，一批最多 2 个 token，第三个 token 单独查询且拿到完整份额
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "tokens": ["0xa", "0xb"], "first": 800 } })))
+            .respond_with(pools(vec![pool_json("0x1", "0xa"), pool_json("0x2", "0xb")]))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "tokens": ["0xc"], "first": 400 } })))
+            .respond_with(pools(vec![pool_json("0x3", "0xc"), pool_json("0x1", "0xa")]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut client = mock_client(server.uri());
+        client.network = "polygon-pos".to_string();
+        let tokens = ["0xA".to_string(), "0xB".to_string(), "0xC".to_string()];
+        let pairs = client.get_v3_pools_by_tokens(&tokens, 400).await.unwrap();
+
+        // 各批结果合并，并跨批去重，且标记为客户端查询的链
+        let ids: Vec<&str> = pairs.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["0x1", "0x2", "0x3"]);
+        assert!(pairs.iter().all(|p| p.network == "polygon-pos"));
+    }
+
+    #[test]
+    fn test_for_chain_selects_subgraph() {
+        let ethereum = TheGraphClient::for_chain(DEFAULT_CHAIN).unwrap();
+        assert_eq!(ethereum.network(), DEFAULT_CHAIN);
+        assert!(ethereum.supports_uniswap_v2());
+
+        let polygon = TheGraphClient::for_chain("polygon-pos").unwrap();
+        assert_eq!(polygon.network(), "polygon-pos");
+        assert_eq!(polygon.uniswap_v3_subgraph_id, "3hCPRGf4z88VC5rsBKU5AA9FBBq5nF3jbKJG7VZCbhjm");
+        // 没有已知 V2 子图的链只能查询 V3
+        assert!(!polygon.supports_uniswap_v2());
+        assert!(polygon.uniswap_v2_url().is_err());
+
+        assert!(TheGraphClient::for_chain("unknown-chain").is_err());
+    }
 }
@@ -1,10 +1,26 @@
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tokio::time::{sleep, Duration};
 
+/// Chain (CoinGecko platform id) used for address lookups and pair updates unless another is given
+pub const DEFAULT_CHAIN: &str = "ethereum";
+
+/// Market data older than this is refetched by `fetch_tokens_since`
+pub const TOKEN_MARKET_DATA_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default delay between market data batches (the free API allows ~30 requests per minute)
+const DEFAULT_BATCH_DELAY_MS: u64 = 2000;
+
+/// Default number of retries for a rate-limited (HTTP 429) batch
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Response header with the number of calls left in the current rate-limit window
+const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
 /// Token information from CoinGecko API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
@@ -19,6 +35,13 @@ pub struct Token {
     pub price_change_percentage_24h: Option<f64>,
 }
 
+impl Token {
+    /// Contract address on the given chain (CoinGecko platform id), if the token is deployed there
+    pub fn address_on(&self, chain: &str) -> Option<&str> {
+        self.platforms.get(chain).and_then(|address| address.as_deref()).filter(|address| !address.is_empty())
+    }
+}
+
 /// Token list with metadata
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenList {
@@ -54,27 +77,77 @@ pub struct TokenManager {
     client: Client,
     api_base_url: String,
     api_key: Option<String>,
+    /// Delay between market data batches, overridable via `COINGECKO_BATCH_DELAY_MS`
+    batch_delay: Duration,
+    /// Retries for a rate-limited batch, overridable via `COINGECKO_MAX_RETRIES`
+    max_retries: u32,
     database: crate::database::Database,
 }
 
 impl TokenManager {
     /// Create a new TokenManager instance
     pub fn new(database: &crate::database::Database) -> Self {
+        Self::with_api_base(database, "https://api.coingecko.com/api/v3")
+    }
+
+    /// Create a TokenManager against a custom CoinGecko-compatible API base URL
+    pub fn with_api_base(database: &crate::database::Database, api_base_url: &str) -> Self {
         let api_key = std::env::var("COINGECKO_API_KEY").ok();
+        let batch_delay_ms = std::env::var("COINGECKO_BATCH_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BATCH_DELAY_MS);
+        let max_retries = std::env::var("COINGECKO_MAX_RETRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_RETRIES);
 
         Self {
             client: Client::new(),
-            api_base_url: "https://api.coingecko.com/api/v3".to_string(),
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
             api_key,
+            batch_delay: Duration::from_millis(batch_delay_ms),
+            max_retries,
             database: database.clone(),
         }
     }
 
 
-    /// Fetch token list from CoinGecko API
-    pub async fn fetch_tokens(&self, limit: Option<usize>) -> Result<TokenList> {
-        log::info!("Fetching token list from CoinGecko API...");
+    /// Incrementally refresh the token list: fetch the full id list, but only request
+    /// market data for ids not in `known_ids`. Fetched tokens are saved to the database
+    /// and merged with the stored rows of the known ids.
+    pub async fn fetch_tokens_since(&self, known_ids: &HashSet<String>) -> Result<TokenList> {
+        log::info!("Fetching token list from CoinGecko API ({} known tokens)...", known_ids.len());
+
+        let missing: Vec<CoinGeckoToken> = self
+            .fetch_ethereum_coins()
+            .await?
+            .into_iter()
+            .filter(|coin| !known_ids.contains(&coin.id))
+            .collect();
+        log::info!("Requesting market data for {} new or stale tokens", missing.len());
 
+        let mut tokens = self.fetch_market_data(missing).await?;
+        if !tokens.is_empty() {
+            self.database.save_tokens(&tokens)?;
+        }
+        tokens.extend(
+            self.database
+                .load_tokens(None)?
+                .into_iter()
+                .filter(|token| known_ids.contains(&token.id)),
+        );
+        Self::sort_by_rank(&mut tokens);
+
+        Ok(TokenList {
+            total_count: tokens.len(),
+            tokens,
+            last_updated: Utc::now(),
+        })
+    }
+
+    /// Fetch the full coin list and keep the coins with an Ethereum contract address
+    async fn fetch_ethereum_coins(&self) -> Result<Vec<CoinGeckoToken>> {
         // First, get the basic coin list with platform information
         let coins_url = format!("{}/coins/list?include_platform=true", self.api_base_url);
         let mut request = self
@@ -100,56 +173,83 @@ impl TokenManager {
         log::info!("Fetched {} coins from CoinGecko", coins.len());
 
         // Filter coins that have Ethereum platform addresses
-        let mut ethereum_tokens: Vec<_> = coins
+        let ethereum_tokens: Vec<_> = coins
             .into_iter()
             .filter(|coin| {
                 coin.platforms.get("ethereum").is_some()
                     && coin.platforms.get("ethereum").unwrap().is_some()
             })
             .collect();
-        
-        // Apply limit if specified
-        if let Some(limit_value) = limit {
-            ethereum_tokens.truncate(limit_value);
-        }
 
         log::info!("Found {} Ethereum tokens", ethereum_tokens.len());
+        Ok(ethereum_tokens)
+    }
 
-        // Get market data for these tokens
-        let mut tokens = self.fetch_market_data(ethereum_tokens).await?;
-
-        // Sort by market cap rank (if available)
+    /// Sort by market cap rank (if available)
+    fn sort_by_rank(tokens: &mut [Token]) {
         tokens.sort_by(|a, b| match (a.market_cap_rank, b.market_cap_rank) {
             (Some(rank_a), Some(rank_b)) => rank_a.cmp(&rank_b),
             (Some(_), None) => std::cmp::Ordering::Less,
             (None, Some(_)) => std::cmp::Ordering::Greater,
             (None, None) => a.symbol.cmp(&b.symbol),
         });
-
-        let token_list = TokenList {
-            total_count: tokens.len(),
-            tokens,
-            last_updated: Utc::now(),
-        };
-
-        log::info!("Successfully fetched {} tokens", token_list.total_count);
-        Ok(token_list)
     }
 
     /// Fetch market data for a list of tokens
     async fn fetch_market_data(&self, ethereum_tokens: Vec<CoinGeckoToken>) -> Result<Vec<Token>> {
         let mut tokens = Vec::new();
         let batch_size = 100;
+        let batch_count = ethereum_tokens.len().div_ceil(batch_size);
 
-        for chunk in ethereum_tokens.chunks(batch_size) {
+        for (batch_index, chunk) in ethereum_tokens.chunks(batch_size).enumerate() {
             let ids: Vec<String> = chunk.iter().map(|t| t.id.clone()).collect();
-            let ids_str = ids.join(",");
+            let (market_data, next_delay) = self.fetch_market_batch(&ids.join(","), batch_size).await?;
+            let market_data = market_data.unwrap_or_default();
+
+            // Merge coin info with market data; tokens without market data keep empty fields
+            for coin in chunk {
+                let market_info = market_data.iter().find(|m| m.id == coin.id);
+
+                let token = Token {
+                    id: coin.id.clone(),
+                    symbol: coin.symbol.clone(),
+                    name: coin.name.clone(),
+                    platforms: coin.platforms.clone(),
+                    market_cap_rank: market_info.and_then(|m| m.market_cap_rank),
+                    current_price: market_info.and_then(|m| m.current_price),
+                    market_cap: market_info.and_then(|m| m.market_cap),
+                    total_volume: market_info.and_then(|m| m.total_volume),
+                    price_change_percentage_24h: market_info
+                        .and_then(|m| m.price_change_percentage_24h),
+                };
+
+                tokens.push(token);
+            }
+
+            // Rate limiting: wait between batches as indicated by the last response
+            if batch_index + 1 < batch_count && !next_delay.is_zero() {
+                sleep(next_delay).await;
+            }
+        }
 
-            let market_url = format!(
-                "{}/coins/markets?vs_currency=usd&ids={}&order=market_cap_desc&per_page={}&page=1&sparkline=false",
-                self.api_base_url, ids_str, batch_size
-            );
+        Ok(tokens)
+    }
 
+    /// Request market data for one batch, retrying HTTP 429 after the `Retry-After` duration.
+    /// Returns `None` when the batch could not be fetched, along with how long to wait before
+    /// the next batch
+    async fn fetch_market_batch(
+        &self,
+        ids: &str,
+        per_page: usize,
+    ) -> Result<(Option<Vec<CoinGeckoMarketData>>, Duration)> {
+        let market_url = format!(
+            "{}/coins/markets?vs_currency=usd&ids={}&order=market_cap_desc&per_page={}&page=1&sparkline=false",
+            self.api_base_url, ids, per_page
+        );
+
+        let mut attempt: u32 = 0;
+        loop {
             let mut market_request = self
                 .client
                 .get(&market_url)
@@ -161,60 +261,52 @@ impl TokenManager {
             }
 
             let market_response = market_request.send().await?;
+            let status = market_response.status();
 
-            if market_response.status().is_success() {
-                let market_data: Vec<CoinGeckoMarketData> = market_response.json().await?;
-
-                // Merge coin info with market data
-                for coin in chunk {
-                    let market_info = market_data.iter().find(|m| m.id == coin.id);
-
-                    let token = Token {
-                        id: coin.id.clone(),
-                        symbol: coin.symbol.clone(),
-                        name: coin.name.clone(),
-                        platforms: coin.platforms.clone(),
-                        market_cap_rank: market_info.and_then(|m| m.market_cap_rank),
-                        current_price: market_info.and_then(|m| m.current_price),
-                        market_cap: market_info.and_then(|m| m.market_cap),
-                        total_volume: market_info.and_then(|m| m.total_volume),
-                        price_change_percentage_24h: market_info
-                            .and_then(|m| m.price_change_percentage_24h),
-                    };
-
-                    tokens.push(token);
-                }
-            } else {
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt < self.max_retries {
+                let delay = Self::retry_after(market_response.headers())
+                    .unwrap_or_else(|| self.batch_delay.saturating_mul(1u32 << attempt.min(16)));
+                attempt += 1;
                 log::warn!(
-                    "Failed to fetch market data for batch: {}",
-                    market_response.status()
+                    "CoinGecko rate limited, retrying batch in {}ms ({}/{})",
+                    delay.as_millis(), attempt, self.max_retries
                 );
-
-                // Add tokens without market data
-                for coin in chunk {
-                    let token = Token {
-                        id: coin.id.clone(),
-                        symbol: coin.symbol.clone(),
-                        name: coin.name.clone(),
-                        platforms: coin.platforms.clone(),
-                        market_cap_rank: None,
-                        current_price: None,
-                        market_cap: None,
-                        total_volume: None,
-                        price_change_percentage_24h: None,
-                    };
-
-                    tokens.push(token);
-                }
+                sleep(delay).await;
+                continue;
             }
 
-            // Rate limiting: wait between requests (30 requests per minute max)
-            if chunk.len() == batch_size {
-                sleep(Duration::from_millis(2000)).await;
+            if !status.is_success() {
+                log::warn!("Failed to fetch market data for batch: {}", status);
+                return Ok((None, self.batch_delay));
             }
+
+            let next_delay = Self::next_batch_delay(market_response.headers(), self.batch_delay);
+            return Ok((Some(market_response.json().await?), next_delay));
         }
+    }
 
-        Ok(tokens)
+    /// Parse the `Retry-After` header (in seconds)
+    fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+        headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+    }
+
+    /// Delay before the next batch: none while the rate-limit headers report remaining calls,
+    /// the `Retry-After` duration (or base delay) once exhausted, and the base delay when the
+    /// response carries no rate-limit headers
+    fn next_batch_delay(headers: &HeaderMap, base_delay: Duration) -> Duration {
+        let remaining = headers
+            .get(RATE_LIMIT_REMAINING_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        match remaining {
+            Some(0) => Self::retry_after(headers).unwrap_or(base_delay),
+            Some(_) => Duration::ZERO,
+            None => base_delay,
+        }
     }
 
     /// Get token list from database
@@ -232,9 +324,9 @@ impl TokenManager {
         Ok(token_list)
     }
 
-    /// 根据地址查找token
-    pub async fn find_token_by_address(&self, address: &str) -> Result<Option<Token>> {
-        self.database.find_token_by_address(address)
+    /// 根据 `chain` 平台（如 `ethereum`、`polygon-pos`）上的合约地址查找token
+    pub async fn find_token_by_address(&self, address: &str, chain: &str) -> Result<Option<Token>> {
+        self.database.find_token_by_address(address, chain)
     }
 
     /// 获取token统计信息
@@ -295,4 +387,116 @@ mod tests {
         assert_eq!(token.id, deserialized.id);
         assert_eq!(token.symbol, deserialized.symbol);
     }
+
+    #[tokio::test]
+    async fn test_fetch_tokens_since_requests_only_missing_ids() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let coin = |id: &str| {
+            serde_json::json!({
+                "id": id,
+                "symbol": id,
+                "name": id,
+                "platforms": { "ethereum": format!("0x{}", id) },
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/coins/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![coin("weth"), coin("usdc"), coin("dai")]))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/coins/markets"))
+            .and(query_param("ids", "usdc,dai"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": "usdc", "symbol": "usdc", "name": "usdc", "market_cap_rank": 5, "current_price": 1.0 },
+                { "id": "dai", "symbol": "dai", "name": "dai", "market_cap_rank": 20, "current_price": 1.0 },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let database = crate::database::Database::new(None).unwrap();
+        let mut weth = Token {
+            id: "weth".to_string(),
+            symbol: "weth".to_string(),
+            name: "weth".to_string(),
+            platforms: HashMap::new(),
+            market_cap_rank: Some(2),
+            current_price: Some(2000.0),
+            market_cap: None,
+            total_volume: None,
+            price_change_percentage_24h: None,
+        };
+        weth.platforms.insert("ethereum".to_string(), Some("0xweth".to_string()));
+        database.save_tokens(&[weth]).unwrap();
+
+        let known_ids = database.load_fresh_token_ids(TOKEN_MARKET_DATA_TTL).unwrap();
+        assert_eq!(known_ids, HashSet::from(["weth".to_string()]));
+
+        let manager = TokenManager::with_api_base(&database, &server.uri());
+        let token_list = manager.fetch_tokens_since(&known_ids).await.unwrap();
+
+        // 已知 token 来自数据库，其余来自本次请求，按市值排名合并
+        let ids: Vec<&str> = token_list.tokens.iter().map(|token| token.id.as_str()).collect();
+        assert_eq!(ids, vec!["weth", "usdc", "dai"]);
+        assert_eq!(token_list.tokens[0].current_price, Some(2000.0));
+        assert_eq!(database.load_tokens(None).unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_market_data_retried_after_429() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/coins/markets"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/coins/markets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": "usdc", "symbol": "usdc", "name": "USD Coin", "market_cap_rank": 5, "current_price": 1.0 },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let database = crate::database::Database::new(None).unwrap();
+        let mut manager = TokenManager::with_api_base(&database, &server.uri());
+        manager.batch_delay = Duration::ZERO;
+        let coins = vec![CoinGeckoToken {
+            id: "usdc".to_string(),
+            symbol: "usdc".to_string(),
+            name: "USD Coin".to_string(),
+            platforms: HashMap::new(),
+        }];
+
+        let started = std::time::Instant::now();
+        let tokens = manager.fetch_market_data(coins).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(tokens[0].market_cap_rank, Some(5));
+        assert_eq!(tokens[0].current_price, Some(1.0));
+    }
+
+    #[test]
+    fn test_next_batch_delay_from_headers() {
+        let base = Duration::from_millis(2000);
+        let mut headers = HeaderMap::new();
+        assert_eq!(TokenManager::next_batch_delay(&headers, base), base);
+
+        headers.insert(RATE_LIMIT_REMAINING_HEADER, "12".parse().unwrap());
+        assert_eq!(TokenManager::next_batch_delay(&headers, base), Duration::ZERO);
+
+        headers.insert(RATE_LIMIT_REMAINING_HEADER, "0".parse().unwrap());
+        assert_eq!(TokenManager::next_batch_delay(&headers, base), base);
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(TokenManager::next_batch_delay(&headers, base), Duration::from_secs(7));
+    }
 }
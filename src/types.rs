@@ -8,6 +8,10 @@ fn display_price(price: &BigDecimal) -> String {
     format!("{:.6}", price)
 }
 
+fn display_risk(risk: &f64) -> String {
+    format!("{:.2}", risk)
+}
+
 fn display_token_pair(token_pair: &TokenPair) -> String {
     format!("{}/{}", token_pair.token_a.symbol, token_pair.token_b.symbol)
 }
@@ -38,6 +42,10 @@ pub struct ArbitrageOpportunity {
     pub timestamp: DateTime<Utc>,
     #[tabled(rename = "置信度")]
     pub confidence_score: f64,
+    /// 被夹子攻击（三明治）的风险，0-1，池子越浅、经过公开内存池的兑换越多越高
+    #[tabled(rename = "夹子风险", display_with = "display_risk")]
+    #[serde(default)]
+    pub sandwich_risk: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -22,22 +22,29 @@ pub fn ether_to_wei(ether: &BigDecimal) -> BigDecimal {
     ether * ether_multiplier
 }
 
+/// 计算 10^exp，使用平方求幂，乘法次数为 O(log exp)
+pub fn pow10(exp: u32) -> BigDecimal {
+    let mut result = BigDecimal::from(1);
+    let mut base = BigDecimal::from(10);
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = &result * &base;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = &base * &base;
+        }
+    }
+    result
+}
+
 /// 根据代币精度调整数量
 pub fn adjust_for_decimals(amount: &BigDecimal, decimals: u8) -> BigDecimal {
-    // 安全地计算 10^decimals，避免整数溢出
-    let divisor = if decimals <= 18 {
-        // 对于常见的小数位数，使用预计算的值
-        BigDecimal::from(10u64.pow(decimals as u32))
-    } else {
-        // 对于极大的小数位数，使用BigDecimal的乘法
-        let ten = BigDecimal::from(10);
-        let mut result = BigDecimal::from(1);
-        for _ in 0..decimals {
-            result = result * &ten;
-        }
-        result
-    };
-    amount / divisor
+    if decimals == 0 {
+        return amount.clone();
+    }
+    amount / pow10(decimals as u32)
 }
 
 /// 计算价格影响
@@ -138,62 +145,29 @@ pub fn format_big_number(number: &BigDecimal, decimals: usize) -> String {
     rounded.to_string()
 }
 
-/// 将带小数点的reserve字符串转换为整数型字符串
-/// 将浮点数转换为uint格式，移除小数点但保留所有数字
-/// 例如: "123.45" -> "12345", "0.001" -> "1", "1000" -> "1000"
-pub fn convert_decimal_to_integer_string(decimal_str: &str) -> Result<String> {
-    if decimal_str.is_empty() {
-        return Ok("0".to_string());
-    }
-    
-    // 移除前导和尾随空格
+/// 将十进制字符串拆分为整数字符串和小数位数（scale），满足 值 = 整数 × 10^-scale
+/// 例如: "123.45" -> ("12345", 2), "0.001" -> ("1", 3), "1000" -> ("1000", 0)
+pub fn convert_decimal_to_scaled_integer(decimal_str: &str) -> Result<(String, i64)> {
     let trimmed = decimal_str.trim();
-    
-    // 如果是"0"或"0.0"等，直接返回"0"
-    if let Ok(val) = trimmed.parse::<f64>() {
-        if val == 0.0 {
-            return Ok("0".to_string());
-        }
-    }
-    
-    // 分割整数部分和小数部分
-    let parts: Vec<&str> = trimmed.split('.').collect();
-    let integer_part = parts[0];
-    let decimal_part = if parts.len() > 1 { parts[1] } else { "" };
-    
-    // 处理整数部分：移除前导零
-    let mut integer_clean = integer_part.trim_start_matches('0');
-    if integer_clean.is_empty() || integer_clean == "-" {
-        integer_clean = "0";
+    if trimmed.is_empty() {
+        return Ok(("0".to_string(), 0));
     }
     
-    // 处理小数部分：移除尾随零
-     let decimal_clean = decimal_part.trim_end_matches('0');
-     
-     // 组合结果
-     let mut result = String::new();
-     
-     // 添加整数部分
-     if integer_clean == "0" && !decimal_clean.is_empty() {
-         // 如果整数部分是0且有小数部分，不添加0
-     } else {
-         result.push_str(integer_clean);
-     }
-     
-     // 添加小数部分（移除前导零）
-     if !decimal_clean.is_empty() {
-         let decimal_no_leading_zeros = decimal_clean.trim_start_matches('0');
-         if !decimal_no_leading_zeros.is_empty() {
-             result.push_str(decimal_no_leading_zeros);
-         }
-     }
-    
-    // 最终检查：如果结果为空，返回"0"
-    if result.is_empty() {
-        return Ok("0".to_string());
+    // 去掉尾随零；整数的 scale 不小于 0，避免 "1000" 变成 ("1", -3)
+    let value = str_to_bigdecimal(trimmed)?.normalized();
+    let value = if value.fractional_digit_count() < 0 { value.with_scale(0) } else { value };
+    let (digits, scale) = value.into_bigint_and_scale();
+    Ok((digits.to_string(), scale))
+}
+
+/// `convert_decimal_to_scaled_integer` 的逆运算：整数字符串 × 10^-scale
+pub fn convert_integer_string_to_decimal(s: &str, scale: i64) -> Result<BigDecimal> {
+    let digits = s.trim();
+    let unsigned = digits.strip_prefix('-').unwrap_or(digits);
+    if unsigned.is_empty() || !unsigned.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow::anyhow!("Invalid integer string: {:?}", s));
     }
-    
-    Ok(result)
+    str_to_bigdecimal(&format!("{}E{}", digits, -scale))
 }
 
 #[cfg(test)]
@@ -207,6 +181,13 @@ mod tests {
         assert_eq!(ether, BigDecimal::from(1));
     }
     
+    #[test]
+    fn test_pow10() {
+        assert_eq!(pow10(0), BigDecimal::from(1));
+        assert_eq!(pow10(18), BigDecimal::from_str("1000000000000000000").unwrap());
+        assert_eq!(pow10(77), BigDecimal::from_str(&format!("1{}", "0".repeat(77))).unwrap());
+    }
+    
     #[test]
     fn test_calculate_amount_out() {
         let amount_in = BigDecimal::from(1000);
@@ -227,6 +208,10 @@ mod tests {
     
     #[test]
     fn test_convert_decimal_to_integer_string() {
+        // 整数部分移除小数点但保留所有数字
+        let convert_decimal_to_integer_string =
+            |s: &str| convert_decimal_to_scaled_integer(s).map(|(digits, _)| digits);
+
         // 测试基本的小数转换 - 保留所有数字
         assert_eq!(convert_decimal_to_integer_string("123.456").unwrap(), "123456");
         assert_eq!(convert_decimal_to_integer_string("123.45").unwrap(), "12345");
@@ -264,4 +249,26 @@ mod tests {
         // 测试带空格
         assert_eq!(convert_decimal_to_integer_string(" 123.45 ").unwrap(), "12345");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_scaled_integer_round_trip() {
+        assert_eq!(convert_decimal_to_scaled_integer("1.05").unwrap(), ("105".to_string(), 2));
+        assert_eq!(convert_decimal_to_scaled_integer("1000").unwrap(), ("1000".to_string(), 0));
+        assert_eq!(convert_integer_string_to_decimal("12345", 2).unwrap(), BigDecimal::from_str("123.45").unwrap());
+        assert!(convert_integer_string_to_decimal("12.5", 1).is_err());
+        
+        // 随机生成的小数经转换再还原后完全相等
+        for _ in 0..500 {
+            let integer: u64 = rand::random::<u64>() >> (rand::random::<u32>() % 64);
+            let fraction_digits = rand::random::<usize>() % 24;
+            let fraction: String = (0..fraction_digits)
+                .map(|_| char::from(b'0' + rand::random::<u8>() % 10))
+                .collect();
+            let original = if fraction.is_empty() { integer.to_string() } else { format!("{}.{}", integer, fraction) };
+            
+            let (digits, scale) = convert_decimal_to_scaled_integer(&original).unwrap();
+            let restored = convert_integer_string_to_decimal(&digits, scale).unwrap();
+            assert_eq!(restored, BigDecimal::from_str(&original).unwrap(), "round trip of {}", original);
+        }
+    }
+}